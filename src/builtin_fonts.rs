@@ -0,0 +1,147 @@
+use printpdf::BuiltinFont;
+
+/// Built-in PDF fonts that can be used for text, by their PDF names. Names are matched ignoring case.
+const FONTS: &[(&str, BuiltinFont)] = &[
+    ("Helvetica", BuiltinFont::Helvetica),
+    ("Helvetica-Bold", BuiltinFont::HelveticaBold),
+    ("Helvetica-Oblique", BuiltinFont::HelveticaOblique),
+    ("Helvetica-BoldOblique", BuiltinFont::HelveticaBoldOblique),
+    ("Times-Roman", BuiltinFont::TimesRoman),
+    ("Times-Bold", BuiltinFont::TimesBold),
+    ("Times-Italic", BuiltinFont::TimesItalic),
+    ("Times-BoldItalic", BuiltinFont::TimesBoldItalic),
+    ("Courier", BuiltinFont::Courier),
+    ("Courier-Bold", BuiltinFont::CourierBold),
+    ("Courier-Oblique", BuiltinFont::CourierOblique),
+    ("Courier-BoldOblique", BuiltinFont::CourierBoldOblique)
+];
+
+pub const DEFAULT_FONT: &str = "Helvetica";
+
+/// First and last character that the built-in fonts can show. printpdf writes text in built-in fonts
+/// byte for byte, so only printable ASCII comes out the same in every viewer.
+const FIRST_CHAR: char = ' ';
+const LAST_CHAR: char = '~';
+
+// Glyph widths in 1/1000 of the font size, from ' ' to '~', taken from the Adobe font metrics files.
+// The oblique faces are as wide as the upright ones, and every Courier glyph is 600 wide.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556,
+    1015, 667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 278, 278, 278, 469, 556,
+    333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, 556, 556,
+    556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584
+];
+
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278,
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611,
+    975, 722, 722, 722, 722, 667, 611, 778, 722, 278, 556, 722, 611, 833, 722, 778,
+    667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, 333, 278, 333, 584, 556,
+    333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556, 278, 889, 611, 611,
+    611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584
+];
+
+const TIMES_ROMAN_WIDTHS: [u16; 95] = [
+    250, 333, 408, 500, 500, 833, 778, 180, 333, 333, 500, 564, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 278, 278, 564, 564, 564, 444,
+    921, 722, 667, 667, 722, 611, 556, 722, 722, 333, 389, 722, 611, 889, 722, 722,
+    556, 722, 667, 556, 611, 722, 722, 944, 722, 722, 611, 333, 278, 333, 469, 500,
+    333, 444, 500, 444, 500, 444, 333, 500, 500, 278, 278, 500, 278, 778, 500, 500,
+    500, 500, 333, 389, 278, 500, 500, 722, 500, 500, 444, 480, 200, 480, 541
+];
+
+const TIMES_BOLD_WIDTHS: [u16; 95] = [
+    250, 333, 555, 500, 500, 1000, 833, 278, 333, 333, 500, 570, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500,
+    930, 722, 667, 722, 722, 667, 611, 778, 778, 389, 500, 778, 667, 944, 722, 778,
+    611, 778, 722, 556, 667, 722, 722, 1000, 722, 722, 667, 333, 278, 333, 581, 500,
+    333, 500, 556, 444, 556, 444, 333, 500, 556, 278, 333, 556, 278, 833, 556, 500,
+    556, 556, 444, 389, 333, 556, 500, 722, 500, 500, 444, 394, 220, 394, 520
+];
+
+const TIMES_ITALIC_WIDTHS: [u16; 95] = [
+    250, 333, 420, 500, 500, 833, 778, 214, 333, 333, 500, 675, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 675, 675, 675, 500,
+    920, 611, 611, 667, 722, 611, 611, 722, 722, 333, 444, 667, 556, 833, 667, 722,
+    611, 722, 611, 500, 556, 722, 611, 833, 611, 556, 556, 389, 278, 389, 422, 500,
+    333, 500, 500, 444, 500, 444, 278, 500, 500, 278, 278, 444, 278, 722, 500, 500,
+    500, 500, 389, 389, 278, 500, 444, 667, 444, 444, 389, 400, 275, 400, 541
+];
+
+const TIMES_BOLD_ITALIC_WIDTHS: [u16; 95] = [
+    250, 389, 555, 500, 500, 833, 778, 278, 333, 333, 500, 570, 250, 333, 250, 278,
+    500, 500, 500, 500, 500, 500, 500, 500, 500, 500, 333, 333, 570, 570, 570, 500,
+    832, 667, 667, 667, 722, 667, 667, 722, 778, 389, 500, 667, 611, 889, 722, 722,
+    611, 722, 667, 556, 611, 722, 667, 889, 667, 611, 611, 333, 278, 333, 570, 500,
+    333, 500, 500, 444, 500, 444, 333, 500, 556, 278, 278, 500, 278, 778, 556, 500,
+    500, 500, 389, 389, 278, 556, 444, 667, 500, 444, 389, 348, 220, 348, 570
+];
+
+const COURIER_WIDTH: u16 = 600;
+
+/// Looks up a built-in font by its PDF name.
+pub fn find(name: &str) -> Option<BuiltinFont> {
+    FONTS.iter()
+        .find(|(font_name, _)| font_name.eq_ignore_ascii_case(name.trim()))
+        .map(|&(_, font)| font)
+}
+
+pub fn names() -> Vec<&'static str> {
+    FONTS.iter().map(|(name, _)| *name).collect()
+}
+
+/// Finds the first character of the text that the built-in fonts can't show.
+pub fn unsupported_char(text: &str) -> Option<char> {
+    text.chars().find(|c| !(FIRST_CHAR..=LAST_CHAR).contains(c))
+}
+
+/// Width of the text in font sizes, e.g. 2.5 is two and a half times the font size.
+/// Characters that the built-in fonts can't show count as nothing.
+pub fn text_width(font: BuiltinFont, text: &str) -> f64 {
+    let widths = match font {
+        BuiltinFont::Helvetica | BuiltinFont::HelveticaOblique => &HELVETICA_WIDTHS,
+        BuiltinFont::HelveticaBold | BuiltinFont::HelveticaBoldOblique => &HELVETICA_BOLD_WIDTHS,
+        BuiltinFont::TimesRoman => &TIMES_ROMAN_WIDTHS,
+        BuiltinFont::TimesBold => &TIMES_BOLD_WIDTHS,
+        BuiltinFont::TimesItalic => &TIMES_ITALIC_WIDTHS,
+        BuiltinFont::TimesBoldItalic => &TIMES_BOLD_ITALIC_WIDTHS,
+        _ => return text.chars().filter(|c| (FIRST_CHAR..=LAST_CHAR).contains(c)).count() as f64
+            * f64::from(COURIER_WIDTH) / 1000.0
+    };
+
+    let total: u32 = text.chars()
+        .filter(|c| (FIRST_CHAR..=LAST_CHAR).contains(c))
+        .map(|c| u32::from(widths[c as usize - FIRST_CHAR as usize]))
+        .sum();
+    f64::from(total) / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn font_names_ignore_case() {
+        assert!(matches!(find("helvetica-bold"), Some(BuiltinFont::HelveticaBold)));
+        assert!(matches!(find("Times-Roman"), Some(BuiltinFont::TimesRoman)));
+        assert!(find("Comic Sans").is_none());
+    }
+
+    #[test]
+    fn text_widths() {
+        // H 722, e 556, l 222, l 222, o 556
+        assert!((text_width(BuiltinFont::Helvetica, "Hello") - 2.278).abs() < 1e-9);
+        assert!((text_width(BuiltinFont::HelveticaOblique, "Hello") - 2.278).abs() < 1e-9);
+        assert!((text_width(BuiltinFont::CourierBold, "Name:") - 3.0).abs() < 1e-9);
+        assert!((text_width(BuiltinFont::TimesRoman, "1 2") - 1.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn only_printable_ascii_is_supported() {
+        assert_eq!(unsupported_char("Name: ____ Date: ____"), None);
+        assert_eq!(unsupported_char("Café"), Some('é'));
+        assert_eq!(unsupported_char("Tab\there"), Some('\t'));
+    }
+}
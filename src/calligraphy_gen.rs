@@ -0,0 +1,254 @@
+use crate::geometry_def::{LineDef, LineStyleDef, PointDef, PaperSize, CalligraphyLineSet};
+use crate::geometry_def::coord::Coord;
+use crate::geometry_def::length::Length;
+use crate::clipping::{Rect, EPSILON};
+use crate::slant_lines_gen;
+use thiserror::Error;
+
+pub fn create_calligraphy_lines(line_set: &CalligraphyLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let nib_width = line_set.nib_width.mm();
+    if nib_width <= 0.0 {
+        return Err(Error::NibWidthIsNotPositive(nib_width));
+    }
+
+    if line_set.x_height <= 0.0 {
+        return Err(Error::XHeightIsNotPositive(line_set.x_height));
+    }
+
+    if line_set.ascender < 0.0 {
+        return Err(Error::AscenderIsNegative(line_set.ascender));
+    }
+
+    if line_set.descender < 0.0 {
+        return Err(Error::DescenderIsNegative(line_set.descender));
+    }
+
+    if line_set.interline_gap < 0.0 {
+        return Err(Error::InterlineGapIsNegative(line_set.interline_gap));
+    }
+
+    let top_margin = line_set.top_margin.mm();
+    if top_margin < 0.0 {
+        return Err(Error::TopMarginIsNegative(top_margin));
+    }
+
+    let bottom_margin = line_set.bottom_margin.mm();
+    if bottom_margin < 0.0 {
+        return Err(Error::BottomMarginIsNegative(bottom_margin));
+    }
+
+    let ascender = line_set.ascender * nib_width;
+    let x_height = line_set.x_height * nib_width;
+    let descender = line_set.descender * nib_width;
+    let row_height = ascender + x_height + descender;
+    let row_pitch = row_height + line_set.interline_gap * nib_width;
+
+    let top = paper_size.height - top_margin;
+    let room = top - bottom_margin;
+    let num_rows = if room + EPSILON < row_height {
+        0
+    } else {
+        ((room - row_height + EPSILON) / row_pitch).floor() as u32 + 1
+    };
+
+    let mut slant_lines: Vec<LineDef> = Vec::new();
+    if let Some(slant) = &line_set.slant {
+        // The rows' x-height bands and the calligraphy set's own bounds take the place of these
+        if slant.bands.is_some() {
+            return Err(Error::SlantBandsAreNotAllowed);
+        }
+        if slant.bounds.is_some() {
+            return Err(Error::SlantBoundsAreNotAllowed);
+        }
+        slant_lines_gen::create_slant_lines(slant, paper_size, &mut slant_lines)?;
+    }
+
+    for row in 0..num_rows {
+        let ascender_y = top - row as f64 * row_pitch;
+        let waist_y = ascender_y - ascender;
+        let baseline_y = waist_y - x_height;
+        let descender_y = baseline_y - descender;
+
+        if let Some(style) = &line_set.ascender_line {
+            result.push(guideline(ascender_y, style));
+        }
+        result.push(guideline(waist_y, &line_set.waist_line));
+        result.push(guideline(baseline_y, &line_set.baseline));
+        if let Some(style) = &line_set.descender_line {
+            result.push(guideline(descender_y, style));
+        }
+
+        let band = Rect { left: 0.0, bottom: baseline_y, right: paper_size.width, top: waist_y };
+        for line in slant_lines.iter() {
+            let clipped = band.clip_segment(
+                line.start.x_coord(paper_size), line.start.y_coord(paper_size),
+                line.end.x_coord(paper_size), line.end.y_coord(paper_size));
+            if let Some((x0, y0, x1, y1)) = clipped {
+                result.push(LineDef {
+                    start: PointDef::from_mm(x0, y0),
+                    end: PointDef::from_mm(x1, y1),
+                    thickness: line.thickness,
+                    color: line.color,
+                    dash_pattern: line.dash_pattern
+                });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn guideline(y: f64, style: &LineStyleDef) -> LineDef {
+    LineDef {
+        start: PointDef { x: Coord::OffZero(Length::ZERO), y: Coord::OffZero(Length::from_mm(y)) },
+        end: PointDef { x: Coord::OffFarEdge(Length::ZERO), y: Coord::OffZero(Length::from_mm(y)) },
+        thickness: style.thickness,
+        color: style.color,
+        dash_pattern: style.dash_pattern
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Nib width of {0} is not a positive number.")]
+    NibWidthIsNotPositive(f64),
+
+    #[error("X-height of {0} nib widths is not a positive number.")]
+    XHeightIsNotPositive(f64),
+
+    #[error("Ascender of {0} nib widths is a negative number.")]
+    AscenderIsNegative(f64),
+
+    #[error("Descender of {0} nib widths is a negative number.")]
+    DescenderIsNegative(f64),
+
+    #[error("Interline gap of {0} nib widths is a negative number.")]
+    InterlineGapIsNegative(f64),
+
+    #[error("Top margin of {0} is a negative number.")]
+    TopMarginIsNegative(f64),
+
+    #[error("Bottom margin of {0} is a negative number.")]
+    BottomMarginIsNegative(f64),
+
+    #[error("Slant lines of a calligraphy line set can't have bands, they are drawn in the x-height band of each row.")]
+    SlantBandsAreNotAllowed,
+
+    #[error("Slant lines of a calligraphy line set can't have bounds, use the bounds of the calligraphy line set.")]
+    SlantBoundsAreNotAllowed,
+
+    #[error("Slant lines generation error.")]
+    SlantLinesGenError(#[from] slant_lines_gen::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::{BandsDef, CmykDef, RectDef, SlantLineSet};
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    const STYLE: LineStyleDef = LineStyleDef { thickness: Length::ZERO, color: CmykDef::BLACK, dash_pattern: None };
+
+    fn italic(slant: Option<SlantLineSet>) -> CalligraphyLineSet {
+        CalligraphyLineSet {
+            nib_width: Length::from_mm(2.0),
+            ascender: 5.0,
+            x_height: 5.0,
+            descender: 5.0,
+            interline_gap: 2.0,
+            top_margin: Length::from_mm(19.4),
+            bottom_margin: Length::from_mm(20.0),
+            baseline: STYLE,
+            waist_line: STYLE,
+            ascender_line: Some(STYLE),
+            descender_line: Some(STYLE),
+            slant,
+            bounds: None
+        }
+    }
+
+    fn slant_lines() -> SlantLineSet {
+        SlantLineSet {
+            x_spacing: Length::from_mm(5.0),
+            slant_angle: 80.0,
+            thickness: Length::ZERO,
+            color: CmykDef::BLACK,
+            bands: None,
+            bounds: None
+        }
+    }
+
+    #[test]
+    fn rows_of_guidelines_between_margins() {
+        // 240mm of room, 30mm rows every 34mm: 7 rows take 6 * 34 + 30 = 234mm
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_calligraphy_lines(&italic(None), paper_size, &mut result).unwrap();
+
+        assert_eq!(result.len(), 7 * 4);
+        let ys: Vec<f64> = result.iter().map(|l| l.start.y_coord(paper_size)).collect();
+        assert_eq!(&ys[0..5], &[260.0, 250.0, 240.0, 230.0, 226.0]);
+        assert!((ys[27] - 26.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn slant_lines_stay_in_the_x_height_band() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_calligraphy_lines(&italic(Some(slant_lines())), paper_size, &mut result).unwrap();
+
+        let slanted: Vec<&LineDef> = result.iter()
+            .filter(|l| l.start.y_coord(paper_size) != l.end.y_coord(paper_size))
+            .collect();
+        assert!(!slanted.is_empty());
+
+        // The first row's x-height band is from 240 to 250
+        for line in slanted.iter().filter(|l| l.start.y_coord(paper_size) > 235.0) {
+            for y in &[line.start.y_coord(paper_size), line.end.y_coord(paper_size)] {
+                assert!((240.0 - 1e-9..=250.0 + 1e-9).contains(y), "Slant line end at y {} is out of the band", y);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<CalligraphyLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|lines, _| lines.nib_width = Length::ZERO, Error::NibWidthIsNotPositive(0.0)),
+            (|lines, _| lines.x_height = 0.0, Error::XHeightIsNotPositive(0.0)),
+            (|lines, _| lines.ascender = -1.0, Error::AscenderIsNegative(-1.0)),
+            (|lines, _| lines.descender = -1.0, Error::DescenderIsNegative(-1.0)),
+            (|lines, _| lines.interline_gap = -1.0, Error::InterlineGapIsNegative(-1.0)),
+            (|lines, _| lines.top_margin = Length::from_mm(-1.0), Error::TopMarginIsNegative(-1.0)),
+            (|lines, _| lines.bottom_margin = Length::from_mm(-1.0), Error::BottomMarginIsNegative(-1.0)),
+            (|lines, _| lines.slant.as_mut().unwrap().bands = Some(BandsDef::FromLineSet { line_set: 1, height: Length::from_mm(5.0) }),
+                Error::SlantBandsAreNotAllowed),
+            (|lines, _| {
+                let mm = |v| Coord::OffZero(Length::from_mm(v));
+                lines.slant.as_mut().unwrap().bounds = Some(RectDef { left: mm(20.0), bottom: mm(20.0), right: mm(190.0), top: mm(250.0) });
+            }, Error::SlantBoundsAreNotAllowed),
+            (|lines, _| lines.slant.as_mut().unwrap().slant_angle = 100.0,
+                Error::SlantLinesGenError(slant_lines_gen::Error::SlantAngleIsOutOfRange { actual: 100.0, min: 45.0, max: 90.0 }))
+        ];
+        assert_rejected(|| italic(Some(slant_lines())), cases,
+            |line_set, paper_size| create_calligraphy_lines(line_set, paper_size, &mut Vec::new()));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::geometry_def::{LineDef, LineStyleDef, PointDef, PaperSize, CharacterGridLineSet};
+use crate::clipping::EPSILON;
+use thiserror::Error;
+
+pub fn create_character_grid(line_set: &CharacterGridLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let cell_size = line_set.cell_size.mm();
+    if cell_size <= 0.0 {
+        return Err(Error::CellSizeIsNotPositive(cell_size));
+    }
+
+    let cell_gap = line_set.cell_gap.map_or(0.0, |gap| gap.mm());
+    if cell_gap < 0.0 {
+        return Err(Error::CellGapIsNegative(cell_gap));
+    }
+
+    let top_margin = line_set.top_margin.mm();
+    if top_margin < 0.0 {
+        return Err(Error::TopMarginIsNegative(top_margin));
+    }
+
+    let bottom_margin = line_set.bottom_margin.mm();
+    if bottom_margin < 0.0 {
+        return Err(Error::BottomMarginIsNegative(bottom_margin));
+    }
+
+    let left_margin = line_set.left_margin.mm();
+    if left_margin < 0.0 {
+        return Err(Error::LeftMarginIsNegative(left_margin));
+    }
+
+    let right_margin = line_set.right_margin.mm();
+    if right_margin < 0.0 {
+        return Err(Error::RightMarginIsNegative(right_margin));
+    }
+
+    let top = paper_size.height - top_margin;
+    let right = paper_size.width - right_margin;
+    let cell_pitch = cell_size + cell_gap;
+    let cells_in = |room: f64| if room + EPSILON < cell_size {
+        0
+    } else {
+        ((room - cell_size + EPSILON) / cell_pitch).floor() as u32 + 1
+    };
+    let num_rows = cells_in(top - bottom_margin);
+    let num_columns = cells_in(right - left_margin);
+    if num_rows == 0 || num_columns == 0 {
+        return Ok(());
+    }
+
+    let cell_left = |column: u32| left_margin + column as f64 * cell_pitch;
+    let cell_top = |row: u32| top - row as f64 * cell_pitch;
+
+    let mut add_line = |x0, y0, x1, y1, style: &LineStyleDef| {
+        result.push(LineDef {
+            start: PointDef::from_mm(x0, y0),
+            end: PointDef::from_mm(x1, y1),
+            thickness: style.thickness,
+            color: style.color,
+            dash_pattern: style.dash_pattern
+        });
+    };
+
+    // Guides go first, so that the borders are drawn over their ends
+    for row in 0..num_rows {
+        let (top, bottom) = (cell_top(row), cell_top(row) - cell_size);
+        let middle_y = top - cell_size / 2.0;
+        for column in 0..num_columns {
+            let (left, right) = (cell_left(column), cell_left(column) + cell_size);
+            let middle_x = left + cell_size / 2.0;
+            add_line(left, middle_y, right, middle_y, &line_set.guides);
+            add_line(middle_x, top, middle_x, bottom, &line_set.guides);
+            if line_set.diagonals {
+                add_line(left, top, right, bottom, &line_set.guides);
+                add_line(left, bottom, right, top, &line_set.guides);
+            }
+        }
+    }
+
+    let grid_right = cell_left(num_columns - 1) + cell_size;
+    let grid_bottom = cell_top(num_rows - 1) - cell_size;
+    if cell_gap < EPSILON {
+        // Neighbouring cells share their borders, so each border line goes across the whole grid once
+        for row in 0..=num_rows {
+            let y = top - row as f64 * cell_size;
+            add_line(left_margin, y, grid_right, y, &line_set.border);
+        }
+        for column in 0..=num_columns {
+            let x = left_margin + column as f64 * cell_size;
+            add_line(x, top, x, grid_bottom, &line_set.border);
+        }
+    } else {
+        for row in 0..num_rows {
+            let (top, bottom) = (cell_top(row), cell_top(row) - cell_size);
+            for column in 0..num_columns {
+                let (left, right) = (cell_left(column), cell_left(column) + cell_size);
+                add_line(left, top, right, top, &line_set.border);
+                add_line(right, top, right, bottom, &line_set.border);
+                add_line(right, bottom, left, bottom, &line_set.border);
+                add_line(left, bottom, left, top, &line_set.border);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Cell size of {0} is not a positive number.")]
+    CellSizeIsNotPositive(f64),
+
+    #[error("Cell gap of {0} is a negative number.")]
+    CellGapIsNegative(f64),
+
+    #[error("Top margin of {0} is a negative number.")]
+    TopMarginIsNegative(f64),
+
+    #[error("Bottom margin of {0} is a negative number.")]
+    BottomMarginIsNegative(f64),
+
+    #[error("Left margin of {0} is a negative number.")]
+    LeftMarginIsNegative(f64),
+
+    #[error("Right margin of {0} is a negative number.")]
+    RightMarginIsNegative(f64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::{CmykDef, DashPatternDef};
+    use crate::geometry_def::length::Length;
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    const BORDER: LineStyleDef = LineStyleDef { thickness: Length::from_mm(0.5), color: CmykDef::BLACK, dash_pattern: None };
+    const GUIDES: LineStyleDef = LineStyleDef {
+        thickness: Length::ZERO,
+        color: CmykDef::BLACK,
+        dash_pattern: Some(DashPatternDef { dash: 2, gap: None })
+    };
+
+    // 20mm cells in 100mm by 50mm of room
+    fn character_grid(cell_gap: Option<f64>, diagonals: bool) -> CharacterGridLineSet {
+        CharacterGridLineSet {
+            cell_size: Length::from_mm(20.0),
+            cell_gap: cell_gap.map(Length::from_mm),
+            top_margin: Length::from_mm(29.4),
+            bottom_margin: Length::from_mm(200.0),
+            left_margin: Length::from_mm(10.0),
+            right_margin: Length::from_mm(105.9),
+            border: BORDER,
+            guides: GUIDES,
+            diagonals,
+            bounds: None
+        }
+    }
+
+    fn assert_point(point: &PointDef, paper_size: &PaperSize, x: f64, y: f64) {
+        let (px, py) = (point.x_coord(paper_size), point.y_coord(paper_size));
+        assert!((px - x).abs() < 1e-9 && (py - y).abs() < 1e-9, "Point ({}, {}), expected ({}, {})", px, py, x, y);
+    }
+
+    fn count_dashed(lines: &[LineDef]) -> usize {
+        lines.iter().filter(|l| l.dash_pattern.is_some()).count()
+    }
+
+    #[test]
+    fn tian_zi_ge_cells_share_borders() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_character_grid(&character_grid(None, false), paper_size, &mut result).unwrap();
+
+        // 5 by 2 cells, each with a dashed cross, and 3 + 6 border lines across the grid
+        assert_eq!(count_dashed(&result), 5 * 2 * 2);
+        assert_eq!(result.len(), 5 * 2 * 2 + 3 + 6);
+        let last = result.last().unwrap();
+        assert_point(&last.start, paper_size, 110.0, 250.0);
+        assert_point(&last.end, paper_size, 110.0, 210.0);
+    }
+
+    #[test]
+    fn mi_zi_ge_cells_with_gaps_have_own_borders() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_character_grid(&character_grid(Some(5.0), true), paper_size, &mut result).unwrap();
+
+        // 4 by 2 cells, each with a cross, two diagonals and a border of its own
+        assert_eq!(count_dashed(&result), 4 * 2 * 4);
+        assert_eq!(result.len(), 4 * 2 * (4 + 4));
+
+        let diagonal = &result[2];
+        assert_point(&diagonal.start, paper_size, 10.0, 250.0);
+        assert_point(&diagonal.end, paper_size, 30.0, 230.0);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<CharacterGridLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|grid, _| grid.cell_size = Length::ZERO, Error::CellSizeIsNotPositive(0.0)),
+            (|grid, _| grid.cell_gap = Some(Length::from_mm(-1.0)), Error::CellGapIsNegative(-1.0)),
+            (|grid, _| grid.top_margin = Length::from_mm(-1.0), Error::TopMarginIsNegative(-1.0)),
+            (|grid, _| grid.bottom_margin = Length::from_mm(-1.0), Error::BottomMarginIsNegative(-1.0)),
+            (|grid, _| grid.left_margin = Length::from_mm(-1.0), Error::LeftMarginIsNegative(-1.0)),
+            (|grid, _| grid.right_margin = Length::from_mm(-1.0), Error::RightMarginIsNegative(-1.0))
+        ];
+        assert_rejected(|| character_grid(None, false), cases,
+            |line_set, paper_size| create_character_grid(line_set, paper_size, &mut Vec::new()));
+    }
+}
@@ -0,0 +1,540 @@
+use crate::geometry_def::{LineDef, ArcDef, PointDef, PolylineDef, PaperSize, RectDef, Shape};
+use crate::outline::{Outline, Point};
+use thiserror::Error;
+
+/// Tolerance for comparing computed positions in mm, and angles and ratios, so that rounding doesn't drop
+/// a line, dot or row that lands exactly on an edge or a margin.
+pub const EPSILON: f64 = 1e-9;
+
+/// A rectangle resolved to millimeters off the lower left corner of the paper.
+#[derive(Debug, Copy, Clone)]
+pub struct Rect {
+    pub left: f64,
+    pub bottom: f64,
+    pub right: f64,
+    pub top: f64
+}
+
+impl Rect {
+    pub fn from_def(rect: &RectDef, paper_size: &PaperSize) -> Result<Rect, Error> {
+        let rect = Rect {
+            left: rect.left_coord(paper_size),
+            bottom: rect.bottom_coord(paper_size),
+            right: rect.right_coord(paper_size),
+            top: rect.top_coord(paper_size)
+        };
+
+        if rect.left >= rect.right {
+            return Err(Error::RectIsTooNarrow { left: rect.left, right: rect.right });
+        }
+
+        if rect.bottom >= rect.top {
+            return Err(Error::RectIsTooShort { bottom: rect.bottom, top: rect.top });
+        }
+
+        Ok(rect)
+    }
+
+    pub fn width(&self) -> f64 {
+        self.right - self.left
+    }
+
+    pub fn height(&self) -> f64 {
+        self.top - self.bottom
+    }
+
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        self.left - EPSILON <= x && x <= self.right + EPSILON
+            && self.bottom - EPSILON <= y && y <= self.top + EPSILON
+    }
+
+    /// Cuts the segment to the part inside the rectangle with the Liang-Barsky algorithm.
+    /// Returns None if nothing of the segment is inside.
+    pub fn clip_segment(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> Option<(f64, f64, f64, f64)> {
+        let dx = x1 - x0;
+        let dy = y1 - y0;
+
+        if dx == 0.0 && dy == 0.0 {
+            return if self.contains(x0, y0) { Some((x0, y0, x1, y1)) } else { None };
+        }
+
+        // Start and end of the visible part, as fractions along the segment
+        let mut t0 = 0.0_f64;
+        let mut t1 = 1.0_f64;
+
+        let edges = [
+            (-dx, x0 - self.left),
+            (dx, self.right - x0),
+            (-dy, y0 - self.bottom),
+            (dy, self.top - y0)
+        ];
+
+        for &(p, q) in edges.iter() {
+            if p == 0.0 {
+                // Parallel to this edge: either entirely inside or entirely outside of it
+                if q < -EPSILON {
+                    return None;
+                }
+            } else {
+                let t = q / p;
+                if p < 0.0 {
+                    t0 = t0.max(t);
+                } else {
+                    t1 = t1.min(t);
+                }
+            }
+        }
+
+        if t0 >= t1 {
+            return None;
+        }
+
+        Some((x0 + t0 * dx, y0 + t0 * dy, x0 + t1 * dx, y0 + t1 * dy))
+    }
+}
+
+/// Cuts every line and arc to the parts that are inside the rectangle and drops the shapes that are entirely outside.
+/// An arc can be cut into several arcs. Dots are kept whole if their center is inside the rectangle,
+/// and so are text and images if their position is.
+/// Other shapes that stick out are flattened and cut into polygons and polylines, see `clip_outline`.
+pub fn clip_shapes(shapes: &mut Vec<Shape>, bounds: &Rect, paper_size: &PaperSize) {
+    let unclipped = std::mem::take(shapes);
+    for mut shape in unclipped {
+        match &mut shape {
+            Shape::Line(line) => {
+                if clip_line(line, bounds, paper_size) {
+                    shapes.push(shape);
+                }
+            },
+            Shape::Dot(dot) => {
+                if bounds.contains(dot.center.x_coord(paper_size), dot.center.y_coord(paper_size)) {
+                    shapes.push(shape);
+                }
+            },
+            Shape::Text(text) => {
+                if bounds.contains(text.position.x_coord(paper_size), text.position.y_coord(paper_size)) {
+                    shapes.push(shape);
+                }
+            },
+            Shape::Image(image) => {
+                if bounds.contains(image.position.x_coord(paper_size), image.position.y_coord(paper_size)) {
+                    shapes.push(shape);
+                }
+            },
+            Shape::Arc(arc) =>
+                shapes.extend(clip_arc(arc, bounds, paper_size).into_iter().map(Shape::Arc)),
+            Shape::Rectangle(_) | Shape::Polyline(_) | Shape::Circle(_) | Shape::Ellipse(_) | Shape::Bezier(_) =>
+                shapes.extend(clip_outline(shape, bounds, paper_size))
+        }
+    }
+}
+
+/// Keeps the shape as it is if it is entirely inside the rectangle. Otherwise the fill becomes a polygon
+/// cut to the rectangle, and the stroke becomes polylines along the parts of the outline that are inside.
+fn clip_outline(shape: Shape, bounds: &Rect, paper_size: &PaperSize) -> Vec<Shape> {
+    let outline = match Outline::from_shape(&shape, paper_size) {
+        Some(outline) => outline,
+        None => return Vec::new()
+    };
+
+    let (left, bottom, right, top) = outline.bounding_box();
+    if bounds.contains(left, bottom) && bounds.contains(right, top) {
+        return vec![shape];
+    }
+    if right < bounds.left || left > bounds.right || top < bounds.bottom || bottom > bounds.top {
+        return Vec::new();
+    }
+
+    let to_point_defs = |points: Vec<Point>| points.into_iter().map(|(x, y)| PointDef::from_mm(x, y)).collect();
+    let points = outline.flatten();
+    let mut result = Vec::new();
+
+    if let Some(fill) = shape.fill() {
+        let polygon = clip_polygon(&points, bounds);
+        if polygon.len() > 2 {
+            result.push(Shape::Polyline(PolylineDef {
+                points: to_point_defs(polygon), closed: true, stroke: None, fill: Some(fill)
+            }));
+        }
+    }
+
+    if let Some(stroke) = shape.stroke() {
+        for (run, closed) in clip_polyline(&points, outline.closed, bounds) {
+            result.push(Shape::Polyline(PolylineDef {
+                points: to_point_defs(run), closed, stroke: Some(stroke), fill: None
+            }));
+        }
+    }
+
+    result
+}
+
+/// Cuts the polygon to the rectangle, one rectangle edge at a time (Sutherland-Hodgman).
+fn clip_polygon(points: &[Point], bounds: &Rect) -> Vec<Point> {
+    // Axis of the edge (0 for X, 1 for Y), its position, and whether the inside is above it
+    let edges = [(0, bounds.left, true), (0, bounds.right, false), (1, bounds.bottom, true), (1, bounds.top, false)];
+
+    let mut polygon = points.to_vec();
+    for &(axis, edge, inside_is_above) in edges.iter() {
+        let coord = |p: Point| if axis == 0 { p.0 } else { p.1 };
+        let inside = |p: Point| if inside_is_above { coord(p) >= edge } else { coord(p) <= edge };
+
+        let input = std::mem::take(&mut polygon);
+        for (i, &current) in input.iter().enumerate() {
+            let previous = input[(i + input.len() - 1) % input.len()];
+            if inside(current) != inside(previous) {
+                let t = (edge - coord(previous)) / (coord(current) - coord(previous));
+                polygon.push((previous.0 + t * (current.0 - previous.0), previous.1 + t * (current.1 - previous.1)));
+            }
+            if inside(current) {
+                polygon.push(current);
+            }
+        }
+    }
+    polygon
+}
+
+/// Cuts the polyline to the rectangle and returns the connected pieces that are inside.
+/// A piece is closed only if the whole closed polyline turned out to be inside.
+fn clip_polyline(points: &[Point], closed: bool, bounds: &Rect) -> Vec<(Vec<Point>, bool)> {
+    let same = |a: Point, b: Point| (a.0 - b.0).abs() <= EPSILON && (a.1 - b.1).abs() <= EPSILON;
+
+    let mut segments: Vec<(Point, Point)> = points.windows(2).map(|w| (w[0], w[1])).collect();
+    if closed && points.len() > 2 {
+        segments.push((points[points.len() - 1], points[0]));
+    }
+
+    let mut runs: Vec<Vec<Point>> = Vec::new();
+    for (p0, p1) in segments {
+        if let Some((x0, y0, x1, y1)) = bounds.clip_segment(p0.0, p0.1, p1.0, p1.1) {
+            match runs.last_mut() {
+                Some(run) if same(run[run.len() - 1], (x0, y0)) => run.push((x1, y1)),
+                _ => runs.push(vec![(x0, y0), (x1, y1)])
+            }
+        }
+    }
+
+    if closed && !runs.is_empty() {
+        let first_start = runs[0][0];
+        let last_end = *runs.last().unwrap().last().unwrap();
+        if same(first_start, last_end) {
+            if runs.len() == 1 {
+                runs[0].pop();
+                return vec![(runs.remove(0), true)];
+            }
+            // The outline was cut somewhere after its start, so the last piece goes on into the first one
+            let last = runs.pop().unwrap();
+            let first = std::mem::replace(&mut runs[0], last);
+            runs[0].extend_from_slice(&first[1..]);
+        }
+    }
+
+    runs.into_iter().map(|run| (run, false)).collect()
+}
+
+/// Cuts the arc where it crosses the rectangle edges and returns the pieces that are inside.
+pub fn clip_arc(arc: &ArcDef, bounds: &Rect, paper_size: &PaperSize) -> Vec<ArcDef> {
+    let cx = arc.center.x_coord(paper_size);
+    let cy = arc.center.y_coord(paper_size);
+    let r = arc.radius.mm();
+    let point_at = |angle: f64| {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        (cx + r * cos, cy + r * sin)
+    };
+
+    // Angles where the whole circle crosses the rectangle edges, moved into the arc's range
+    let mut cuts: Vec<f64> = vec![arc.start_angle, arc.end_angle];
+    let mut add_cuts = |offset: f64, base_angle: f64| {
+        if offset.abs() < r {
+            let half = (offset / r).acos().to_degrees();
+            for angle in [base_angle + half, base_angle - half].iter() {
+                let angle = arc.start_angle + (angle - arc.start_angle).rem_euclid(360.0);
+                if angle < arc.end_angle {
+                    cuts.push(angle);
+                }
+            }
+        }
+    };
+    add_cuts(bounds.right - cx, 0.0);
+    add_cuts(bounds.top - cy, 90.0);
+    add_cuts(cx - bounds.left, 180.0);
+    add_cuts(cy - bounds.bottom, 270.0);
+    cuts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    // Keep the pieces with their middle inside, joining neighbors that were cut at a mere touch
+    let mut result: Vec<ArcDef> = Vec::new();
+    for piece in cuts.windows(2) {
+        let (start, end) = (piece[0], piece[1]);
+        if end - start <= EPSILON {
+            continue;
+        }
+        let (mx, my) = point_at((start + end) / 2.0);
+        if !bounds.contains(mx, my) {
+            continue;
+        }
+        match result.last_mut() {
+            Some(last) if (last.end_angle - start).abs() <= EPSILON => last.end_angle = end,
+            _ => result.push(ArcDef { start_angle: start, end_angle: end, .. arc.clone() })
+        }
+    }
+
+    // A full circle that was cut only where it crosses the zero angle is still one piece
+    if result.len() > 1 && arc.end_angle - arc.start_angle >= 360.0 {
+        let first_start = result[0].start_angle;
+        let last = result.last().unwrap();
+        if (first_start - arc.start_angle).abs() <= EPSILON && (last.end_angle - arc.end_angle).abs() <= EPSILON {
+            let last = result.pop().unwrap();
+            result[0].start_angle = last.start_angle - 360.0;
+        }
+    }
+
+    result
+}
+
+/// Clips one line in place. Returns false if nothing of the line is left.
+fn clip_line(line: &mut LineDef, bounds: &Rect, paper_size: &PaperSize) -> bool {
+    let clipped = bounds.clip_segment(
+        line.start.x_coord(paper_size), line.start.y_coord(paper_size),
+        line.end.x_coord(paper_size), line.end.y_coord(paper_size));
+
+    match clipped {
+        Some((x0, y0, x1, y1)) => {
+            line.start = PointDef::from_mm(x0, y0);
+            line.end = PointDef::from_mm(x1, y1);
+            true
+        },
+        None => false
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Rectangle left edge at {left} is not left of its right edge at {right}.")]
+    RectIsTooNarrow { left: f64, right: f64 },
+
+    #[error("Rectangle bottom edge at {bottom} is not below its top edge at {top}.")]
+    RectIsTooShort { bottom: f64, top: f64 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::{CmykDef, DotDef, EllipseDef, LineStyleDef, RectangleDef};
+    use crate::geometry_def::coord::Coord;
+    use crate::geometry_def::length::Length;
+
+    const BOUNDS: Rect = Rect { left: 20.0, bottom: 10.0, right: 100.0, top: 200.0 };
+
+    fn line(x0: f64, y0: f64, x1: f64, y1: f64) -> Shape {
+        Shape::Line(LineDef {
+            start: PointDef::from_mm(x0, y0),
+            end: PointDef::from_mm(x1, y1),
+            thickness: Length::ZERO,
+            color: CmykDef::BLACK,
+            dash_pattern: None
+        })
+    }
+
+    fn dot(x: f64, y: f64) -> Shape {
+        Shape::Dot(DotDef {
+            center: PointDef::from_mm(x, y),
+            diameter: Length::from_mm(1.0),
+            color: CmykDef::BLACK
+        })
+    }
+
+    fn coords(shape: &Shape) -> (f64, f64, f64, f64) {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let line = match shape {
+            Shape::Line(line) => line,
+            _ => panic!("Expecting a line.")
+        };
+        (line.start.x_coord(paper_size), line.start.y_coord(paper_size),
+            line.end.x_coord(paper_size), line.end.y_coord(paper_size))
+    }
+
+    #[test]
+    fn line_inside_is_unchanged() {
+        let mut lines = vec![line(30.0, 20.0, 90.0, 150.0)];
+        clip_shapes(&mut lines, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(coords(&lines[0]), (30.0, 20.0, 90.0, 150.0));
+    }
+
+    #[test]
+    fn line_across_is_cut_at_the_edges() {
+        let mut lines = vec![line(0.0, 50.0, 215.9, 50.0), line(50.0, 279.4, 50.0, 0.0)];
+        clip_shapes(&mut lines, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(coords(&lines[0]), (20.0, 50.0, 100.0, 50.0));
+        assert_eq!(coords(&lines[1]), (50.0, 200.0, 50.0, 10.0));
+    }
+
+    #[test]
+    fn slanted_line_is_cut_at_the_edges() {
+        let mut lines = vec![line(0.0, 0.0, 120.0, 120.0)];
+        clip_shapes(&mut lines, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(lines.len(), 1);
+        let (x0, y0, x1, y1) = coords(&lines[0]);
+        assert!((x0 - 20.0).abs() < 1e-6 && (y0 - 20.0).abs() < 1e-6);
+        assert!((x1 - 100.0).abs() < 1e-6 && (y1 - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn line_on_the_edge_is_kept() {
+        let mut lines = vec![line(0.0, 200.0, 215.9, 200.0)];
+        clip_shapes(&mut lines, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(lines.len(), 1);
+    }
+
+    #[test]
+    fn line_outside_is_dropped() {
+        let mut lines = vec![line(0.0, 250.0, 215.9, 250.0), line(0.0, 150.0, 50.0, 279.4)];
+        clip_shapes(&mut lines, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert!(lines.is_empty());
+    }
+
+    #[test]
+    fn dots_are_kept_by_center() {
+        let mut shapes = vec![dot(20.0, 10.0), dot(19.0, 50.0), dot(60.0, 60.0)];
+        clip_shapes(&mut shapes, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(shapes.len(), 2);
+    }
+
+    fn circle(x: f64, y: f64, r: f64) -> Shape {
+        Shape::Arc(ArcDef {
+            center: PointDef::from_mm(x, y),
+            radius: Length::from_mm(r),
+            start_angle: 0.0,
+            end_angle: 360.0,
+            thickness: Length::ZERO,
+            color: CmykDef::BLACK,
+            dash_pattern: None
+        })
+    }
+
+    fn arc_angles(shape: &Shape) -> (f64, f64) {
+        match shape {
+            Shape::Arc(arc) => (arc.start_angle, arc.end_angle),
+            _ => panic!("Expecting an arc.")
+        }
+    }
+
+    #[test]
+    fn circle_inside_is_unchanged() {
+        let mut shapes = vec![circle(50.0, 100.0, 20.0)];
+        clip_shapes(&mut shapes, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(shapes.len(), 1);
+        assert_eq!(arc_angles(&shapes[0]), (0.0, 360.0));
+    }
+
+    #[test]
+    fn circle_across_an_edge_is_cut() {
+        // Center on the left edge: only the right half is inside
+        let mut shapes = vec![circle(20.0, 100.0, 30.0)];
+        clip_shapes(&mut shapes, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(shapes.len(), 1);
+        let (start, end) = arc_angles(&shapes[0]);
+        assert!((start + 90.0).abs() < 1e-9, "start angle {}", start);
+        assert!((end - 90.0).abs() < 1e-9, "end angle {}", end);
+    }
+
+    #[test]
+    fn circle_across_a_corner_is_cut_into_pieces() {
+        // Center near the bottom right corner, sticking out through the right and the bottom edges
+        let mut shapes = vec![circle(95.0, 15.0, 10.0)];
+        clip_shapes(&mut shapes, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(shapes.len(), 1);
+        let (start, end) = arc_angles(&shapes[0]);
+        // Cut at acos(5 / 10) = 60 degrees from the right and acos(5 / 10) = 60 degrees from the bottom
+        assert!((start - 60.0).abs() < 1e-9, "start angle {}", start);
+        assert!((end - 210.0).abs() < 1e-9, "end angle {}", end);
+
+        // Wider than the bounds: two separate arcs remain, above and below the middle
+        let mut shapes = vec![circle(60.0, 100.0, 50.0)];
+        clip_shapes(&mut shapes, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(shapes.len(), 2);
+    }
+
+    fn filled_rectangle(left: f64, bottom: f64, right: f64, top: f64) -> Shape {
+        Shape::Rectangle(RectangleDef {
+            left: Coord::OffZero(Length::from_mm(left)),
+            bottom: Coord::OffZero(Length::from_mm(bottom)),
+            right: Coord::OffZero(Length::from_mm(right)),
+            top: Coord::OffZero(Length::from_mm(top)),
+            corner_radius: None,
+            stroke: Some(LineStyleDef { thickness: Length::ZERO, color: CmykDef::BLACK, dash_pattern: None }),
+            fill: Some(CmykDef::BLACK)
+        })
+    }
+
+    fn polyline_points(shape: &Shape) -> (Vec<(f64, f64)>, bool, bool) {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        match shape {
+            Shape::Polyline(polyline) => (
+                polyline.points.iter().map(|p| (p.x_coord(paper_size), p.y_coord(paper_size))).collect(),
+                polyline.closed,
+                polyline.fill.is_some()),
+            _ => panic!("Expecting a polyline.")
+        }
+    }
+
+    #[test]
+    fn rectangle_inside_is_unchanged() {
+        let mut shapes = vec![filled_rectangle(30.0, 20.0, 90.0, 150.0)];
+        clip_shapes(&mut shapes, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(shapes.len(), 1);
+        assert!(matches!(shapes[0], Shape::Rectangle(_)));
+    }
+
+    #[test]
+    fn rectangle_across_an_edge_is_cut_into_fill_and_stroke() {
+        let mut shapes = vec![filled_rectangle(50.0, 150.0, 90.0, 250.0)];
+        clip_shapes(&mut shapes, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(shapes.len(), 2);
+
+        // The fill is the part below the top edge
+        let (points, closed, filled) = polyline_points(&shapes[0]);
+        assert!(closed && filled);
+        assert_eq!(points, vec![(50.0, 200.0), (50.0, 150.0), (90.0, 150.0), (90.0, 200.0)]);
+
+        // The stroke is open where the rectangle went out through the top edge
+        let (points, closed, filled) = polyline_points(&shapes[1]);
+        assert!(!closed && !filled);
+        assert_eq!(points, vec![(50.0, 200.0), (50.0, 150.0), (90.0, 150.0), (90.0, 200.0)]);
+    }
+
+    #[test]
+    fn stroked_ellipse_across_an_edge_is_cut() {
+        let mut shapes = vec![Shape::Ellipse(EllipseDef {
+            center: PointDef::from_mm(20.0, 100.0),
+            x_radius: Length::from_mm(30.0),
+            y_radius: Length::from_mm(10.0),
+            rotation: 0.0,
+            stroke: Some(LineStyleDef { thickness: Length::ZERO, color: CmykDef::BLACK, dash_pattern: None }),
+            fill: None
+        })];
+        clip_shapes(&mut shapes, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert_eq!(shapes.len(), 1);
+        let (points, closed, _) = polyline_points(&shapes[0]);
+        assert!(!closed);
+        assert!(points.iter().all(|&(x, _)| x >= 20.0 - 1e-9));
+        let (first, last) = (points[0], points[points.len() - 1]);
+        assert!((first.0 - 20.0).abs() < 1e-9 && (first.1 - 90.0).abs() < 1e-3, "Starts at {:?}", first);
+        assert!((last.0 - 20.0).abs() < 1e-9 && (last.1 - 110.0).abs() < 1e-3, "Ends at {:?}", last);
+    }
+
+    #[test]
+    fn shape_outside_is_dropped() {
+        let mut shapes = vec![filled_rectangle(120.0, 20.0, 150.0, 50.0)];
+        clip_shapes(&mut shapes, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert!(shapes.is_empty());
+    }
+
+    #[test]
+    fn circle_outside_is_dropped() {
+        let mut shapes = vec![circle(150.0, 100.0, 20.0)];
+        clip_shapes(&mut shapes, &BOUNDS, &PaperSize::LETTER_PORTRAIT);
+        assert!(shapes.is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use crate::parameters;
 use std::path::PathBuf;
 use structopt::StructOpt;
 use thiserror::Error;
@@ -15,7 +16,11 @@ pub struct CmdLineOpts {
 
     /// Number of pages to generate
     #[structopt(short, long, default_value = "1")]
-    pub num_pages: u32
+    pub num_pages: u32,
+
+    /// Value of a parameter from the definition's parameters section, e.g. "spacing=6mm". Can be repeated
+    #[structopt(short, long = "param", value_name = "name=value", number_of_values = 1, parse(try_from_str = parameters::parse_override))]
+    pub params: Vec<(String, String)>
 }
 
 #[derive(Debug)]
@@ -47,6 +52,7 @@ pub fn parse_cmd_line() -> Result<CmdLine, Error> {
 }
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("Failed to parse command line arguments.")]
     ClapError(#[from] clap::Error),
@@ -80,6 +86,16 @@ mod test {
         assert_eq!(opts.num_pages, 1);
     }
 
+    #[test]
+    fn params_parse() {
+        let cmd_line = ["lined_paper_pdf", "in.yml", "out.pdf", "-p", "spacing=6mm", "--param", "color=[0, 0, 0, 1]"];
+        let opts = parse(&cmd_line);
+        assert_eq!(opts.params, vec![
+            ("spacing".to_string(), "6mm".to_string()),
+            ("color".to_string(), "[0, 0, 0, 1]".to_string())
+        ]);
+    }
+
     #[test]
     fn help_message_formatting() {
         let mut app = CmdLineOpts::clap();
@@ -0,0 +1,162 @@
+use crate::geometry_def::{DotDef, PointDef, PaperSize, DotGridLineSet, Shape};
+use crate::clipping::EPSILON;
+use thiserror::Error;
+
+pub fn create_dot_grid(line_set: &DotGridLineSet, paper_size: &PaperSize, result: &mut Vec<Shape>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let x_spacing = line_set.x_spacing.mm();
+    if x_spacing <= 0.0 {
+        return Err(Error::XSpacingIsNotPositive(x_spacing));
+    }
+
+    let y_spacing = line_set.y_spacing.mm();
+    if y_spacing <= 0.0 {
+        return Err(Error::YSpacingIsNotPositive(y_spacing));
+    }
+
+    let top_margin = line_set.top_margin.mm();
+    if top_margin <= 0.0 {
+        return Err(Error::TopMarginIsNotPositive(top_margin));
+    }
+
+    let bottom_margin = line_set.bottom_margin.mm();
+    if bottom_margin <= 0.0 {
+        return Err(Error::BottomMarginIsNotPositive(bottom_margin));
+    }
+
+    let left_margin = line_set.left_margin.mm();
+    if left_margin <= 0.0 {
+        return Err(Error::LeftMarginIsNotPositive(left_margin));
+    }
+
+    let right_margin = line_set.right_margin.mm();
+    if right_margin <= 0.0 {
+        return Err(Error::RightMarginIsNotPositive(right_margin));
+    }
+
+    if line_set.dot_diameter.mm() <= 0.0 {
+        return Err(Error::DotDiameterIsNotPositive(line_set.dot_diameter.mm()));
+    }
+
+    // Rows go down from the top margin and columns go right from the left margin,
+    // same as the horizontal and vertical line sets.
+    let top = paper_size.height - top_margin;
+    let right = paper_size.width - right_margin;
+    let num_rows = ((top - bottom_margin + EPSILON) / y_spacing).floor() as i64 + 1;
+    let num_columns = ((right - left_margin + EPSILON) / x_spacing).floor() as i64 + 1;
+
+    for row in 0..num_rows {
+        let y = top - row as f64 * y_spacing;
+        for column in 0..num_columns {
+            let x = left_margin + column as f64 * x_spacing;
+            result.push(Shape::Dot(DotDef {
+                center: PointDef::from_mm(x, y),
+                diameter: line_set.dot_diameter,
+                color: line_set.color
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("X spacing of {0} is not a positive number.")]
+    XSpacingIsNotPositive(f64),
+
+    #[error("Y spacing of {0} is not a positive number.")]
+    YSpacingIsNotPositive(f64),
+
+    #[error("Top margin of {0} is not a positive number.")]
+    TopMarginIsNotPositive(f64),
+
+    #[error("Bottom margin of {0} is not a positive number.")]
+    BottomMarginIsNotPositive(f64),
+
+    #[error("Left margin of {0} is not a positive number.")]
+    LeftMarginIsNotPositive(f64),
+
+    #[error("Right margin of {0} is not a positive number.")]
+    RightMarginIsNotPositive(f64),
+
+    #[error("Dot diameter of {0} is not a positive number.")]
+    DotDiameterIsNotPositive(f64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::CmykDef;
+    use crate::geometry_def::length::Length;
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    fn dot_grid(spacing: f64, margin: f64) -> DotGridLineSet {
+        DotGridLineSet {
+            x_spacing: Length::from_mm(spacing),
+            y_spacing: Length::from_mm(spacing),
+            top_margin: Length::from_mm(margin),
+            bottom_margin: Length::from_mm(margin),
+            left_margin: Length::from_mm(margin),
+            right_margin: Length::from_mm(margin),
+            dot_diameter: Length::from_mm(0.5),
+            color: CmykDef::BLACK,
+            bounds: None
+        }
+    }
+
+    #[test]
+    fn dots_land_on_grid_intersections() {
+        let paper_size = &PaperSize { width: 100.0, height: 50.0 };
+        let mut result: Vec<Shape> = Vec::new();
+        create_dot_grid(&dot_grid(5.0, 10.0), paper_size, &mut result).unwrap();
+
+        // 10..=90 is 17 columns, 40 down to 10 is 7 rows
+        assert_eq!(result.len(), 17 * 7);
+        if let Shape::Dot(first) = &result[0] {
+            assert_eq!(first.center.x_coord(paper_size), 10.0);
+            assert_eq!(first.center.y_coord(paper_size), 40.0);
+        } else {
+            panic!("The dot grid is supposed to generate dots.");
+        }
+        if let Shape::Dot(last) = result.last().unwrap() {
+            assert_eq!(last.center.x_coord(paper_size), 90.0);
+            assert_eq!(last.center.y_coord(paper_size), 10.0);
+        } else {
+            panic!("The dot grid is supposed to generate dots.");
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<DotGridLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = -1.0, Error::PaperHeightIsNotPositive(-1.0)),
+            (|dots, _| dots.x_spacing = Length::ZERO, Error::XSpacingIsNotPositive(0.0)),
+            (|dots, _| dots.y_spacing = Length::from_mm(-5.0), Error::YSpacingIsNotPositive(-5.0)),
+            (|dots, _| dots.top_margin = Length::ZERO, Error::TopMarginIsNotPositive(0.0)),
+            (|dots, _| dots.bottom_margin = Length::ZERO, Error::BottomMarginIsNotPositive(0.0)),
+            (|dots, _| dots.left_margin = Length::ZERO, Error::LeftMarginIsNotPositive(0.0)),
+            (|dots, _| dots.right_margin = Length::ZERO, Error::RightMarginIsNotPositive(0.0)),
+            (|dots, _| dots.dot_diameter = Length::ZERO, Error::DotDiameterIsNotPositive(0.0))
+        ];
+        assert_rejected(|| dot_grid(5.0, 10.0), cases,
+            |line_set, paper_size| create_dot_grid(line_set, paper_size, &mut Vec::new()));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::font_subset;
+use rusttype::Font;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// A font file with TrueType outlines that gets embedded into the PDF. Only the glyphs that the text uses
+/// are embedded, which needs the "glyf" table, so OpenType fonts with PostScript (CFF) outlines can't be
+/// loaded: use a .ttf version of such a font.
+pub struct EmbeddedFont {
+    pub bytes: Vec<u8>,
+    font: Font<'static>
+}
+
+impl EmbeddedFont {
+    /// Reads the font file, and makes sure that it can be subset and embedded before any page is drawn.
+    pub fn load(path: &Path) -> Result<EmbeddedFont, Error> {
+        let bytes = std::fs::read(path)
+            .map_err(|source| Error::ReadError { path: path.to_path_buf(), source })?;
+        font_subset::check(&bytes)
+            .map_err(|source| Error::UnsupportedFont { path: path.to_path_buf(), source })?;
+        let font = Font::from_bytes(bytes.clone())
+            .map_err(|source| Error::ParseError { path: path.to_path_buf(), source })?;
+        Ok(EmbeddedFont { bytes, font })
+    }
+
+    /// Glyph that shows the character. 0 is the missing glyph.
+    pub fn glyph_id(&self, c: char) -> u16 {
+        self.font.glyph(c).id().0 as u16
+    }
+
+    /// Finds the first character of the text that the font has no glyph for.
+    pub fn missing_char(&self, text: &str) -> Option<char> {
+        text.chars().find(|&c| self.glyph_id(c) == 0)
+    }
+
+    /// Advance width of the glyph in 1/1000 of the font size, the unit of PDF glyph widths.
+    pub fn glyph_width(&self, glyph_id: u16) -> i64 {
+        let advance = self.font.glyph(rusttype::GlyphId(u32::from(glyph_id)))
+            .standalone()
+            .get_data()
+            .map_or(0.0, |data| data.unit_h_metrics.advance_width);
+        (f64::from(advance) * 1000.0 / f64::from(self.font.units_per_em())).round() as i64
+    }
+
+    /// Width of the text in font sizes, the same way as the PDF viewer lays it out.
+    pub fn text_width(&self, text: &str) -> f64 {
+        let total: i64 = text.chars().map(|c| self.glyph_width(self.glyph_id(c))).sum();
+        total as f64 / 1000.0
+    }
+}
+
+/// Loads every font of the `fonts` section. Relative paths are relative to the directory of the geometry definition.
+pub fn load_all(fonts: &BTreeMap<String, PathBuf>, base_dir: &Path) -> Result<BTreeMap<String, EmbeddedFont>, Error> {
+    fonts.iter()
+        .map(|(name, path)| Ok((name.clone(), EmbeddedFont::load(&base_dir.join(path))?)))
+        .collect()
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("Can't read font file {path:?}.")]
+    ReadError { path: PathBuf, source: std::io::Error },
+
+    #[error("Font file {path:?} can't be embedded.")]
+    UnsupportedFont { path: PathBuf, source: font_subset::Error },
+
+    #[error("Font file {path:?} can't be parsed.")]
+    ParseError { path: PathBuf, source: rusttype::Error }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn has_outline(font: &Font, glyph_id: u16) -> bool {
+        font.glyph(rusttype::GlyphId(u32::from(glyph_id)))
+            .scaled(rusttype::Scale::uniform(12.0))
+            .exact_bounding_box()
+            .is_some()
+    }
+
+    #[test]
+    fn example_font_is_subset_to_the_used_glyphs() {
+        let font = EmbeddedFont::load(Path::new("test_line_defs/fonts/DejaVuSerif.ttf")).unwrap();
+        assert_eq!(font.missing_char("Meeting Notes"), None);
+        let used: BTreeSet<u16> = "Notes".chars().map(|c| font.glyph_id(c)).collect();
+        let unused = font.glyph_id('Q');
+
+        let subset = font_subset::subset(&font.bytes, &used).unwrap();
+        assert!(subset.len() < font.bytes.len() / 10);
+        let reparsed = Font::from_bytes(subset).expect("The subset should be a valid TrueType font.");
+        assert!(used.iter().all(|&glyph_id| has_outline(&reparsed, glyph_id)));
+        assert!(!has_outline(&reparsed, unused));
+        assert!(has_outline(&font.font, unused));
+    }
+}
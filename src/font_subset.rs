@@ -0,0 +1,293 @@
+use std::collections::BTreeSet;
+use thiserror::Error;
+
+/// Tables that a PDF viewer needs from an embedded TrueType font. The rest are left out of the subset.
+/// The "cmap" table isn't used with the Identity encoding, but font parsers refuse a font without it.
+const KEPT_TABLES: &[&[u8; 4]] =
+    &[b"cmap", b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"prep"];
+
+/// Tables that have to be there to make a subset.
+const REQUIRED_TABLES: &[&[u8; 4]] = &[b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp"];
+
+/// Flags of a composite glyph component, see the "glyf" table in the OpenType specification.
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+struct Table<'a> {
+    tag: [u8; 4],
+    data: &'a [u8]
+}
+
+/// Checks that the font is a single font with TrueType outlines, which is what `subset` can handle.
+pub fn check(font: &[u8]) -> Result<(), Error> {
+    let tables = read_tables(font)?;
+    for tag in REQUIRED_TABLES.iter() {
+        find_table(&tables, tag)?;
+    }
+    Ok(())
+}
+
+/// Makes a copy of the TrueType font with the outlines of only the given glyphs, the glyphs that they are
+/// made of, and the missing glyph. Glyph ids stay the same, the glyphs that aren't needed are left empty.
+pub fn subset(font: &[u8], glyph_ids: &BTreeSet<u16>) -> Result<Vec<u8>, Error> {
+    let tables = read_tables(font)?;
+    let head = find_table(&tables, b"head")?;
+    let maxp = find_table(&tables, b"maxp")?;
+    let loca = find_table(&tables, b"loca")?;
+    let glyf = find_table(&tables, b"glyf")?;
+
+    let long_offsets = read_u16(head, 50)? != 0;
+    let num_glyphs = read_u16(maxp, 4)? as usize;
+    let offsets = (0..=num_glyphs)
+        .map(|i| if long_offsets { read_u32(loca, 4 * i).map(|o| o as usize) } else { read_u16(loca, 2 * i).map(|o| 2 * o as usize) })
+        .collect::<Result<Vec<usize>, Error>>()?;
+    let glyph = |id: u16| {
+        let (start, end) = (offsets[id as usize], offsets[id as usize + 1]);
+        if start > end || end > glyf.len() {
+            return Err(Error::Malformed);
+        }
+        Ok(&glyf[start..end])
+    };
+
+    let mut kept: BTreeSet<u16> = glyph_ids.iter().copied().filter(|&id| (id as usize) < num_glyphs).collect();
+    kept.insert(0);
+    let mut pending: Vec<u16> = kept.iter().copied().collect();
+    while let Some(id) = pending.pop() {
+        for component in composite_components(glyph(id)?)? {
+            if (component as usize) < num_glyphs && kept.insert(component) {
+                pending.push(component);
+            }
+        }
+    }
+
+    // Short offsets are stored halved, so the glyphs only need to stay 2 byte aligned for them
+    let alignment = if long_offsets { 4 } else { 2 };
+    let mut new_glyf: Vec<u8> = Vec::new();
+    let mut new_loca: Vec<u8> = Vec::new();
+    for id in 0..=num_glyphs {
+        if long_offsets {
+            new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+        } else {
+            new_loca.extend_from_slice(&((new_glyf.len() / 2) as u16).to_be_bytes());
+        }
+        if id < num_glyphs && kept.contains(&(id as u16)) {
+            new_glyf.extend_from_slice(glyph(id as u16)?);
+            new_glyf.resize(new_glyf.len().next_multiple_of(alignment), 0);
+        }
+    }
+
+    let new_tables: Vec<([u8; 4], Vec<u8>)> = tables.iter()
+        .filter(|table| KEPT_TABLES.contains(&&table.tag))
+        .map(|table| match &table.tag {
+            b"glyf" => (table.tag, new_glyf.clone()),
+            b"loca" => (table.tag, new_loca.clone()),
+            _ => (table.tag, table.data.to_vec())
+        })
+        .collect();
+    Ok(write_font(new_tables))
+}
+
+/// Ids of the glyphs that a composite glyph is made of. A simple or empty glyph has none.
+fn composite_components(glyph: &[u8]) -> Result<Vec<u16>, Error> {
+    let mut components = Vec::new();
+    if glyph.is_empty() || (read_u16(glyph, 0)? as i16) >= 0 {
+        return Ok(components);
+    }
+
+    let mut pos = 10;
+    loop {
+        let flags = read_u16(glyph, pos)?;
+        components.push(read_u16(glyph, pos + 2)?);
+        pos += 4;
+        pos += if flags & ARG_1_AND_2_ARE_WORDS != 0 { 4 } else { 2 };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            pos += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            pos += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            pos += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            return Ok(components);
+        }
+    }
+}
+
+fn read_tables(font: &[u8]) -> Result<Vec<Table<'_>>, Error> {
+    match font.get(0..4) {
+        Some([0, 1, 0, 0]) | Some(b"true") => (),
+        Some(b"OTTO") => return Err(Error::PostScriptOutlines),
+        Some(b"ttcf") => return Err(Error::FontCollection),
+        _ => return Err(Error::Malformed)
+    }
+
+    let num_tables = read_u16(font, 4)? as usize;
+    (0..num_tables)
+        .map(|i| {
+            let entry = 12 + 16 * i;
+            let mut tag = [0; 4];
+            tag.copy_from_slice(font.get(entry..entry + 4).ok_or(Error::Malformed)?);
+            let offset = read_u32(font, entry + 8)? as usize;
+            let length = read_u32(font, entry + 12)? as usize;
+            let data = font.get(offset..offset + length).ok_or(Error::Malformed)?;
+            Ok(Table { tag, data })
+        })
+        .collect()
+}
+
+fn find_table<'a>(tables: &[Table<'a>], tag: &[u8; 4]) -> Result<&'a [u8], Error> {
+    tables.iter()
+        .find(|table| &table.tag == tag)
+        .map(|table| table.data)
+        .ok_or_else(|| Error::MissingTable(String::from_utf8_lossy(tag).trim().to_string()))
+}
+
+/// Puts the tables together into a font file, with the table directory sorted by tag and the checksums
+/// that the OpenType specification asks for.
+fn write_font(mut tables: Vec<([u8; 4], Vec<u8>)>) -> Vec<u8> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    let num_tables = tables.len() as u16;
+    let entry_selector = 15 - num_tables.max(1).leading_zeros() as u16;
+    let search_range = 16 << entry_selector;
+
+    let mut font: Vec<u8> = Vec::new();
+    font.extend_from_slice(&[0, 1, 0, 0]);
+    for value in [num_tables, search_range, entry_selector, num_tables * 16 - search_range].iter() {
+        font.extend_from_slice(&value.to_be_bytes());
+    }
+
+    let mut offset = 12 + 16 * tables.len();
+    let mut head_offset = None;
+    for (tag, data) in tables.iter_mut() {
+        if tag == b"head" && data.len() >= 12 {
+            // The font checksum adjustment is filled in at the end, and counts as zero until then
+            data[8..12].copy_from_slice(&[0; 4]);
+            head_offset = Some(offset);
+        }
+        font.extend_from_slice(tag);
+        font.extend_from_slice(&checksum(data).to_be_bytes());
+        font.extend_from_slice(&(offset as u32).to_be_bytes());
+        font.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+
+    for (_, data) in tables.iter() {
+        font.extend_from_slice(data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+
+    if let Some(head_offset) = head_offset {
+        let adjustment = 0xB1B0_AFBA_u32.wrapping_sub(checksum(&font));
+        font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+    }
+    font
+}
+
+/// Sum of the data as big endian 32 bit numbers, padded with zeros to a whole number.
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4)
+        .map(|chunk| {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            u32::from_be_bytes(word)
+        })
+        .fold(0, u32::wrapping_add)
+}
+
+fn read_u16(data: &[u8], pos: usize) -> Result<u16, Error> {
+    let bytes = data.get(pos..pos + 2).ok_or(Error::Malformed)?;
+    Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(data: &[u8], pos: usize) -> Result<u32, Error> {
+    let bytes = data.get(pos..pos + 4).ok_or(Error::Malformed)?;
+    Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("The font has PostScript (CFF) outlines. Only fonts with TrueType outlines (\"glyf\" table) are supported, use a .ttf version of the font.")]
+    PostScriptOutlines,
+
+    #[error("The file is a font collection. Only single fonts can be embedded.")]
+    FontCollection,
+
+    #[error("The font has no \"{0}\" table.")]
+    MissingTable(String),
+
+    #[error("The font file is damaged or is not a TrueType or OpenType font.")]
+    Malformed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A font with 4 glyphs: 0 and 1 are simple, 2 is empty, and 3 is made of glyph 1.
+    fn test_font() -> Vec<u8> {
+        let simple = vec![0, 1, 0, 0, 0, 0, 0, 10, 0, 10, 0, 0, 0, 0, 0, 0];
+        // One component, glyph 1, with byte arguments and no scale
+        let composite = vec![0xFF, 0xFF, 0, 0, 0, 0, 0, 10, 0, 10, 0, 0, 0, 1, 0, 0];
+
+        let mut glyf = Vec::new();
+        let mut loca = Vec::new();
+        for glyph in [simple.clone(), simple, Vec::new(), composite].iter() {
+            loca.extend_from_slice(&((glyf.len() / 2) as u16).to_be_bytes());
+            glyf.extend_from_slice(glyph);
+        }
+        loca.extend_from_slice(&((glyf.len() / 2) as u16).to_be_bytes());
+
+        let mut head = vec![0; 54];
+        head[12..16].copy_from_slice(&0x5F0F_3CF5_u32.to_be_bytes());
+        let mut maxp = vec![0, 1, 0, 0];
+        maxp.extend_from_slice(&4_u16.to_be_bytes());
+
+        write_font(vec![
+            (*b"head", head), (*b"maxp", maxp), (*b"loca", loca), (*b"glyf", glyf),
+            (*b"hhea", vec![0; 36]), (*b"hmtx", vec![0; 16]), (*b"name", vec![1, 2, 3])
+        ])
+    }
+
+    fn glyph_lengths(font: &[u8]) -> Vec<usize> {
+        let tables = read_tables(font).unwrap();
+        let loca = find_table(&tables, b"loca").unwrap();
+        let offsets: Vec<usize> = (0..5).map(|i| 2 * read_u16(loca, 2 * i).unwrap() as usize).collect();
+        offsets.windows(2).map(|w| w[1] - w[0]).collect()
+    }
+
+    #[test]
+    fn unused_glyphs_are_emptied() {
+        let font = test_font();
+        let subset = subset(&font, &[1].iter().copied().collect()).unwrap();
+        // The missing glyph is always kept
+        assert_eq!(glyph_lengths(&subset), vec![16, 16, 0, 0]);
+    }
+
+    #[test]
+    fn composite_glyphs_keep_their_components() {
+        let font = test_font();
+        let subset = subset(&font, &[3].iter().copied().collect()).unwrap();
+        assert_eq!(glyph_lengths(&subset), vec![16, 16, 0, 16]);
+    }
+
+    #[test]
+    fn subset_drops_unneeded_tables_and_has_valid_checksum() {
+        let font = test_font();
+        let subset = subset(&font, &BTreeSet::new()).unwrap();
+        let tables = read_tables(&subset).unwrap();
+        assert!(find_table(&tables, b"name").is_err());
+        assert!(find_table(&tables, b"hmtx").is_ok());
+        assert_eq!(checksum(&subset), 0xB1B0_AFBA);
+    }
+
+    #[test]
+    fn postscript_fonts_and_collections_are_rejected() {
+        assert!(matches!(check(b"OTTO\0\0\0\0\0\0\0\0"), Err(Error::PostScriptOutlines)));
+        assert!(matches!(check(b"ttcf\0\0\0\0\0\0\0\0"), Err(Error::FontCollection)));
+        assert!(matches!(check(b"GIF89a"), Err(Error::Malformed)));
+        assert!(check(&test_font()).is_ok());
+    }
+}
@@ -0,0 +1,226 @@
+use crate::geometry_def::{LineDef, PointDef, PaperSize, GenkouyoushiLineSet, WritingDirection};
+use crate::clipping::EPSILON;
+use thiserror::Error;
+
+pub fn create_genkouyoushi(line_set: &GenkouyoushiLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let cell_size = line_set.cell_size.mm();
+    if cell_size <= 0.0 {
+        return Err(Error::CellSizeIsNotPositive(cell_size));
+    }
+
+    if line_set.columns == 0 {
+        return Err(Error::ColumnsIsZero);
+    }
+
+    if line_set.rows == 0 {
+        return Err(Error::RowsIsZero);
+    }
+
+    let ruby_gap = line_set.ruby_gap.map_or(0.0, |gap| gap.mm());
+    if ruby_gap < 0.0 {
+        return Err(Error::RubyGapIsNegative(ruby_gap));
+    }
+
+    let direction = line_set.writing_direction.unwrap_or(WritingDirection::Vertical);
+
+    // A strip is one column of cells for vertical writing, or one row for horizontal writing
+    let (num_strips, cells_per_strip) = match direction {
+        WritingDirection::Vertical => (line_set.columns, line_set.rows),
+        WritingDirection::Horizontal => (line_set.rows, line_set.columns)
+    };
+
+    let center_fold = match line_set.center_fold {
+        Some(center_fold) => {
+            let center_fold = center_fold.mm();
+            if center_fold <= 0.0 {
+                return Err(Error::CenterFoldIsNotPositive(center_fold));
+            }
+            if num_strips % 2 != 0 {
+                return Err(Error::OddNumberOfStripsWithFold(num_strips));
+            }
+            Some(center_fold)
+        },
+        None => None
+    };
+
+    let (cx, cy) = match &line_set.center {
+        Some(center) => (center.x_coord(paper_size), center.y_coord(paper_size)),
+        None => (paper_size.width / 2.0, paper_size.height / 2.0)
+    };
+
+    // Strip positions are measured across the strips, from the first strip's leading edge
+    let strip_start = |strip: u32| {
+        let start = strip as f64 * (cell_size + ruby_gap);
+        match center_fold {
+            Some(center_fold) if strip >= num_strips / 2 => start - ruby_gap + center_fold,
+            _ => start
+        }
+    };
+    let across_size = strip_start(num_strips - 1) + cell_size;
+    let along_size = cells_per_strip as f64 * cell_size;
+
+    // Vertical strips go left to right, horizontal ones top to bottom, both centered on the grid center
+    let to_page = |across: f64, along: f64| {
+        let (across, along) = (across - across_size / 2.0, along - along_size / 2.0);
+        match direction {
+            WritingDirection::Vertical => PointDef::from_mm(cx + across, cy + along),
+            WritingDirection::Horizontal => PointDef::from_mm(cx + along, cy - across)
+        }
+    };
+
+    let mut add_line = |across0, along0, across1, along1| {
+        result.push(LineDef {
+            start: to_page(across0, along0),
+            end: to_page(across1, along1),
+            thickness: line_set.thickness,
+            color: line_set.color,
+            dash_pattern: line_set.dash_pattern
+        });
+    };
+
+    for strip in 0..num_strips {
+        let start = strip_start(strip);
+        let end = start + cell_size;
+
+        // Without a gap, the leading edge was already drawn as the previous strip's trailing edge
+        let shares_edge = strip > 0 && (start - (strip_start(strip - 1) + cell_size)).abs() < EPSILON;
+        if !shares_edge {
+            add_line(start, 0.0, start, along_size);
+        }
+        add_line(end, 0.0, end, along_size);
+
+        for cell in 0..=cells_per_strip {
+            let along = cell as f64 * cell_size;
+            add_line(start, along, end, along);
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Cell size of {0} is not a positive number.")]
+    CellSizeIsNotPositive(f64),
+
+    #[error("The number of columns must be 1 or more.")]
+    ColumnsIsZero,
+
+    #[error("The number of rows must be 1 or more.")]
+    RowsIsZero,
+
+    #[error("Ruby gap of {0} is a negative number.")]
+    RubyGapIsNegative(f64),
+
+    #[error("Center fold width of {0} is not a positive number.")]
+    CenterFoldIsNotPositive(f64),
+
+    #[error("A center fold splits the grid in two halves, so it needs an even number of columns \
+        (rows for horizontal writing), not {0}.")]
+    OddNumberOfStripsWithFold(u32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::CmykDef;
+    use crate::geometry_def::length::Length;
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    fn genkouyoushi(ruby_gap: f64, center_fold: Option<f64>, direction: WritingDirection) -> GenkouyoushiLineSet {
+        GenkouyoushiLineSet {
+            cell_size: Length::from_mm(10.0),
+            columns: 4,
+            rows: 3,
+            ruby_gap: Some(Length::from_mm(ruby_gap)),
+            writing_direction: Some(direction),
+            center_fold: center_fold.map(Length::from_mm),
+            center: Some(PointDef::from_mm(100.0, 100.0)),
+            thickness: Length::ZERO,
+            color: CmykDef::BLACK,
+            dash_pattern: None,
+            bounds: None
+        }
+    }
+
+    fn vertical_line_xs(lines: &[LineDef], paper_size: &PaperSize) -> Vec<f64> {
+        lines.iter()
+            .filter(|l| l.start.x_coord(paper_size) == l.end.x_coord(paper_size))
+            .map(|l| l.start.x_coord(paper_size))
+            .collect()
+    }
+
+    #[test]
+    fn columns_with_ruby_gaps_and_center_fold() {
+        // Columns of 10mm with 3mm gaps, and a 12mm fold between the 2nd and the 3rd column: 58mm in total
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_genkouyoushi(&genkouyoushi(3.0, Some(12.0), WritingDirection::Vertical), paper_size, &mut result).unwrap();
+
+        assert_eq!(result.len(), 4 * (2 + 4));
+        let xs = vertical_line_xs(&result, paper_size);
+        let expected = [71.0, 81.0, 84.0, 94.0, 106.0, 116.0, 119.0, 129.0];
+        assert_eq!(xs.len(), expected.len());
+        for (x, expected_x) in xs.iter().zip(expected.iter()) {
+            assert!((x - expected_x).abs() < 1e-9, "Column edge at x {}, expected {}", x, expected_x);
+        }
+    }
+
+    #[test]
+    fn shared_edges_are_drawn_once_without_gaps() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_genkouyoushi(&genkouyoushi(0.0, None, WritingDirection::Vertical), paper_size, &mut result).unwrap();
+
+        assert_eq!(vertical_line_xs(&result, paper_size), vec![80.0, 90.0, 100.0, 110.0, 120.0]);
+    }
+
+    #[test]
+    fn horizontal_writing_puts_gaps_between_rows() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_genkouyoushi(&genkouyoushi(3.0, None, WritingDirection::Horizontal), paper_size, &mut result).unwrap();
+
+        // 3 rows of 4 cells
+        assert_eq!(result.len(), 3 * (2 + 5));
+        let mut ys: Vec<f64> = result.iter()
+            .filter(|l| l.start.y_coord(paper_size) == l.end.y_coord(paper_size))
+            .map(|l| l.start.y_coord(paper_size))
+            .collect();
+        ys.dedup();
+        assert_eq!(ys, vec![118.0, 108.0, 105.0, 95.0, 92.0, 82.0]);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<GenkouyoushiLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|grid, _| grid.cell_size = Length::ZERO, Error::CellSizeIsNotPositive(0.0)),
+            (|grid, _| grid.columns = 0, Error::ColumnsIsZero),
+            (|grid, _| grid.rows = 0, Error::RowsIsZero),
+            (|grid, _| grid.ruby_gap = Some(Length::from_mm(-1.0)), Error::RubyGapIsNegative(-1.0)),
+            (|grid, _| grid.center_fold = Some(Length::ZERO), Error::CenterFoldIsNotPositive(0.0)),
+            // The fold goes between strips, so with vertical writing there must be an even number of columns
+            (|grid, _| grid.columns = 5, Error::OddNumberOfStripsWithFold(5))
+        ];
+        assert_rejected(|| genkouyoushi(3.0, Some(12.0), WritingDirection::Vertical), cases,
+            |line_set, paper_size| create_genkouyoushi(line_set, paper_size, &mut Vec::new()));
+    }
+}
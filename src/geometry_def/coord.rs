@@ -1,17 +1,21 @@
 use std::fmt;
 use serde::{self, Deserialize};
 use serde::de::{self, Deserializer, Visitor};
+use super::expr;
+use super::length::{Length, LengthVisitor, Unit};
 
 #[derive(Debug, Copy, Clone)]
 pub enum Coord {
-    OffZero (f64),
-    OffFarEdge (f64)
+    OffZero (Length),
+    OffFarEdge (Length),
+    /// A length off zero plus a fraction of the far edge, from an expression with a percentage such as "50% - 10mm".
+    Proportional (Length, f64)
 }
 
 #[derive(Debug, Deserialize)]
 struct OffFarEdgeCoord {
     #[serde(rename = "off far edge")]
-    off_far_edge: f64
+    off_far_edge: Length
 }
 
 struct CoordVisitor;
@@ -20,12 +24,35 @@ impl<'de> Visitor<'de> for CoordVisitor {
     type Value = Coord;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "A floating point value or a map {{from far edge: <floating point value>}}")
+        write!(formatter, "A length, an expression such as \"width/2 - 10\" or \"50%\", or a map {{off far edge: <length>}}")
     }
 
     fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
     where E: de::Error {
-        Ok(Coord::OffZero(v))
+        LengthVisitor { bare_number_unit: Unit::Mm }.visit_f64(v).map(Coord::OffZero)
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where E: de::Error {
+        LengthVisitor { bare_number_unit: Unit::Mm }.visit_i64(v).map(Coord::OffZero)
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where E: de::Error {
+        LengthVisitor { bare_number_unit: Unit::Mm }.visit_u64(v).map(Coord::OffZero)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where E: de::Error {
+        if expr::is_expression(v) {
+            let (mm, fraction) = expr::evaluate(v, Unit::Mm).map_err(E::custom)?;
+            return Ok(if fraction == 0.0 {
+                Coord::OffZero(Length::from_mm(mm))
+            } else {
+                Coord::Proportional(Length::from_mm(mm), fraction)
+            });
+        }
+        LengthVisitor { bare_number_unit: Unit::Mm }.visit_str(v).map(Coord::OffZero)
     }
 
     fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
@@ -35,6 +62,17 @@ impl<'de> Visitor<'de> for CoordVisitor {
     }
 }
 
+impl Coord {
+    /// Resolves the coordinate to millimeters off zero, given the paper width or height as the far edge.
+    pub fn resolve(self, far_edge: f64) -> f64 {
+        match self {
+            Coord::OffZero(v) => v.mm(),
+            Coord::OffFarEdge(v) => far_edge - v.mm(),
+            Coord::Proportional(v, fraction) => v.mm() + fraction * far_edge
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for Coord {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where D: Deserializer<'de> {
@@ -51,7 +89,7 @@ mod tests {
         let c: Coord = serde_yaml::from_str("4.0")
             .expect("A simple number shoul be successfully parsed as a coordinate.");
         if let Coord::OffZero(x) = c {
-            assert_eq!(x, 4.0);
+            assert_eq!(x.mm(), 4.0);
         } else {
             panic!("A simple number shoul be parsed as a zero-based coordinate, but was not.")
         }
@@ -62,9 +100,44 @@ mod tests {
         let c: Coord = serde_yaml::from_str("{\"off far edge\": 3.0}")
             .expect("A {\"off far edge\": number} map should be successfully parsed as a coordinate.");
         if let Coord::OffFarEdge(x) = c {
-            assert_eq!(x, 3.0);
+            assert_eq!(x.mm(), 3.0);
         } else {
-            panic!("A {\"off far edge\": number} should be parsed as a far-edge-based coordinate, but was not.")
+            panic!("An \"off far edge\" map should be parsed as a far-edge-based coordinate, but was not.")
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn parse_lengths_with_units_as_coords() {
+        let c: Coord = serde_yaml::from_str("1in")
+            .expect("A length with a unit should be successfully parsed as a coordinate.");
+        if let Coord::OffZero(x) = c {
+            assert_eq!(x.mm(), 25.4);
+        } else {
+            panic!("A length with a unit should be parsed as a zero-based coordinate, but was not.")
+        }
+
+        let c: Coord = serde_yaml::from_str("{\"off far edge\": 2cm}")
+            .expect("A {\"off far edge\": length} map should be successfully parsed as a coordinate.");
+        if let Coord::OffFarEdge(x) = c {
+            assert_eq!(x.mm(), 20.0);
+        } else {
+            panic!("An \"off far edge\" map with a unit should be parsed as a far-edge-based coordinate, but was not.")
+        }
+    }
+
+    #[test]
+    fn parse_expressions_as_coords() {
+        let c: Coord = serde_yaml::from_str("\"10mm + 2cm\"")
+            .expect("An expression should be successfully parsed as a coordinate.");
+        assert_eq!(c.resolve(100.0), 30.0);
+
+        let c: Coord = serde_yaml::from_str("\"50% - 10\"")
+            .expect("An expression with a percentage should be successfully parsed as a coordinate.");
+        if let Coord::Proportional(x, fraction) = c {
+            assert_eq!((x.mm(), fraction), (-10.0, 0.5));
+            assert_eq!(c.resolve(300.0), 140.0);
+        } else {
+            panic!("An expression with a percentage should be parsed as a proportional coordinate, but was not.")
+        }
+    }
+}
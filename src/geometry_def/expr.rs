@@ -0,0 +1,350 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use serde_yaml::Value;
+use thiserror::Error;
+use super::length::{self, Length, Unit};
+
+/// What the names in expressions stand for: the paper `width` and `height` in mm, and the parameters.
+/// Serde has no way to pass it down to `Length` and `Coord`, so it's kept per thread while deserializing.
+pub struct Context {
+    pub width: f64,
+    pub height: f64,
+    pub parameters: BTreeMap<String, Value>
+}
+
+thread_local! {
+    static CONTEXT: RefCell<Option<Context>> = const { RefCell::new(None) };
+}
+
+/// Makes the context available to the expressions deserialized by `f`.
+pub fn with_context<T>(context: Context, f: impl FnOnce() -> T) -> T {
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            CONTEXT.with(|c| *c.borrow_mut() = None);
+        }
+    }
+
+    CONTEXT.with(|c| *c.borrow_mut() = Some(context));
+    let _reset = Reset;
+    f()
+}
+
+/// Tells an expression such as "width/2 - 10" from a plain length such as "-1.5 mm" or "2px@300dpi",
+/// which keep their own parsing and error messages.
+pub fn is_expression(s: &str) -> bool {
+    let s = s.trim_start().trim_start_matches(['-', '+']).trim_start();
+    s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '(')
+        || s.contains(['+', '-', '*', '/', '(', ')', '%'])
+}
+
+/// Evaluates the expression to a length in mm plus a fraction of the paper width or height, the part that
+/// comes from percentages. Bare numbers are in `bare_number_unit`.
+pub fn evaluate(s: &str, bare_number_unit: Unit) -> Result<(f64, f64), Error> {
+    let tokens = tokenize(s)?;
+    let mut parser = Parser { expr: s, tokens: &tokens, pos: 0, bare_number_unit };
+    let value = parser.sum()?;
+    if let Some(token) = parser.tokens.get(parser.pos) {
+        return Err(parser.unexpected(token));
+    }
+    Ok(value.as_length(bare_number_unit))
+}
+
+/// Evaluates an expression where a percentage has nothing to be a percentage of.
+pub fn evaluate_length(s: &str, bare_number_unit: Unit) -> Result<Length, Error> {
+    let (mm, fraction) = evaluate(s, bare_number_unit)?;
+    if fraction != 0.0 {
+        return Err(Error::PercentageOutsideCoordinate(s.to_string()));
+    }
+    Ok(Length::from_mm(mm))
+}
+
+#[derive(Debug, Clone)]
+enum Token {
+    Number(f64),
+    Length(Length),
+    Percentage(f64),
+    Name(String),
+    Operator(char)
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = s.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let end = start + length::number_len(&s[start..]);
+            while chars.peek().is_some_and(|&(i, _)| i < end) {
+                chars.next();
+            }
+            let number: f64 = s[start..end].parse().map_err(|_| length::Error::InvalidNumber(s[start..end].to_string()))?;
+
+            // A unit or a percent sign right after a number belongs to it, e.g. "10 mm" or "50%"
+            while chars.peek().is_some_and(|(_, c)| *c == ' ') {
+                chars.next();
+            }
+            match chars.peek() {
+                Some(&(_, '%')) => {
+                    chars.next();
+                    tokens.push(Token::Percentage(number / 100.0));
+                },
+                Some(&(unit_start, c)) if c.is_ascii_alphabetic() => {
+                    let mut unit_end = unit_start;
+                    while let Some(&(i, c)) = chars.peek().filter(|(_, c)| c.is_ascii_alphanumeric() || *c == '@' || *c == '.') {
+                        unit_end = i + c.len_utf8();
+                        chars.next();
+                    }
+                    tokens.push(Token::Length(Length::new(number, s[unit_start..unit_end].parse()?)));
+                },
+                _ => tokens.push(Token::Number(number))
+            }
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(i, c)) = chars.peek().filter(|(_, c)| c.is_ascii_alphanumeric() || *c == '_') {
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(Token::Name(s[start..end].to_string()));
+        } else if "+-*/()".contains(c) {
+            chars.next();
+            tokens.push(Token::Operator(c));
+        } else {
+            return Err(Error::UnexpectedCharacter { expr: s.to_string(), character: c });
+        }
+    }
+    Ok(tokens)
+}
+
+/// A number, or a length made of mm and a fraction of the paper width or height.
+#[derive(Debug, Copy, Clone)]
+enum Quantity {
+    Number(f64),
+    Length { mm: f64, fraction: f64 }
+}
+
+impl Quantity {
+    fn add(self, other: Quantity, sign: f64, bare_number_unit: Unit) -> Quantity {
+        match (self, other) {
+            (Quantity::Number(a), Quantity::Number(b)) => Quantity::Number(a + sign * b),
+            // A bare number next to a length is in the same unit as a bare number on its own
+            (a, b) => {
+                let (a_mm, a_fraction) = a.as_length(bare_number_unit);
+                let (b_mm, b_fraction) = b.as_length(bare_number_unit);
+                Quantity::Length { mm: a_mm + sign * b_mm, fraction: a_fraction + sign * b_fraction }
+            }
+        }
+    }
+
+    fn as_length(self, bare_number_unit: Unit) -> (f64, f64) {
+        match self {
+            Quantity::Number(n) => (bare_number_unit.to_mm(n), 0.0),
+            Quantity::Length { mm, fraction } => (mm, fraction)
+        }
+    }
+
+    fn scale(self, factor: f64) -> Quantity {
+        match self {
+            Quantity::Number(n) => Quantity::Number(n * factor),
+            Quantity::Length { mm, fraction } => Quantity::Length { mm: mm * factor, fraction: fraction * factor }
+        }
+    }
+}
+
+/// Recursive descent over sums of products of signed factors.
+struct Parser<'a> {
+    expr: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+    bare_number_unit: Unit
+}
+
+impl<'a> Parser<'a> {
+    fn sum(&mut self) -> Result<Quantity, Error> {
+        let mut value = self.product()?;
+        while let Some(Token::Operator(op)) = self.tokens.get(self.pos) {
+            let sign = match op {
+                '+' => 1.0,
+                '-' => -1.0,
+                _ => break
+            };
+            self.pos += 1;
+            value = value.add(self.product()?, sign, self.bare_number_unit);
+        }
+        Ok(value)
+    }
+
+    fn product(&mut self) -> Result<Quantity, Error> {
+        let mut value = self.factor()?;
+        while let Some(Token::Operator(op)) = self.tokens.get(self.pos) {
+            let op = *op;
+            if op != '*' && op != '/' {
+                break;
+            }
+            self.pos += 1;
+            let other = self.factor()?;
+            value = match (op, value, other) {
+                ('*', Quantity::Number(a), b) => b.scale(a),
+                ('*', a, Quantity::Number(b)) => a.scale(b),
+                ('*', _, _) => return Err(Error::LengthTimesLength(self.expr.to_string())),
+                (_, a, Quantity::Number(b)) if b != 0.0 => a.scale(1.0 / b),
+                (_, _, Quantity::Number(_)) => return Err(Error::DivisionByZero(self.expr.to_string())),
+                _ => return Err(Error::DivisionByLength(self.expr.to_string()))
+            };
+        }
+        Ok(value)
+    }
+
+    fn factor(&mut self) -> Result<Quantity, Error> {
+        let token = self.tokens.get(self.pos).ok_or_else(|| Error::UnexpectedEnd(self.expr.to_string()))?;
+        self.pos += 1;
+        match token {
+            Token::Number(n) => Ok(Quantity::Number(*n)),
+            Token::Length(l) => Ok(Quantity::Length { mm: l.mm(), fraction: 0.0 }),
+            Token::Percentage(p) => Ok(Quantity::Length { mm: 0.0, fraction: *p }),
+            Token::Name(name) => self.lookup(name),
+            Token::Operator('-') => Ok(self.factor()?.scale(-1.0)),
+            Token::Operator('+') => self.factor(),
+            Token::Operator('(') => {
+                let value = self.sum()?;
+                match self.tokens.get(self.pos) {
+                    Some(Token::Operator(')')) => {
+                        self.pos += 1;
+                        Ok(value)
+                    },
+                    Some(token) => Err(self.unexpected(token)),
+                    None => Err(Error::UnexpectedEnd(self.expr.to_string()))
+                }
+            },
+            token => Err(self.unexpected(token))
+        }
+    }
+
+    /// `width` and `height` are the paper's, anything else is a parameter holding a number or a length.
+    fn lookup(&self, name: &str) -> Result<Quantity, Error> {
+        let unknown = || Error::UnknownName { expr: self.expr.to_string(), name: name.to_string() };
+        CONTEXT.with(|context| {
+            let context = context.borrow();
+            let context = context.as_ref().ok_or_else(unknown)?;
+            match name {
+                "width" => Ok(Quantity::Length { mm: context.width, fraction: 0.0 }),
+                "height" => Ok(Quantity::Length { mm: context.height, fraction: 0.0 }),
+                _ => match context.parameters.get(name).ok_or_else(unknown)? {
+                    Value::Number(n) => n.as_f64().map(Quantity::Number).ok_or_else(unknown),
+                    Value::String(s) => s.parse::<Length>()
+                        .map(|l| Quantity::Length { mm: l.mm(), fraction: 0.0 })
+                        .map_err(|_| Error::ParameterIsNotALength(name.to_string())),
+                    _ => Err(Error::ParameterIsNotALength(name.to_string()))
+                }
+            }
+        })
+    }
+
+    fn unexpected(&self, token: &Token) -> Error {
+        let token = match token {
+            Token::Number(n) => n.to_string(),
+            Token::Length(l) => format!("{}mm", l.mm()),
+            Token::Percentage(p) => format!("{}%", p * 100.0),
+            Token::Name(name) => name.clone(),
+            Token::Operator(op) => op.to_string()
+        };
+        Error::UnexpectedToken { expr: self.expr.to_string(), token }
+    }
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("Expression \"{expr}\" has an unexpected character '{character}'.")]
+    UnexpectedCharacter { expr: String, character: char },
+
+    #[error("Expression \"{expr}\" has an unexpected \"{token}\".")]
+    UnexpectedToken { expr: String, token: String },
+
+    #[error("Expression \"{0}\" ends too early.")]
+    UnexpectedEnd(String),
+
+    #[error("Unknown name \"{name}\" in expression \"{expr}\". Use width, height or a parameter name.")]
+    UnknownName { expr: String, name: String },
+
+    #[error("Parameter \"{0}\" is used in an expression, but is neither a number nor a length.")]
+    ParameterIsNotALength(String),
+
+    #[error("Expression \"{0}\" multiplies a length by a length.")]
+    LengthTimesLength(String),
+
+    #[error("Expression \"{0}\" divides by a length.")]
+    DivisionByLength(String),
+
+    #[error("Expression \"{0}\" divides by zero.")]
+    DivisionByZero(String),
+
+    #[error("Expression \"{0}\" has a percentage, but only coordinates can be a percentage of the paper size.")]
+    PercentageOutsideCoordinate(String),
+
+    #[error(transparent)]
+    LengthError(#[from] length::Error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letter_context() -> Context {
+        let mut parameters = BTreeMap::new();
+        parameters.insert("top_margin".to_string(), Value::String("30mm".to_string()));
+        parameters.insert("columns".to_string(), Value::Number(4.into()));
+        parameters.insert("title".to_string(), Value::String("Notes".to_string()));
+        Context { width: 215.9, height: 279.4, parameters }
+    }
+
+    fn assert_evaluates(expr: &str, expected_mm: f64, expected_fraction: f64) {
+        let (mm, fraction) = with_context(letter_context(), || evaluate(expr, Unit::Mm))
+            .unwrap_or_else(|e| panic!("\"{}\" should be successfully evaluated: {}", expr, e));
+        assert!((mm - expected_mm).abs() < 1e-9, "\"{}\" is {} mm, expected {} mm", expr, mm, expected_mm);
+        assert!((fraction - expected_fraction).abs() < 1e-9, "\"{}\" is {} of the far edge, expected {}", expr, fraction, expected_fraction);
+    }
+
+    #[test]
+    fn evaluate_expressions() {
+        assert_evaluates("width/2 - 10", 97.95, 0.0);
+        assert_evaluates("height - top_margin", 249.4, 0.0);
+        assert_evaluates("(width - 2 * 20mm) / columns", 43.975, 0.0);
+        assert_evaluates("-1in + 2 * 3", -19.4, 0.0);
+        assert_evaluates("50%", 0.0, 0.5);
+        assert_evaluates("100% - 15mm", -15.0, 1.0);
+        assert_evaluates("2.5E-1in + 1e1", 16.35, 0.0);
+    }
+
+    #[test]
+    fn reject_invalid_expressions() {
+        let evaluate = |expr: &str| with_context(letter_context(), || evaluate(expr, Unit::Mm));
+        assert!(matches!(evaluate("width / 2 -"), Err(Error::UnexpectedEnd(_))));
+        assert!(matches!(evaluate("(width / 2"), Err(Error::UnexpectedEnd(_))));
+        assert!(matches!(evaluate("width 2"), Err(Error::UnexpectedToken { .. })));
+        assert!(matches!(evaluate("width # 2"), Err(Error::UnexpectedCharacter { character: '#', .. })));
+        assert!(matches!(evaluate("depth / 2"), Err(Error::UnknownName { name, .. }) if name == "depth"));
+        assert!(matches!(evaluate("title + 2"), Err(Error::ParameterIsNotALength(_))));
+        assert!(matches!(evaluate("width * height"), Err(Error::LengthTimesLength(_))));
+        assert!(matches!(evaluate("10 / (2 - 2)"), Err(Error::DivisionByZero(_))));
+        assert!(matches!(evaluate("100 / width"), Err(Error::DivisionByLength(_))));
+        assert!(matches!(evaluate("5 furlongs + 1"), Err(Error::LengthError(_))));
+    }
+
+    #[test]
+    fn names_are_unknown_outside_of_context() {
+        assert!(matches!(evaluate("width / 2", Unit::Mm), Err(Error::UnknownName { .. })));
+        assert!(matches!(evaluate_length("50% + 1mm", Unit::Mm), Err(Error::PercentageOutsideCoordinate(_))));
+    }
+
+    #[test]
+    fn tell_expressions_from_lengths() {
+        for s in &["width/2 - 10", "height", "-width", "50%", "2 * 5mm", "(1in)"] {
+            assert!(is_expression(s), "\"{}\" should be an expression.", s);
+        }
+        for s in &["5mm", "-1.5 mm", "2px@300dpi", "5", "5 furlongs"] {
+            assert!(!is_expression(s), "\"{}\" should be a plain length.", s);
+        }
+    }
+}
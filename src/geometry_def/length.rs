@@ -0,0 +1,253 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{self, Deserialize};
+use serde::de::{self, Deserializer, Visitor};
+use thiserror::Error;
+use super::expr;
+
+pub const MM_PER_INCH: f64 = 25.4;
+pub const PT_PER_INCH: f64 = 72.0;
+
+/// A length on paper, written in any supported unit: "5mm", "1cm", "0.25in", "12pt", "2px@300dpi",
+/// or as an expression such as "width/2 - 10" (see `expr`).
+/// The unit it was written in is kept, so reading it back in the same unit is exact.
+#[derive(Debug, Copy, Clone)]
+pub struct Length {
+    value: f64,
+    unit: Unit
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Unit {
+    Mm,
+    Cm,
+    In,
+    Pt,
+    Px { dpi: f64 }
+}
+
+impl Unit {
+    pub fn to_mm(self, v: f64) -> f64 {
+        match self {
+            Unit::Mm => v,
+            Unit::Cm => v * 10.0,
+            Unit::In => v * MM_PER_INCH,
+            Unit::Pt => v * MM_PER_INCH / PT_PER_INCH,
+            Unit::Px { dpi } => v * MM_PER_INCH / dpi
+        }
+    }
+}
+
+impl FromStr for Unit {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mm" => Ok(Unit::Mm),
+            "cm" => Ok(Unit::Cm),
+            "in" => Ok(Unit::In),
+            "pt" => Ok(Unit::Pt),
+            _ => {
+                // Pixels only make sense with a resolution, e.g. "px@300dpi"
+                let dpi = s.strip_prefix("px@")
+                    .and_then(|dpi| dpi.strip_suffix("dpi"))
+                    .ok_or_else(|| Error::UnknownUnit(s.to_string()))?;
+                let dpi: f64 = dpi.trim().parse().map_err(|_| Error::InvalidDpi(s.to_string()))?;
+                if dpi <= 0.0 {
+                    return Err(Error::InvalidDpi(s.to_string()));
+                }
+                Ok(Unit::Px { dpi })
+            }
+        }
+    }
+}
+
+impl Length {
+    pub const ZERO: Length = Length::from_mm(0.0);
+
+    pub const fn from_mm(mm: f64) -> Length {
+        Length { value: mm, unit: Unit::Mm }
+    }
+
+    pub const fn new(value: f64, unit: Unit) -> Length {
+        Length { value, unit }
+    }
+
+    pub fn mm(self) -> f64 {
+        self.unit.to_mm(self.value)
+    }
+
+    pub fn pt(self) -> f64 {
+        match self.unit {
+            Unit::Pt => self.value,
+            _ => self.mm() * PT_PER_INCH / MM_PER_INCH
+        }
+    }
+}
+
+impl FromStr for Length {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let (number, unit) = s.split_at(number_len(s));
+        let unit = unit.trim_start();
+        if unit.is_empty() {
+            return Err(Error::MissingUnit(s.to_string()));
+        }
+        let number: f64 = number.parse().map_err(|_| Error::InvalidNumber(s.to_string()))?;
+        Ok(Length::new(number, unit.parse()?))
+    }
+}
+
+/// Length in bytes of the number at the start of the string: an optional sign, digits and decimal points,
+/// and an optional exponent such as "e3" or "E-1". An "e" that isn't followed by digits is left to the unit.
+pub fn number_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let is_digit = |i: usize| bytes.get(i).is_some_and(u8::is_ascii_digit);
+
+    let mut end = if matches!(bytes.first(), Some(b'+') | Some(b'-')) { 1 } else { 0 };
+    while is_digit(end) || bytes.get(end) == Some(&b'.') {
+        end += 1;
+    }
+
+    if matches!(bytes.get(end), Some(b'e') | Some(b'E')) {
+        let mut exponent_end = end + 1;
+        if matches!(bytes.get(exponent_end), Some(b'+') | Some(b'-')) {
+            exponent_end += 1;
+        }
+        if is_digit(exponent_end) {
+            while is_digit(exponent_end) {
+                exponent_end += 1;
+            }
+            end = exponent_end;
+        }
+    }
+    end
+}
+
+/// Deserializes a length where a bare number means millimeters.
+impl<'de> Deserialize<'de> for Length {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        deserializer.deserialize_any(LengthVisitor { bare_number_unit: Unit::Mm })
+    }
+}
+
+/// Deserializes a length where a bare number means points. Used for line thickness.
+pub fn deserialize_pt<'de, D>(deserializer: D) -> Result<Length, D::Error>
+where D: Deserializer<'de> {
+    deserializer.deserialize_any(LengthVisitor { bare_number_unit: Unit::Pt })
+}
+
+pub struct LengthVisitor {
+    pub bare_number_unit: Unit
+}
+
+impl<'de> Visitor<'de> for LengthVisitor {
+    type Value = Length;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "A number or a string with a unit, such as \"5mm\", \"1cm\", \"0.25in\", \"12pt\" or \"2px@300dpi\"")
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E>
+    where E: de::Error {
+        Ok(Length::new(v, self.bare_number_unit))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+    where E: de::Error {
+        self.visit_f64(v as f64)
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+    where E: de::Error {
+        self.visit_f64(v as f64)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where E: de::Error {
+        if expr::is_expression(v) {
+            return expr::evaluate_length(v, self.bare_number_unit).map_err(E::custom);
+        }
+        v.parse().map_err(E::custom)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Length \"{0}\" has no unit. Use one of mm, cm, in, pt or px@<n>dpi.")]
+    MissingUnit(String),
+
+    #[error("Length \"{0}\" does not start with a valid number.")]
+    InvalidNumber(String),
+
+    #[error("Unknown length unit \"{0}\". Use one of mm, cm, in, pt or px@<n>dpi.")]
+    UnknownUnit(String),
+
+    #[error("Pixel unit \"{0}\" must have a positive resolution, e.g. px@300dpi.")]
+    InvalidDpi(String)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_mm(yaml: &str, expected_mm: f64) {
+        let l: Length = serde_yaml::from_str(yaml)
+            .unwrap_or_else(|e| panic!("\"{}\" should be successfully parsed as a length: {}", yaml, e));
+        assert!((l.mm() - expected_mm).abs() < 1e-9, "\"{}\" is {} mm, expected {} mm", yaml, l.mm(), expected_mm);
+    }
+
+    #[test]
+    fn parse_lengths_with_units() {
+        assert_mm("5mm", 5.0);
+        assert_mm("1cm", 10.0);
+        assert_mm("0.25in", 6.35);
+        assert_mm("72pt", 25.4);
+        assert_mm("2px@300dpi", 2.0 * 25.4 / 300.0);
+        assert_mm("\"-1.5 mm\"", -1.5);
+    }
+
+    #[test]
+    fn parse_numbers_with_exponents() {
+        assert_mm("1e3mm", 1000.0);
+        assert_mm("2.5E-1in", 6.35);
+        assert_mm("\"1E+1 cm\"", 100.0);
+        // Not an exponent, so "em" is an unknown unit
+        assert!("1em".parse::<Length>().is_err());
+    }
+
+    #[test]
+    fn parse_bare_number_as_mm() {
+        assert_mm("4.5", 4.5);
+        assert_mm("4", 4.0);
+    }
+
+    #[test]
+    fn parse_bare_number_as_pt_when_requested() {
+        let l = deserialize_pt(serde_yaml::Deserializer::from_str("12"))
+            .expect("A bare number should be successfully parsed as a length in points.");
+        assert!((l.pt() - 12.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn bare_numbers_in_expressions_are_pt_when_requested() {
+        let l = deserialize_pt(serde_yaml::Deserializer::from_str("1pt + 0.5"))
+            .expect("An expression should be successfully parsed as a length in points.");
+        assert!((l.pt() - 1.5).abs() < 1e-9);
+        let l = deserialize_pt(serde_yaml::Deserializer::from_str("(1mm + 2) / 2"))
+            .expect("An expression should be successfully parsed as a length in points.");
+        assert!((l.mm() - (0.5 + 25.4 / 72.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reject_invalid_lengths() {
+        // A quoted number is a string, and strings must always have a unit
+        for yaml in &["5", "5 furlongs", "mm", "2px", "2px@0dpi"] {
+            let res: Result<Length, _> = serde_yaml::from_str(&format!("\"{}\"", yaml));
+            assert!(res.is_err(), "\"{}\" should fail to parse as a length.", yaml);
+        }
+    }
+}
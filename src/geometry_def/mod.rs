@@ -1,109 +1,764 @@
 pub mod coord;
+pub mod expr;
+pub mod length;
+pub mod paper_size;
 
 use serde::{self, Deserialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use coord::Coord;
+use length::Length;
+pub use paper_size::PaperSize;
 
 #[derive(Deserialize, Debug, Copy, Clone)]
 pub struct PointDef {
-    #[serde(rename = "x mm")]
+    #[serde(rename = "x", alias = "x mm")]
     pub x: Coord,
 
-    #[serde(rename = "y mm")]
+    #[serde(rename = "y", alias = "y mm")]
     pub y: Coord
 }
 
+/// A rectangle on the page. Left and right are X coordinates, bottom and top are Y coordinates,
+/// so e.g. `top: {off far edge: 20mm}` is 20mm below the top edge of the paper.
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub struct RectDef {
+    pub left: Coord,
+    pub bottom: Coord,
+    pub right: Coord,
+    pub top: Coord
+}
+
 #[derive(Deserialize, Debug, Copy, Clone)]
 pub struct DashPatternDef {
     pub dash: i64,
     pub gap: Option<i64>
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct LineDef {
     pub start: PointDef,
     pub end: PointDef,
-    #[serde(rename = "thickness pt")]
-    pub thickness: f64,
+    #[serde(rename = "thickness", alias = "thickness pt", deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
     #[serde(rename = "color cmyk")]
     pub color: CmykDef,
     #[serde(rename = "dash pattern")]
     pub dash_pattern: Option<DashPatternDef>
 }
 
-#[derive(Deserialize, Debug)]
-pub struct PaperSize {
-    #[serde(rename = "width mm")]
-    pub width: f64,
+/// A filled round dot.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DotDef {
+    pub center: PointDef,
+    pub diameter: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef
+}
+
+/// A stroked arc of a circle, going counter-clockwise from the start angle to the end angle.
+/// Angles are in degrees off the X axis. A full circle goes from 0 to 360.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ArcDef {
+    pub center: PointDef,
+    pub radius: Length,
+    #[serde(rename = "start angle deg")]
+    pub start_angle: f64,
+    #[serde(rename = "end angle deg")]
+    pub end_angle: f64,
+    #[serde(deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef,
+    #[serde(rename = "dash pattern")]
+    pub dash_pattern: Option<DashPatternDef>
+}
+
+/// A rectangle with optionally rounded corners. The corner radius is cut down to half the shorter side.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RectangleDef {
+    pub left: Coord,
+    pub bottom: Coord,
+    pub right: Coord,
+    pub top: Coord,
+    #[serde(rename = "corner radius")]
+    pub corner_radius: Option<Length>,
+    pub stroke: Option<LineStyleDef>,
+    #[serde(rename = "fill cmyk")]
+    pub fill: Option<CmykDef>
+}
+
+/// Straight segments through the points. A closed polyline is a polygon.
+#[derive(Deserialize, Debug, Clone)]
+pub struct PolylineDef {
+    pub points: Vec<PointDef>,
+    #[serde(default)]
+    pub closed: bool,
+    pub stroke: Option<LineStyleDef>,
+    #[serde(rename = "fill cmyk")]
+    pub fill: Option<CmykDef>
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CircleDef {
+    pub center: PointDef,
+    pub radius: Length,
+    pub stroke: Option<LineStyleDef>,
+    #[serde(rename = "fill cmyk")]
+    pub fill: Option<CmykDef>
+}
+
+/// An ellipse, turned counter-clockwise by the rotation angle.
+#[derive(Deserialize, Debug, Clone)]
+pub struct EllipseDef {
+    pub center: PointDef,
+    #[serde(rename = "x radius")]
+    pub x_radius: Length,
+    #[serde(rename = "y radius")]
+    pub y_radius: Length,
+    #[serde(rename = "rotation deg", default)]
+    pub rotation: f64,
+    pub stroke: Option<LineStyleDef>,
+    #[serde(rename = "fill cmyk")]
+    pub fill: Option<CmykDef>
+}
+
+/// A cubic Bezier curve. A filled curve is closed with a straight line from the end back to the start.
+#[derive(Deserialize, Debug, Clone)]
+pub struct BezierDef {
+    pub start: PointDef,
+    #[serde(rename = "control 1")]
+    pub control_1: PointDef,
+    #[serde(rename = "control 2")]
+    pub control_2: PointDef,
+    pub end: PointDef,
+    pub stroke: Option<LineStyleDef>,
+    #[serde(rename = "fill cmyk")]
+    pub fill: Option<CmykDef>
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum TextAlignment {
+    #[serde(rename = "left")]
+    Left,
+
+    #[serde(rename = "center")]
+    Center,
 
-    #[serde(rename = "height mm")]
-    pub height: f64
+    #[serde(rename = "right")]
+    Right
+}
+
+/// One line of text. The position is on the baseline, at the left end, the middle or the right end
+/// of the text depending on the alignment, and the text is turned counter-clockwise around it.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TextDef {
+    pub text: String,
+    pub position: PointDef,
+    #[serde(rename = "font size", deserialize_with = "length::deserialize_pt")]
+    pub font_size: Length,
+    /// A font from the `fonts` section, or one of the built-in PDF fonts, e.g. "Helvetica-Bold" or "Times-Roman".
+    /// Helvetica if not given.
+    pub font: Option<String>,
+    /// Left if not given.
+    pub alignment: Option<TextAlignment>,
+    #[serde(rename = "rotation deg", default)]
+    pub rotation: f64,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef
+}
+
+/// A PNG or JPEG image. The position is the lower left corner, and the image is turned counter-clockwise
+/// around it. With only the width or only the height given, the other one follows from the aspect ratio
+/// of the image. With both, the image is as large as fits into that size.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ImageDef {
+    /// Relative to the geometry definition file.
+    pub path: PathBuf,
+    pub position: PointDef,
+    pub width: Option<Length>,
+    pub height: Option<Length>,
+    /// From 0 for invisible to 1 for opaque. 1 if not given.
+    pub opacity: Option<f64>,
+    #[serde(rename = "rotation deg", default)]
+    pub rotation: f64
+}
+
+/// Anything that line sets generate and that gets drawn on the page.
+#[derive(Deserialize, Debug, Clone)]
+pub enum Shape {
+    #[serde(rename = "line")]
+    Line (LineDef),
+
+    #[serde(rename = "dot")]
+    Dot (DotDef),
+
+    #[serde(rename = "arc")]
+    Arc (ArcDef),
+
+    #[serde(rename = "rectangle")]
+    Rectangle (RectangleDef),
+
+    #[serde(rename = "polyline")]
+    Polyline (PolylineDef),
+
+    #[serde(rename = "circle")]
+    Circle (CircleDef),
+
+    #[serde(rename = "ellipse")]
+    Ellipse (EllipseDef),
+
+    #[serde(rename = "bezier")]
+    Bezier (BezierDef),
+
+    #[serde(rename = "text")]
+    Text (TextDef),
+
+    #[serde(rename = "image")]
+    Image (ImageDef)
 }
 
 #[derive(Deserialize, Debug, Copy, Clone)]
 pub struct CmykDef (pub f64, pub f64, pub f64, pub f64);
 
+/// How one kind of line in a line set is stroked, for line sets that draw lines in several styles.
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub struct LineStyleDef {
+    #[serde(deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef,
+    #[serde(rename = "dash pattern")]
+    pub dash_pattern: Option<DashPatternDef>
+}
+
+/// A horizontal band of the page, e.g. the x-height band of a writing line.
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub struct BandDef {
+    pub bottom: Coord,
+    pub top: Coord
+}
+
+/// Horizontal bands that slant lines are confined to.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum BandsDef {
+    /// Bands listed one by one.
+    List (Vec<BandDef>),
+
+    /// A band going up from each line of a horizontal lines set, or from each base line of a Seyes set.
+    FromLineSet {
+        /// Number of the line set in the file, counting from 1.
+        #[serde(rename = "from line set")]
+        line_set: usize,
+        height: Length
+    }
+}
+
+/// Right-leaning slant lines across the whole page, starting one spacing right of the top left corner.
+/// See `ParallelLineSet` for other angles and placements.
 #[derive(Deserialize, Debug)]
 pub struct SlantLineSet {
-    #[serde(rename = "x spacing mm")]
-    pub x_spacing: f64,
+    #[serde(rename = "x spacing", alias = "x spacing mm")]
+    pub x_spacing: Length,
     #[serde(rename = "slant angle deg")]
     pub slant_angle: f64,
-    #[serde(rename = "thickness pt")]
-    pub thickness: f64,
+    #[serde(rename = "thickness", alias = "thickness pt", deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
     #[serde(rename = "color cmyk")]
-    pub color: CmykDef
+    pub color: CmykDef,
+    /// If given, the lines are only drawn inside these bands. Not allowed inside a calligraphy line set,
+    /// which confines its slant lines to its own x-height bands and bounds, and neither are `bounds`.
+    pub bands: Option<BandsDef>,
+    pub bounds: Option<RectDef>
 }
 
 #[derive(Deserialize, Debug)]
 pub struct SeyesLineSet {
-    #[serde(rename = "y spacing mm")]
-    pub y_spacing: f64,
-    #[serde(rename = "top margin mm")]
-    pub top_margin: f64,
-    #[serde(rename = "bottom margin mm")]
-    pub bottom_margin: f64,
-    #[serde(rename = "base thickness pt")]
-    pub base_thickness: f64,
+    #[serde(rename = "y spacing", alias = "y spacing mm")]
+    pub y_spacing: Length,
+    #[serde(rename = "top margin", alias = "top margin mm")]
+    pub top_margin: Length,
+    #[serde(rename = "bottom margin", alias = "bottom margin mm")]
+    pub bottom_margin: Length,
+    #[serde(rename = "base thickness", alias = "base thickness pt", deserialize_with = "length::deserialize_pt")]
+    pub base_thickness: Length,
     #[serde(rename = "base color cmyk")]
     pub base_color: CmykDef,
-    #[serde(rename = "aux thickness pt")]
-    pub aux_thickness: f64,
+    #[serde(rename = "aux thickness", alias = "aux thickness pt", deserialize_with = "length::deserialize_pt")]
+    pub aux_thickness: Length,
     #[serde(rename = "aux color cmyk")]
-    pub aux_color: CmykDef
+    pub aux_color: CmykDef,
+    pub bounds: Option<RectDef>
 }
 
 #[derive(Deserialize, Debug)]
 pub struct HorizontalLineSet {
-    #[serde(rename = "y spacing mm")]
-    pub y_spacing: f64,
-    #[serde(rename = "top margin mm")]
-    pub top_margin: f64,
-    #[serde(rename = "bottom margin mm")]
-    pub bottom_margin: f64,
-    #[serde(rename = "thickness pt")]
-    pub thickness: f64,
+    #[serde(rename = "y spacing", alias = "y spacing mm")]
+    pub y_spacing: Length,
+    #[serde(rename = "top margin", alias = "top margin mm")]
+    pub top_margin: Length,
+    #[serde(rename = "bottom margin", alias = "bottom margin mm")]
+    pub bottom_margin: Length,
+    #[serde(rename = "thickness", alias = "thickness pt", deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
     #[serde(rename = "color cmyk")]
     pub color: CmykDef,
     #[serde(rename = "dash pattern")]
-    pub dash_pattern: Option<DashPatternDef>  
+    pub dash_pattern: Option<DashPatternDef>,
+    pub bounds: Option<RectDef>
 }
 
 #[derive(Deserialize, Debug)]
 pub struct VerticalLineSet {
-    #[serde(rename = "x spacing mm")]
-    pub x_spacing: f64,
-    #[serde(rename = "left margin mm")]
-    pub left_margin: f64,
-    #[serde(rename = "right margin mm")]
-    pub right_margin: f64,
-    #[serde(rename = "thickness pt")]
-    pub thickness: f64,
+    #[serde(rename = "x spacing", alias = "x spacing mm")]
+    pub x_spacing: Length,
+    #[serde(rename = "left margin", alias = "left margin mm")]
+    pub left_margin: Length,
+    #[serde(rename = "right margin", alias = "right margin mm")]
+    pub right_margin: Length,
+    #[serde(rename = "thickness", alias = "thickness pt", deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef,
+    #[serde(rename = "dash pattern")]
+    pub dash_pattern: Option<DashPatternDef>,
+    pub bounds: Option<RectDef>
+}
+
+/// Dots at the intersections of a square or rectangular grid.
+/// Margins work the same as in `HorizontalLineSet` and `VerticalLineSet`, so the dots line up with those lines.
+#[derive(Deserialize, Debug)]
+pub struct DotGridLineSet {
+    #[serde(rename = "x spacing")]
+    pub x_spacing: Length,
+    #[serde(rename = "y spacing")]
+    pub y_spacing: Length,
+    #[serde(rename = "top margin")]
+    pub top_margin: Length,
+    #[serde(rename = "bottom margin")]
+    pub bottom_margin: Length,
+    #[serde(rename = "left margin")]
+    pub left_margin: Length,
+    #[serde(rename = "right margin")]
+    pub right_margin: Length,
+    #[serde(rename = "dot diameter", deserialize_with = "length::deserialize_pt")]
+    pub dot_diameter: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef,
+    pub bounds: Option<RectDef>
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum IsometricOrientation {
+    /// Vertical lines plus lines at 30 and 150 degrees.
+    #[serde(rename = "vertical")]
+    Vertical,
+
+    /// Horizontal lines plus lines at 60 and 120 degrees.
+    #[serde(rename = "horizontal")]
+    Horizontal
+}
+
+/// Isometric (triangular) grid: three families of parallel lines that form equilateral triangles.
+/// Margins are optional and default to the paper edge. The grid starts at the top left corner inside the margins.
+#[derive(Deserialize, Debug)]
+pub struct IsometricLineSet {
+    #[serde(rename = "triangle side")]
+    pub triangle_side: Length,
+    pub orientation: Option<IsometricOrientation>,
+    #[serde(rename = "top margin")]
+    pub top_margin: Option<Length>,
+    #[serde(rename = "bottom margin")]
+    pub bottom_margin: Option<Length>,
+    #[serde(rename = "left margin")]
+    pub left_margin: Option<Length>,
+    #[serde(rename = "right margin")]
+    pub right_margin: Option<Length>,
+    #[serde(deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef,
+    #[serde(rename = "dash pattern")]
+    pub dash_pattern: Option<DashPatternDef>,
+    pub bounds: Option<RectDef>
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum HexOrientation {
+    /// Hexagons with a flat edge at the top, in columns.
+    #[serde(rename = "flat top")]
+    FlatTop,
+
+    /// Hexagons with a vertex at the top, in rows.
+    #[serde(rename = "pointy top")]
+    PointyTop
+}
+
+/// Grid of regular hexagons covering the page. The cell size is given either as the side length
+/// or as the distance between opposite flat sides, but not both.
+#[derive(Deserialize, Debug)]
+pub struct HexGridLineSet {
+    pub orientation: HexOrientation,
+    pub side: Option<Length>,
+    #[serde(rename = "flat to flat")]
+    pub flat_to_flat: Option<Length>,
+    /// Center of one of the hexagons. Defaults to the top left corner of the paper.
+    pub origin: Option<PointDef>,
+    #[serde(deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef,
+    #[serde(rename = "dash pattern")]
+    pub dash_pattern: Option<DashPatternDef>,
+    pub bounds: Option<RectDef>
+}
+
+/// Heavier rings in a polar grid.
+#[derive(Deserialize, Debug)]
+pub struct MajorRingsDef {
+    /// Every this many rings is a major one, counting from the center.
+    pub every: u32,
+    #[serde(deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
+    /// Defaults to the color of the other rings.
+    #[serde(rename = "color cmyk")]
+    pub color: Option<CmykDef>
+}
+
+/// Polar graph paper: concentric rings around the center and radial lines coming out of it.
+#[derive(Deserialize, Debug)]
+pub struct PolarGridLineSet {
+    pub center: PointDef,
+    #[serde(rename = "ring spacing")]
+    pub ring_spacing: Length,
+    /// Defaults to the distance from the center to the farthest corner of the paper.
+    #[serde(rename = "outer radius")]
+    pub outer_radius: Option<Length>,
+    /// A radial line every this many degrees, starting from the X axis.
+    #[serde(rename = "radial step deg")]
+    pub radial_step: f64,
+    #[serde(deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef,
+    #[serde(rename = "dash pattern")]
+    pub dash_pattern: Option<DashPatternDef>,
+    #[serde(rename = "major rings")]
+    pub major_rings: Option<MajorRingsDef>,
+    pub bounds: Option<RectDef>
+}
+
+/// Horizontal lines on a logarithmic scale, with the values growing from the bottom margin up to the top margin.
+#[derive(Deserialize, Debug)]
+pub struct HorizontalLogLineSet {
+    pub decades: u32,
+    /// From 1 to 9. Each decade is split into this many steps, with the minor lines at whole multiples
+    /// of the decade value: 9 gives the usual lines at 2, 3, ... 9, 3 gives lines at 2 and 5, 2 a line at 5,
+    /// and 1 gives the decade lines only.
+    pub subdivisions: u32,
+    #[serde(rename = "top margin")]
+    pub top_margin: Length,
+    #[serde(rename = "bottom margin")]
+    pub bottom_margin: Length,
+    /// Style of the decade lines.
+    pub major: LineStyleDef,
+    pub minor: LineStyleDef,
+    pub bounds: Option<RectDef>
+}
+
+/// Vertical lines on a logarithmic scale, with the values growing from the left margin to the right margin.
+#[derive(Deserialize, Debug)]
+pub struct VerticalLogLineSet {
+    pub decades: u32,
+    /// Same as in `HorizontalLogLineSet`.
+    pub subdivisions: u32,
+    #[serde(rename = "left margin")]
+    pub left_margin: Length,
+    #[serde(rename = "right margin")]
+    pub right_margin: Length,
+    pub major: LineStyleDef,
+    pub minor: LineStyleDef,
+    pub bounds: Option<RectDef>
+}
+
+/// Music staff paper: staves of 5 lines each, stacked down from the top margin.
+#[derive(Deserialize, Debug)]
+pub struct MusicStavesLineSet {
+    /// Distance between two lines of one staff.
+    #[serde(rename = "line spacing")]
+    pub line_spacing: Length,
+    /// Distance from the bottom line of one staff to the top line of the next.
+    #[serde(rename = "staff gap")]
+    pub staff_gap: Length,
+    /// Number of staves on the page. If not given, as many as fit between the top and bottom margins.
+    pub staves: Option<u32>,
+    #[serde(rename = "top margin")]
+    pub top_margin: Length,
+    #[serde(rename = "bottom margin")]
+    pub bottom_margin: Length,
+    #[serde(rename = "left margin")]
+    pub left_margin: Length,
+    #[serde(rename = "right margin")]
+    pub right_margin: Length,
+    #[serde(deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef,
+    /// If given, the staves are paired into grand staves, joined by a line at the left edge.
+    #[serde(rename = "grand staff brace")]
+    pub grand_staff_brace: Option<LineStyleDef>,
+    pub bounds: Option<RectDef>
+}
+
+/// Calligraphy guidelines for rows of writing, all measured in nib widths,
+/// e.g. 5, 5 and 5 for the ascender, x-height and descender of Italic.
+#[derive(Deserialize, Debug)]
+pub struct CalligraphyLineSet {
+    #[serde(rename = "nib width")]
+    pub nib_width: Length,
+    /// From the waist line up to the ascender line, in nib widths.
+    pub ascender: f64,
+    /// From the baseline up to the waist line, in nib widths.
+    #[serde(rename = "x height")]
+    pub x_height: f64,
+    /// From the baseline down to the descender line, in nib widths.
+    pub descender: f64,
+    /// From the descender line of one row to the ascender line of the next, in nib widths.
+    #[serde(rename = "interline gap", default)]
+    pub interline_gap: f64,
+    #[serde(rename = "top margin")]
+    pub top_margin: Length,
+    #[serde(rename = "bottom margin")]
+    pub bottom_margin: Length,
+    pub baseline: LineStyleDef,
+    #[serde(rename = "waist line")]
+    pub waist_line: LineStyleDef,
+    /// Not drawn if not given.
+    #[serde(rename = "ascender line")]
+    pub ascender_line: Option<LineStyleDef>,
+    /// Not drawn if not given.
+    #[serde(rename = "descender line")]
+    pub descender_line: Option<LineStyleDef>,
+    /// Slant lines, only drawn between the baseline and the waist line of each row.
+    pub slant: Option<SlantLineSet>,
+    pub bounds: Option<RectDef>
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum WritingDirection {
+    /// Top to bottom in columns, with the gaps between the columns.
+    #[serde(rename = "vertical")]
+    Vertical,
+
+    /// Left to right in rows, with the gaps between the rows.
+    #[serde(rename = "horizontal")]
+    Horizontal
+}
+
+/// Japanese manuscript paper: a grid of square cells in columns (or rows, for horizontal writing),
+/// with gaps between them for furigana and an optional blank fold column in the middle.
+#[derive(Deserialize, Debug)]
+pub struct GenkouyoushiLineSet {
+    #[serde(rename = "cell size")]
+    pub cell_size: Length,
+    pub columns: u32,
+    pub rows: u32,
+    /// Gap between the columns (or rows) for furigana. No gap if not given.
+    #[serde(rename = "ruby gap")]
+    pub ruby_gap: Option<Length>,
+    /// Defaults to vertical.
+    #[serde(rename = "writing direction")]
+    pub writing_direction: Option<WritingDirection>,
+    /// Width of the blank fold column (hashira) between the two halves of the grid.
+    #[serde(rename = "center fold")]
+    pub center_fold: Option<Length>,
+    /// Center of the grid. Defaults to the center of the paper.
+    pub center: Option<PointDef>,
+    #[serde(deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef,
+    #[serde(rename = "dash pattern")]
+    pub dash_pattern: Option<DashPatternDef>,
+    pub bounds: Option<RectDef>
+}
+
+/// Chinese character practice paper: square cells with a solid border and guide lines inside,
+/// a cross through the center (tian zi ge) and optionally the diagonals too (mi zi ge).
+/// As many cells as fit go right from the left margin and down from the top margin.
+#[derive(Deserialize, Debug)]
+pub struct CharacterGridLineSet {
+    #[serde(rename = "cell size")]
+    pub cell_size: Length,
+    /// Gap between neighbouring cells. The cells share their borders if not given.
+    #[serde(rename = "cell gap")]
+    pub cell_gap: Option<Length>,
+    #[serde(rename = "top margin")]
+    pub top_margin: Length,
+    #[serde(rename = "bottom margin")]
+    pub bottom_margin: Length,
+    #[serde(rename = "left margin")]
+    pub left_margin: Length,
+    #[serde(rename = "right margin")]
+    pub right_margin: Length,
+    pub border: LineStyleDef,
+    pub guides: LineStyleDef,
+    #[serde(default)]
+    pub diagonals: bool,
+    pub bounds: Option<RectDef>
+}
+
+/// Primary school handwriting paper: groups of a top line, a midline and a baseline,
+/// plus an optional descender line, stacked down from the top margin.
+#[derive(Deserialize, Debug)]
+pub struct PenmanshipLineSet {
+    /// From the baseline up to the top line. The midline is halfway between them.
+    #[serde(rename = "writing height")]
+    pub writing_height: Length,
+    /// Blank space below each group. No space if not given.
+    #[serde(rename = "group gap")]
+    pub group_gap: Option<Length>,
+    #[serde(rename = "top margin")]
+    pub top_margin: Length,
+    #[serde(rename = "bottom margin")]
+    pub bottom_margin: Length,
+    #[serde(rename = "top line")]
+    pub top_line: LineStyleDef,
+    pub midline: LineStyleDef,
+    pub baseline: LineStyleDef,
+    /// The descender depth below the baseline. Not drawn if not given.
+    #[serde(rename = "descender line")]
+    pub descender_line: Option<LineStyleDef>,
+    /// From the baseline down to the descender line. Half the writing height if not given.
+    #[serde(rename = "descender depth")]
+    pub descender_depth: Option<Length>,
+    pub bounds: Option<RectDef>
+}
+
+/// Graph paper with minor lines every cell and a heavier major line every few cells, on both axes.
+/// Every position gets one line only, either a minor or a major one.
+#[derive(Deserialize, Debug)]
+pub struct GraphGridLineSet {
+    /// Size of a minor cell.
+    pub spacing: Length,
+    /// Every this many minor lines is a major one, counting from the origin.
+    #[serde(rename = "major every")]
+    pub major_every: u32,
+    /// A crossing of two major lines. Defaults to the top left corner inside the margins.
+    pub origin: Option<PointDef>,
+    #[serde(rename = "top margin")]
+    pub top_margin: Option<Length>,
+    #[serde(rename = "bottom margin")]
+    pub bottom_margin: Option<Length>,
+    #[serde(rename = "left margin")]
+    pub left_margin: Option<Length>,
+    #[serde(rename = "right margin")]
+    pub right_margin: Option<Length>,
+    pub minor: LineStyleDef,
+    pub major: LineStyleDef,
+    pub bounds: Option<RectDef>
+}
+
+/// Horizon line of a perspective grid, going across the whole page.
+#[derive(Deserialize, Debug)]
+pub struct HorizonDef {
+    pub y: Coord,
+    #[serde(deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
     #[serde(rename = "color cmyk")]
     pub color: CmykDef,
     #[serde(rename = "dash pattern")]
     pub dash_pattern: Option<DashPatternDef>
 }
 
+/// Perspective guides: a fan of rays out of each of one to three vanishing points,
+/// which may lie outside the page. The rays go either at a fixed angle step, starting from the horizontal,
+/// or through points at a fixed spacing along the page edges.
+#[derive(Deserialize, Debug)]
+pub struct PerspectiveLineSet {
+    pub horizon: Option<HorizonDef>,
+    #[serde(rename = "vanishing points")]
+    pub vanishing_points: Vec<PointDef>,
+    #[serde(rename = "angle step deg")]
+    pub angle_step: Option<f64>,
+    #[serde(rename = "edge spacing")]
+    pub edge_spacing: Option<Length>,
+    #[serde(deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef,
+    #[serde(rename = "dash pattern")]
+    pub dash_pattern: Option<DashPatternDef>,
+    pub bounds: Option<RectDef>
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum SpacingAxis {
+    /// Spacing is the distance between the lines.
+    #[serde(rename = "perpendicular")]
+    Perpendicular,
+
+    /// Spacing is measured along a horizontal line crossing them.
+    #[serde(rename = "x")]
+    X,
+
+    /// Spacing is measured along a vertical line crossing them.
+    #[serde(rename = "y")]
+    Y
+}
+
+/// A family of parallel lines at any angle, e.g. backhand or left-handed slant guides.
+#[derive(Deserialize, Debug)]
+pub struct ParallelLineSet {
+    /// From 0 to 180, counter-clockwise from the X axis. 90 is vertical, less leans right, more leans left.
+    #[serde(rename = "angle deg")]
+    pub angle: f64,
+    pub spacing: Length,
+    /// Defaults to perpendicular.
+    #[serde(rename = "spacing axis")]
+    pub spacing_axis: Option<SpacingAxis>,
+    /// One of the lines goes through this point. Defaults to the top left corner of the paper.
+    pub anchor: Option<PointDef>,
+    #[serde(deserialize_with = "length::deserialize_pt")]
+    pub thickness: Length,
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef,
+    #[serde(rename = "dash pattern")]
+    pub dash_pattern: Option<DashPatternDef>,
+    pub bounds: Option<RectDef>
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum ShadedRows {
+    #[serde(rename = "odd")]
+    Odd,
+
+    #[serde(rename = "even")]
+    Even
+}
+
+/// Alternating shaded rows, e.g. for ledgers and tables. Only whole rows are shaded.
+#[derive(Deserialize, Debug)]
+pub struct RowBandsLineSet {
+    #[serde(rename = "row height")]
+    pub row_height: Length,
+    #[serde(rename = "top margin")]
+    pub top_margin: Length,
+    #[serde(rename = "bottom margin")]
+    pub bottom_margin: Length,
+    #[serde(rename = "left margin")]
+    pub left_margin: Option<Length>,
+    #[serde(rename = "right margin")]
+    pub right_margin: Option<Length>,
+    /// Rows counted from the top margin down. Odd rows if not given, so the first row is shaded.
+    #[serde(rename = "shaded rows")]
+    pub shaded_rows: Option<ShadedRows>,
+    #[serde(rename = "fill cmyk")]
+    pub fill: CmykDef,
+    pub bounds: Option<RectDef>
+}
+
 #[derive(Deserialize, Debug)]
 pub enum LineSet {
     #[serde(rename = "slant")]
@@ -118,38 +773,264 @@ pub enum LineSet {
     #[serde(rename = "vertical lines")]
     VerticalLines (VerticalLineSet),
 
+    #[serde(rename = "dot grid")]
+    DotGrid (DotGridLineSet),
+
+    #[serde(rename = "isometric")]
+    Isometric (IsometricLineSet),
+
+    #[serde(rename = "hex grid")]
+    HexGrid (HexGridLineSet),
+
+    #[serde(rename = "polar grid")]
+    PolarGrid (PolarGridLineSet),
+
+    #[serde(rename = "horizontal log lines")]
+    HorizontalLogLines (HorizontalLogLineSet),
+
+    #[serde(rename = "vertical log lines")]
+    VerticalLogLines (VerticalLogLineSet),
+
+    #[serde(rename = "music staves")]
+    MusicStaves (MusicStavesLineSet),
+
+    #[serde(rename = "calligraphy")]
+    Calligraphy (Box<CalligraphyLineSet>),
+
+    #[serde(rename = "genkouyoushi")]
+    Genkouyoushi (GenkouyoushiLineSet),
+
+    #[serde(rename = "character practice grid")]
+    CharacterGrid (CharacterGridLineSet),
+
+    #[serde(rename = "penmanship")]
+    Penmanship (PenmanshipLineSet),
+
+    #[serde(rename = "graph grid")]
+    GraphGrid (GraphGridLineSet),
+
+    #[serde(rename = "perspective")]
+    Perspective (PerspectiveLineSet),
+
+    #[serde(rename = "parallel lines")]
+    ParallelLines (ParallelLineSet),
+
+    #[serde(rename = "row bands")]
+    RowBands (RowBandsLineSet),
+
     #[serde(rename = "single line")]
-    SingleLine (LineDef)
+    SingleLine (LineDef),
+
+    #[serde(rename = "single shape")]
+    SingleShape (Shape),
+
+    #[serde(rename = "text")]
+    Text (TextDef),
+
+    #[serde(rename = "image")]
+    Image (ImageDef),
+
+    #[serde(rename = "include")]
+    Include (IncludeDef)
 }
 
+/// Line sets of another geometry definition file, drawn on this paper. Only the `line sets`,
+/// `content area` and `parameters` of the other file are used, its paper size is this one's,
+/// and its text can use the fonts of this file.
+#[derive(Deserialize, Debug)]
+pub struct IncludeDef {
+    /// Relative to this file.
+    pub path: PathBuf,
+    /// Moves everything the included line sets draw.
+    pub offset: Option<OffsetDef>,
+    pub bounds: Option<RectDef>
+}
+
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub struct OffsetDef {
+    pub x: Length,
+    pub y: Length
+}
+
+/// The part of another geometry definition file that an `include` line set brings in.
+#[derive(Deserialize, Debug)]
+pub struct IncludedDef {
+    #[serde(rename = "content area")]
+    pub content_area: Option<RectDef>,
+    #[serde(rename = "line sets")]
+    pub line_sets: Vec<LineSet>
+}
+
+/// Paper color under everything that is drawn.
+#[derive(Deserialize, Debug, Copy, Clone)]
+pub struct BackgroundDef {
+    #[serde(rename = "color cmyk")]
+    pub color: CmykDef
+}
+
+/// A whole paper definition. Its `parameters` section isn't read into it, `parameters::apply` substitutes
+/// the parameter values into the YAML before, and `from_value` gives them to the expressions.
 #[derive(Deserialize, Debug)]
 pub struct GeometryDef {
     #[serde(rename = "paper size")]
     pub paper_size: PaperSize,
+    pub background: Option<BackgroundDef>,
+    /// Fonts that text can use by name, as paths relative to this file. Only fonts with TrueType outlines
+    /// are supported: .ttf files, and .otf files with a "glyf" table. OpenType fonts with PostScript (CFF)
+    /// outlines are rejected.
+    #[serde(default)]
+    pub fonts: BTreeMap<String, PathBuf>,
+    /// Clips every line set that doesn't have its own bounds.
+    #[serde(rename = "content area")]
+    pub content_area: Option<RectDef>,
     #[serde(rename = "line sets")]
     pub line_sets: Vec<LineSet>
 }
 
+impl GeometryDef {
+    /// Deserializes the definition with the paper size and the parameters known to the expressions in it,
+    /// so that lengths and coordinates can be written as "width/2 - 10" or "height - top_margin".
+    pub fn from_value(gdef: serde_yaml::Value, parameters: &BTreeMap<String, serde_yaml::Value>) -> Result<GeometryDef, serde_yaml::Error> {
+        let paper_size: PaperSize = match gdef.get("paper size") {
+            Some(paper_size) => serde_yaml::from_value(paper_size.clone())?,
+            // Leave it to the full deserialization to report the missing field
+            None => return serde_yaml::from_value(gdef)
+        };
+        let context = expr::Context { width: paper_size.width, height: paper_size.height, parameters: parameters.clone() };
+        expr::with_context(context, || serde_yaml::from_value(gdef))
+    }
+}
+
 impl PointDef {
+    /// A point at the given distances from the lower left corner of the paper.
+    pub fn from_mm(x: f64, y: f64) -> PointDef {
+        PointDef { x: Coord::OffZero(Length::from_mm(x)), y: Coord::OffZero(Length::from_mm(y)) }
+    }
+
     pub fn x_coord(&self, paper_size: &PaperSize) -> f64 {
-        match self.x {
-            Coord::OffZero(v) => v,
-            Coord::OffFarEdge(v) => paper_size.width - v
-        }
+        self.x.resolve(paper_size.width)
     }
 
     pub fn y_coord(&self, paper_size: &PaperSize) -> f64 {
-        match self.y {
-            Coord::OffZero(v) => v,
-            Coord::OffFarEdge(v) => paper_size.height - v
+        self.y.resolve(paper_size.height)
+    }
+
+    /// The point moved by the offset, as mm off the lower left corner.
+    pub fn translated(&self, offset: &OffsetDef, paper_size: &PaperSize) -> PointDef {
+        PointDef::from_mm(self.x_coord(paper_size) + offset.x.mm(), self.y_coord(paper_size) + offset.y.mm())
+    }
+}
+
+impl RectDef {
+    pub fn left_coord(&self, paper_size: &PaperSize) -> f64 {
+        self.left.resolve(paper_size.width)
+    }
+
+    pub fn right_coord(&self, paper_size: &PaperSize) -> f64 {
+        self.right.resolve(paper_size.width)
+    }
+
+    pub fn bottom_coord(&self, paper_size: &PaperSize) -> f64 {
+        self.bottom.resolve(paper_size.height)
+    }
+
+    pub fn top_coord(&self, paper_size: &PaperSize) -> f64 {
+        self.top.resolve(paper_size.height)
+    }
+}
+
+impl LineSet {
+    /// The line set's own clipping rectangle, if it has one.
+    pub fn bounds(&self) -> Option<&RectDef> {
+        match self {
+            LineSet::Slant(ls) => ls.bounds.as_ref(),
+            LineSet::Seyes(ls) => ls.bounds.as_ref(),
+            LineSet::HorizontalLines(ls) => ls.bounds.as_ref(),
+            LineSet::VerticalLines(ls) => ls.bounds.as_ref(),
+            LineSet::DotGrid(ls) => ls.bounds.as_ref(),
+            LineSet::Isometric(ls) => ls.bounds.as_ref(),
+            LineSet::HexGrid(ls) => ls.bounds.as_ref(),
+            LineSet::PolarGrid(ls) => ls.bounds.as_ref(),
+            LineSet::HorizontalLogLines(ls) => ls.bounds.as_ref(),
+            LineSet::VerticalLogLines(ls) => ls.bounds.as_ref(),
+            LineSet::MusicStaves(ls) => ls.bounds.as_ref(),
+            LineSet::Calligraphy(ls) => ls.bounds.as_ref(),
+            LineSet::Genkouyoushi(ls) => ls.bounds.as_ref(),
+            LineSet::CharacterGrid(ls) => ls.bounds.as_ref(),
+            LineSet::Penmanship(ls) => ls.bounds.as_ref(),
+            LineSet::GraphGrid(ls) => ls.bounds.as_ref(),
+            LineSet::Perspective(ls) => ls.bounds.as_ref(),
+            LineSet::ParallelLines(ls) => ls.bounds.as_ref(),
+            LineSet::RowBands(ls) => ls.bounds.as_ref(),
+            LineSet::SingleLine(_) => None,
+            LineSet::SingleShape(_) => None,
+            LineSet::Text(_) => None,
+            LineSet::Image(_) => None,
+            LineSet::Include(include) => include.bounds.as_ref()
         }
     }
 }
 
-#[cfg(test)]
-impl PaperSize {
-    pub const LETTER_PORTRAIT: PaperSize = PaperSize { width: 215.9, height: 279.4 };
-    pub const LETTER_LANDSCAPE: PaperSize = PaperSize { width: 215.9, height: 279.4 };
+impl Shape {
+    /// How the shape's outline is stroked, if it is.
+    pub fn stroke(&self) -> Option<LineStyleDef> {
+        let line_style = |thickness, color, dash_pattern| Some(LineStyleDef { thickness, color, dash_pattern });
+        match self {
+            Shape::Line(line) => line_style(line.thickness, line.color, line.dash_pattern),
+            Shape::Arc(arc) => line_style(arc.thickness, arc.color, arc.dash_pattern),
+            Shape::Dot(_) | Shape::Text(_) | Shape::Image(_) => None,
+            Shape::Rectangle(rect) => rect.stroke,
+            Shape::Polyline(polyline) => polyline.stroke,
+            Shape::Circle(circle) => circle.stroke,
+            Shape::Ellipse(ellipse) => ellipse.stroke,
+            Shape::Bezier(bezier) => bezier.stroke
+        }
+    }
+
+    /// The color the shape is filled with, if it is.
+    pub fn fill(&self) -> Option<CmykDef> {
+        match self {
+            Shape::Line(_) | Shape::Arc(_) | Shape::Text(_) | Shape::Image(_) => None,
+            Shape::Dot(dot) => Some(dot.color),
+            Shape::Rectangle(rect) => rect.fill,
+            Shape::Polyline(polyline) => polyline.fill,
+            Shape::Circle(circle) => circle.fill,
+            Shape::Ellipse(ellipse) => ellipse.fill,
+            Shape::Bezier(bezier) => bezier.fill
+        }
+    }
+
+    /// Moves the shape by the offset. Sizes, angles and styles stay as they are.
+    pub fn translate(&mut self, offset: &OffsetDef, paper_size: &PaperSize) {
+        let translate = |point: &mut PointDef| *point = point.translated(offset, paper_size);
+        match self {
+            Shape::Line(line) => {
+                translate(&mut line.start);
+                translate(&mut line.end);
+            },
+            Shape::Dot(dot) => translate(&mut dot.center),
+            Shape::Arc(arc) => translate(&mut arc.center),
+            Shape::Rectangle(rect) => {
+                let (left, right) = (rect.left.resolve(paper_size.width), rect.right.resolve(paper_size.width));
+                let (bottom, top) = (rect.bottom.resolve(paper_size.height), rect.top.resolve(paper_size.height));
+                rect.left = Coord::OffZero(Length::from_mm(left + offset.x.mm()));
+                rect.right = Coord::OffZero(Length::from_mm(right + offset.x.mm()));
+                rect.bottom = Coord::OffZero(Length::from_mm(bottom + offset.y.mm()));
+                rect.top = Coord::OffZero(Length::from_mm(top + offset.y.mm()));
+            },
+            Shape::Polyline(polyline) => polyline.points.iter_mut().for_each(translate),
+            Shape::Circle(circle) => translate(&mut circle.center),
+            Shape::Ellipse(ellipse) => translate(&mut ellipse.center),
+            Shape::Bezier(bezier) => {
+                translate(&mut bezier.start);
+                translate(&mut bezier.control_1);
+                translate(&mut bezier.control_2);
+                translate(&mut bezier.end);
+            },
+            Shape::Text(text) => translate(&mut text.position),
+            Shape::Image(image) => translate(&mut image.position)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -173,21 +1054,21 @@ mod tests {
         assert_eq!(gdef.line_sets.len(), 3);
 
         if let LineSet::Slant(slant_lines) = &gdef.line_sets[0] {
-            assert_eq!(slant_lines.x_spacing, 10.0);
+            assert_eq!(slant_lines.x_spacing.mm(), 10.0);
             assert_eq!(slant_lines.slant_angle, 52.0);
-            assert_eq!(slant_lines.thickness, 0.1);
+            assert_eq!(slant_lines.thickness.pt(), 0.1);
             assert_eq!(slant_lines.color, CmykDef(0.02, 0.34, 0.0, 0.12));
         } else {
             panic!("The first line set is supposed to be the slant lines.");
         }
     
         if let LineSet::Seyes(seyes_lines) = &gdef.line_sets[1] {
-            assert_eq!(seyes_lines.y_spacing, 2.0);
-            assert_eq!(seyes_lines.top_margin, 30.0);
-            assert_eq!(seyes_lines.bottom_margin, 20.0);
-            assert_eq!(seyes_lines.base_thickness, 0.4);
+            assert_eq!(seyes_lines.y_spacing.mm(), 2.0);
+            assert_eq!(seyes_lines.top_margin.mm(), 30.0);
+            assert_eq!(seyes_lines.bottom_margin.mm(), 20.0);
+            assert_eq!(seyes_lines.base_thickness.pt(), 0.4);
             assert_eq!(seyes_lines.base_color, CmykDef(0.02, 0.34, 0.0, 0.12));
-            assert_eq!(seyes_lines.aux_thickness, 0.1);
+            assert_eq!(seyes_lines.aux_thickness.pt(), 0.1);
             assert_eq!(seyes_lines.aux_color, CmykDef(0.02, 0.34, 0.0, 0.12));
         } else {
             panic!("The second line set is supposed to be the seyes (French ruled) lines.");
@@ -198,7 +1079,7 @@ mod tests {
             assert_eq!(line.start.y_coord(&gdef.paper_size), PaperSize::LETTER_PORTRAIT.height);
             assert_eq!(line.end.x_coord(&gdef.paper_size), 30.0);
             assert_eq!(line.end.y_coord(&gdef.paper_size), 0.0);
-            assert_eq!(line.thickness, 0.4);
+            assert_eq!(line.thickness.pt(), 0.4);
             assert_eq!(line.color, CmykDef(0.0, 0.36, 0.26, 0.04));
         } else {
             panic!("The third line set is supposed to be a single vertical line.");
@@ -216,20 +1097,20 @@ mod tests {
         assert_eq!(gdef.line_sets.len(), 3);
 
         if let LineSet::HorizontalLines(h_lines) = &gdef.line_sets[0] {
-            assert_eq!(h_lines.y_spacing, 5.0);
-            assert_eq!(h_lines.thickness, 0.1);
-            assert_eq!(h_lines.top_margin, 30.0);
-            assert_eq!(h_lines.bottom_margin, 20.0);
+            assert_eq!(h_lines.y_spacing.mm(), 5.0);
+            assert_eq!(h_lines.thickness.pt(), 0.1);
+            assert_eq!(h_lines.top_margin.mm(), 30.0);
+            assert_eq!(h_lines.bottom_margin.mm(), 20.0);
             assert_eq!(h_lines.color, CmykDef(0.02, 0.34, 0.0, 0.12));
         } else {
             panic!("The first line set is supposed to be the horizontal lines.");
         }
 
         if let LineSet::VerticalLines(v_lines) = &gdef.line_sets[1] {
-            assert_eq!(v_lines.x_spacing, 5.0);
-            assert_eq!(v_lines.thickness, 0.1);
-            assert_eq!(v_lines.left_margin, 30.0);
-            assert_eq!(v_lines.right_margin, 20.0);
+            assert_eq!(v_lines.x_spacing.mm(), 5.0);
+            assert_eq!(v_lines.thickness.pt(), 0.1);
+            assert_eq!(v_lines.left_margin.mm(), 30.0);
+            assert_eq!(v_lines.right_margin.mm(), 20.0);
             assert_eq!(v_lines.color, CmykDef(0.02, 0.34, 0.0, 0.12));
         } else {
             panic!("The second line set is supposed to be the horizontal lines.");
@@ -240,7 +1121,7 @@ mod tests {
             assert_eq!(line.start.y_coord(&gdef.paper_size), PaperSize::LETTER_PORTRAIT.height);
             assert_eq!(line.end.x_coord(&gdef.paper_size), 30.0);
             assert_eq!(line.end.y_coord(&gdef.paper_size), 0.0);
-            assert_eq!(line.thickness, 0.4);
+            assert_eq!(line.thickness.pt(), 0.4);
             assert_eq!(line.color, CmykDef(0.0, 0.36, 0.26, 0.04));
         } else {
             panic!("The third line set is supposed to be a single vertical line.");
@@ -256,10 +1137,10 @@ mod tests {
        assert_eq!(gdef.paper_size.height, PaperSize::LETTER_PORTRAIT.height);
 
        if let LineSet::HorizontalLines(h_lines) = &gdef.line_sets[0] {
-            assert_eq!(h_lines.y_spacing, 6.0);
-            assert_eq!(h_lines.thickness, 0.8);
-            assert_eq!(h_lines.top_margin, 30.0);
-            assert_eq!(h_lines.bottom_margin, 20.0);
+            assert_eq!(h_lines.y_spacing.mm(), 6.0);
+            assert_eq!(h_lines.thickness.pt(), 0.8);
+            assert_eq!(h_lines.top_margin.mm(), 30.0);
+            assert_eq!(h_lines.bottom_margin.mm(), 20.0);
             assert_eq!(h_lines.color, CmykDef(0.02, 0.34, 0.0, 0.12));
 
             match h_lines.dash_pattern {
@@ -286,6 +1167,521 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_letter_quarter_inch_graph() {
+        let yml = fs::read_to_string("test_line_defs/letter_quarter_inch_graph.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        assert_eq!(gdef.paper_size.width, PaperSize::LETTER_PORTRAIT.width);
+        assert_eq!(gdef.paper_size.height, PaperSize::LETTER_PORTRAIT.height);
+
+        if let LineSet::HorizontalLines(h_lines) = &gdef.line_sets[0] {
+            assert_eq!(h_lines.y_spacing.mm(), 6.35);
+            assert_eq!(h_lines.top_margin.mm(), 25.4);
+            assert_eq!(h_lines.bottom_margin.mm(), 12.7);
+            assert_eq!(h_lines.thickness.pt(), 0.1);
+        } else {
+            panic!("The first line set is supposed to be the horizontal lines.");
+        }
+
+        if let LineSet::VerticalLines(v_lines) = &gdef.line_sets[1] {
+            assert_eq!(v_lines.x_spacing.mm(), 6.35);
+            assert_eq!(v_lines.left_margin.mm(), 12.7);
+            assert_eq!(v_lines.right_margin.mm(), 12.7);
+            assert_eq!(v_lines.thickness.pt(), 0.1);
+        } else {
+            panic!("The second line set is supposed to be the vertical lines.");
+        }
+
+        if let LineSet::SingleLine(line) = &gdef.line_sets[2] {
+            assert_eq!(line.start.x_coord(&gdef.paper_size), 31.75);
+            assert_eq!(line.start.y_coord(&gdef.paper_size), PaperSize::LETTER_PORTRAIT.height);
+            assert_eq!(line.end.y_coord(&gdef.paper_size), 0.0);
+            assert_eq!(line.thickness.mm(), 0.15);
+        } else {
+            panic!("The third line set is supposed to be a single vertical line.");
+        }
+    }
+
+    #[test]
+    fn parse_a5_framed_slant() {
+        let yml = fs::read_to_string("test_line_defs/a5_framed_slant.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        let content_area = gdef.content_area.expect("Expecting a content area.");
+        assert_eq!(content_area.left_coord(&gdef.paper_size), 15.0);
+        assert_eq!(content_area.bottom_coord(&gdef.paper_size), 15.0);
+        assert_eq!(content_area.right_coord(&gdef.paper_size), 148.0 - 15.0);
+        assert_eq!(content_area.top_coord(&gdef.paper_size), 210.0 - 25.0);
+
+        assert!(gdef.line_sets[0].bounds().is_none());
+        assert!(gdef.line_sets[1].bounds().is_none());
+        match gdef.line_sets[2].bounds() {
+            Some(bounds) => assert_eq!(bounds.right_coord(&gdef.paper_size), 31.0),
+            None => panic!("The third line set is supposed to have its own bounds.")
+        }
+    }
+
+    #[test]
+    fn parse_letter_dot_grid_5mm() {
+        let yml = fs::read_to_string("test_line_defs/letter_dot_grid_5mm.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::DotGrid(dots) = &gdef.line_sets[0] {
+            assert_eq!(dots.x_spacing.mm(), 5.0);
+            assert_eq!(dots.y_spacing.mm(), 5.0);
+            assert_eq!(dots.top_margin.mm(), 30.0);
+            assert_eq!(dots.bottom_margin.mm(), 20.0);
+            assert_eq!(dots.left_margin.mm(), 20.0);
+            assert_eq!(dots.right_margin.mm(), 20.0);
+            assert_eq!(dots.dot_diameter.pt(), 0.8);
+            assert_eq!(dots.color, CmykDef(0.02, 0.34, 0.0, 0.12));
+        } else {
+            panic!("The first line set is supposed to be the dot grid.");
+        }
+    }
+
+    #[test]
+    fn parse_a4_isometric_5mm() {
+        let yml = fs::read_to_string("test_line_defs/a4_isometric_5mm.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::Isometric(iso) = &gdef.line_sets[0] {
+            assert_eq!(iso.triangle_side.mm(), 5.0);
+            assert_eq!(iso.orientation, Some(IsometricOrientation::Vertical));
+            assert_eq!(iso.top_margin.unwrap().mm(), 15.0);
+            assert_eq!(iso.thickness.pt(), 0.1);
+        } else {
+            panic!("The first line set is supposed to be the isometric grid.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_hex_grid() {
+        let yml = fs::read_to_string("test_line_defs/letter_hex_grid.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::HexGrid(hex) = &gdef.line_sets[0] {
+            assert_eq!(hex.orientation, HexOrientation::PointyTop);
+            assert!(hex.side.is_none());
+            assert_eq!(hex.flat_to_flat.unwrap().mm(), 12.7);
+            let origin = hex.origin.expect("Expecting the hex grid origin.");
+            assert_eq!(origin.x_coord(&gdef.paper_size), 12.7);
+            assert_eq!(origin.y_coord(&gdef.paper_size), PaperSize::LETTER_PORTRAIT.height - 12.7);
+        } else {
+            panic!("The first line set is supposed to be the hex grid.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_polar_grid() {
+        let yml = fs::read_to_string("test_line_defs/letter_polar_grid.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::PolarGrid(polar) = &gdef.line_sets[0] {
+            assert_eq!(polar.center.x_coord(&gdef.paper_size), 107.95);
+            assert_eq!(polar.center.y_coord(&gdef.paper_size), 139.7);
+            assert_eq!(polar.ring_spacing.mm(), 5.0);
+            assert_eq!(polar.outer_radius.unwrap().mm(), 100.0);
+            assert_eq!(polar.radial_step, 15.0);
+            assert_eq!(polar.thickness.pt(), 0.2);
+            let major_rings = polar.major_rings.as_ref().expect("Expecting the major rings.");
+            assert_eq!(major_rings.every, 5);
+            assert_eq!(major_rings.thickness.pt(), 0.6);
+            assert_eq!(major_rings.color, Some(CmykDef(0.0, 0.0, 0.0, 0.6)));
+        } else {
+            panic!("The first line set is supposed to be the polar grid.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_semi_log() {
+        let yml = fs::read_to_string("test_line_defs/letter_semi_log.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        assert_eq!(gdef.line_sets.len(), 2);
+
+        if let LineSet::HorizontalLogLines(log_lines) = &gdef.line_sets[0] {
+            assert_eq!(log_lines.decades, 3);
+            assert_eq!(log_lines.subdivisions, 9);
+            assert_eq!(log_lines.top_margin.mm(), 15.0);
+            assert_eq!(log_lines.bottom_margin.mm(), 15.0);
+            assert_eq!(log_lines.major.thickness.pt(), 0.6);
+            assert_eq!(log_lines.minor.color, CmykDef(0.0, 0.0, 0.0, 0.4));
+            assert!(log_lines.minor.dash_pattern.is_none());
+        } else {
+            panic!("The first line set is supposed to be the horizontal log lines.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_grand_staves() {
+        let yml = fs::read_to_string("test_line_defs/letter_grand_staves.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::MusicStaves(staves) = &gdef.line_sets[0] {
+            assert_eq!(staves.line_spacing.mm(), 2.0);
+            assert_eq!(staves.staff_gap.mm(), 12.0);
+            assert!(staves.staves.is_none());
+            assert_eq!(staves.top_margin.mm(), 12.7);
+            assert_eq!(staves.left_margin.mm(), 15.0);
+            assert_eq!(staves.thickness.pt(), 0.4);
+            let brace = staves.grand_staff_brace.expect("Expecting the grand staff brace.");
+            assert_eq!(brace.thickness.pt(), 1.5);
+        } else {
+            panic!("The first line set is supposed to be the music staves.");
+        }
+    }
+
+    #[test]
+    fn parse_a4_italic_calligraphy() {
+        let yml = fs::read_to_string("test_line_defs/a4_italic_calligraphy.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::Calligraphy(calligraphy) = &gdef.line_sets[0] {
+            assert_eq!(calligraphy.nib_width.mm(), 3.8);
+            assert_eq!(calligraphy.ascender, 5.0);
+            assert_eq!(calligraphy.x_height, 5.0);
+            assert_eq!(calligraphy.descender, 5.0);
+            assert_eq!(calligraphy.interline_gap, 1.0);
+            assert_eq!(calligraphy.baseline.thickness.pt(), 0.6);
+            let ascender_line = calligraphy.ascender_line.expect("Expecting the ascender line style.");
+            assert_eq!(ascender_line.dash_pattern.unwrap().dash, 3);
+            let slant = calligraphy.slant.as_ref().expect("Expecting the slant lines.");
+            assert_eq!(slant.slant_angle, 83.0);
+            assert_eq!(slant.x_spacing.mm(), 8.0);
+        } else {
+            panic!("The first line set is supposed to be the calligraphy lines.");
+        }
+    }
+
+    #[test]
+    fn parse_a4_genkouyoushi() {
+        let yml = fs::read_to_string("test_line_defs/a4_genkouyoushi.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        assert_eq!(gdef.paper_size.width, 297.0);
+        assert_eq!(gdef.paper_size.height, 210.0);
+
+        if let LineSet::Genkouyoushi(genkouyoushi) = &gdef.line_sets[0] {
+            assert_eq!(genkouyoushi.cell_size.mm(), 8.0);
+            assert_eq!(genkouyoushi.columns, 20);
+            assert_eq!(genkouyoushi.rows, 20);
+            assert_eq!(genkouyoushi.ruby_gap.unwrap().mm(), 3.0);
+            assert_eq!(genkouyoushi.writing_direction, Some(WritingDirection::Vertical));
+            assert_eq!(genkouyoushi.center_fold.unwrap().mm(), 12.0);
+            assert!(genkouyoushi.center.is_none());
+            assert_eq!(genkouyoushi.color, CmykDef(0.0, 0.8, 0.8, 0.1));
+        } else {
+            panic!("The first line set is supposed to be the genkouyoushi grid.");
+        }
+    }
+
+    #[test]
+    fn parse_a4_mi_zi_ge() {
+        let yml = fs::read_to_string("test_line_defs/a4_mi_zi_ge.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::CharacterGrid(grid) = &gdef.line_sets[0] {
+            assert_eq!(grid.cell_size.mm(), 18.0);
+            assert_eq!(grid.cell_gap.unwrap().mm(), 3.0);
+            assert_eq!(grid.top_margin.mm(), 15.0);
+            assert_eq!(grid.border.thickness.pt(), 0.8);
+            assert!(grid.border.dash_pattern.is_none());
+            assert_eq!(grid.guides.dash_pattern.unwrap().gap, Some(2));
+            assert!(grid.diagonals);
+        } else {
+            panic!("The first line set is supposed to be the character practice grid.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_penmanship() {
+        let yml = fs::read_to_string("test_line_defs/letter_penmanship.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::Penmanship(penmanship) = &gdef.line_sets[0] {
+            assert_eq!(penmanship.writing_height.mm(), 25.4);
+            assert_eq!(penmanship.group_gap.unwrap().mm(), 6.35);
+            assert_eq!(penmanship.top_line.thickness.pt(), 0.8);
+            assert_eq!(penmanship.midline.dash_pattern.unwrap().dash, 6);
+            assert_eq!(penmanship.baseline.color, CmykDef(0.0, 0.9, 0.9, 0.0));
+            assert!(penmanship.descender_line.is_some());
+            assert_eq!(penmanship.descender_depth.map(|d| d.mm()), Some(10.16));
+        } else {
+            panic!("The first line set is supposed to be the penmanship lines.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_engineering_graph() {
+        let yml = fs::read_to_string("test_line_defs/letter_engineering_graph.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::GraphGrid(grid) = &gdef.line_sets[0] {
+            assert_eq!(grid.spacing.mm(), 1.0);
+            assert_eq!(grid.major_every, 10);
+            let origin = grid.origin.expect("Expecting the graph grid origin.");
+            assert_eq!(origin.x_coord(&gdef.paper_size), 107.95);
+            assert_eq!(grid.left_margin.unwrap().mm(), 10.0);
+            assert_eq!(grid.minor.thickness.pt(), 0.1);
+            assert_eq!(grid.major.color, CmykDef(0.7, 0.0, 0.4, 0.2));
+        } else {
+            panic!("The first line set is supposed to be the graph grid.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_two_point_perspective() {
+        let yml = fs::read_to_string("test_line_defs/letter_two_point_perspective.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::Perspective(perspective) = &gdef.line_sets[0] {
+            let horizon = perspective.horizon.as_ref().expect("Expecting the horizon.");
+            assert_eq!(horizon.y.resolve(gdef.paper_size.height), 120.0);
+            assert_eq!(horizon.thickness.pt(), 0.6);
+            assert_eq!(perspective.vanishing_points.len(), 2);
+            assert_eq!(perspective.vanishing_points[0].x_coord(&gdef.paper_size), -150.0);
+            assert_eq!(perspective.vanishing_points[1].x_coord(&gdef.paper_size), PaperSize::LETTER_LANDSCAPE.width + 150.0);
+            assert!(perspective.angle_step.is_none());
+            assert_eq!(perspective.edge_spacing.unwrap().mm(), 8.0);
+        } else {
+            panic!("The first line set is supposed to be the perspective lines.");
+        }
+    }
+
+    #[test]
+    fn parse_a5_left_handed_slant() {
+        let yml = fs::read_to_string("test_line_defs/a5_left_handed_slant.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::ParallelLines(parallel_lines) = &gdef.line_sets[1] {
+            assert_eq!(parallel_lines.angle, 105.0);
+            assert_eq!(parallel_lines.spacing.mm(), 6.0);
+            assert_eq!(parallel_lines.spacing_axis, Some(SpacingAxis::X));
+            let anchor = parallel_lines.anchor.expect("Expecting the anchor point.");
+            assert_eq!(anchor.x_coord(&gdef.paper_size), 10.0);
+            assert_eq!(anchor.y_coord(&gdef.paper_size), 10.0);
+            assert_eq!(parallel_lines.dash_pattern.unwrap().dash, 2);
+        } else {
+            panic!("The second line set is supposed to be the parallel lines.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_seyes_banded_slant() {
+        let yml = fs::read_to_string("test_line_defs/letter_seyes_banded_slant.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::Slant(slant_lines) = &gdef.line_sets[1] {
+            match &slant_lines.bands {
+                Some(BandsDef::FromLineSet { line_set, height }) => {
+                    assert_eq!(*line_set, 1);
+                    assert_eq!(height.mm(), 2.0);
+                },
+                other => panic!("Expecting bands from a line set, got {:?}", other)
+            }
+        } else {
+            panic!("The second line set is supposed to be the slant lines.");
+        }
+    }
+
+    #[test]
+    fn parse_listed_bands() {
+        let bands: BandsDef = serde_yaml::from_str("[{bottom: 20mm, top: 25mm}, {bottom: 40mm, top: {off far edge: 30mm}}]")
+            .expect("A list of bands should be successfully parsed.");
+        if let BandsDef::List(bands) = bands {
+            assert_eq!(bands.len(), 2);
+            assert_eq!(bands[1].top.resolve(PaperSize::LETTER_PORTRAIT.height), PaperSize::LETTER_PORTRAIT.height - 30.0);
+        } else {
+            panic!("Expecting a list of bands.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_title_frame() {
+        let yml = fs::read_to_string("test_line_defs/letter_title_frame.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+        assert_eq!(gdef.line_sets.len(), 5);
+
+        if let LineSet::SingleShape(Shape::Rectangle(rect)) = &gdef.line_sets[0] {
+            assert_eq!(rect.corner_radius.map(|r| r.mm()), Some(4.0));
+            assert_eq!(rect.stroke.map(|s| s.thickness.pt()), Some(0.8));
+            assert_eq!(rect.fill, Some(CmykDef(0.0, 0.0, 0.0, 0.05)));
+        } else {
+            panic!("The first line set is supposed to be a rectangle.");
+        }
+
+        if let LineSet::SingleShape(Shape::Polyline(polyline)) = &gdef.line_sets[1] {
+            assert_eq!(polyline.points.len(), 4);
+            assert!(polyline.closed);
+            assert!(polyline.fill.is_none());
+        } else {
+            panic!("The second line set is supposed to be a polyline.");
+        }
+
+        if let LineSet::SingleShape(Shape::Ellipse(ellipse)) = &gdef.line_sets[3] {
+            assert_eq!(ellipse.rotation, 30.0);
+            assert!(ellipse.stroke.is_none());
+        } else {
+            panic!("The fourth line set is supposed to be an ellipse.");
+        }
+
+        assert!(matches!(&gdef.line_sets[4], LineSet::SingleShape(Shape::Bezier(_))));
+    }
+
+    #[test]
+    fn parse_letter_shaded_ledger() {
+        let yml = fs::read_to_string("test_line_defs/letter_shaded_ledger.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+        assert_eq!(gdef.background.map(|b| b.color), Some(CmykDef(0.0, 0.02, 0.1, 0.0)));
+
+        if let LineSet::RowBands(row_bands) = &gdef.line_sets[2] {
+            assert_eq!(row_bands.row_height.mm(), 8.0);
+            assert_eq!(row_bands.left_margin.map(|m| m.mm()), Some(25.0));
+            assert!(row_bands.right_margin.is_none());
+            assert_eq!(row_bands.shaded_rows, Some(ShadedRows::Even));
+        } else {
+            panic!("The third line set is supposed to be the row bands.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_titled_sheet() {
+        let yml = fs::read_to_string("test_line_defs/letter_titled_sheet.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::Text(title) = &gdef.line_sets[0] {
+            assert_eq!(title.text, "Spelling Practice");
+            assert_eq!(title.font_size.pt(), 20.0);
+            assert_eq!(title.font.as_deref(), Some("Helvetica-Bold"));
+            assert_eq!(title.alignment, Some(TextAlignment::Center));
+            assert_eq!(title.rotation, 0.0);
+        } else {
+            panic!("The first line set is supposed to be the title.");
+        }
+
+        if let LineSet::Text(caption) = &gdef.line_sets[3] {
+            assert_eq!(caption.font_size.pt(), 8.5);
+            assert_eq!(caption.rotation, 90.0);
+        } else {
+            panic!("The fourth line set is supposed to be the caption.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_branded_header() {
+        let yml = fs::read_to_string("test_line_defs/letter_branded_header.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        assert_eq!(gdef.fonts.len(), 2);
+        assert_eq!(gdef.fonts["Brand Bold"], PathBuf::from("fonts/DejaVuSerif-Bold.ttf"));
+
+        if let LineSet::Text(title) = &gdef.line_sets[0] {
+            assert_eq!(title.font.as_deref(), Some("Brand Bold"));
+        } else {
+            panic!("The first line set is supposed to be the title.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_crest_header() {
+        let yml = fs::read_to_string("test_line_defs/letter_crest_header.yml").unwrap();
+        let gdef: GeometryDef = serde_yaml::from_str(&yml).unwrap();
+
+        if let LineSet::Image(crest) = &gdef.line_sets[1] {
+            assert_eq!(crest.path, PathBuf::from("images/crest.png"));
+            assert!(crest.width.is_none());
+            assert_eq!(crest.height.map(|h| h.mm()), Some(24.0));
+            assert!(crest.opacity.is_none());
+        } else {
+            panic!("The second line set is supposed to be the crest.");
+        }
+
+        if let LineSet::Image(watermark) = &gdef.line_sets[3] {
+            assert_eq!(watermark.opacity, Some(0.08));
+            assert_eq!(watermark.rotation, 30.0);
+        } else {
+            panic!("The fourth line set is supposed to be the watermark.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_parameterized_lines() {
+        let yml = fs::read_to_string("test_line_defs/letter_parameterized_lines.yml").unwrap();
+        let mut gdef: serde_yaml::Value = serde_yaml::from_str(&yml).unwrap();
+        crate::parameters::apply(&mut gdef, &[("spacing".to_string(), "6mm".to_string())]).unwrap();
+        let gdef: GeometryDef = serde_yaml::from_value(gdef).unwrap();
+
+        if let LineSet::HorizontalLines(lines) = &gdef.line_sets[0] {
+            assert_eq!(lines.y_spacing.mm(), 6.0);
+            assert_eq!(lines.top_margin.mm(), 30.0);
+            assert_eq!(lines.color, CmykDef(0.0, 0.0, 0.0, 0.35));
+        } else {
+            panic!("The first line set is supposed to be the horizontal lines.");
+        }
+
+        if let LineSet::SingleLine(margin_line) = &gdef.line_sets[1] {
+            assert_eq!(margin_line.start.x_coord(&gdef.paper_size), 30.0);
+            assert_eq!(margin_line.color, CmykDef(0.0, 0.36, 0.26, 0.04));
+        } else {
+            panic!("The second line set is supposed to be the margin line.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_centered_layout() {
+        let yml = fs::read_to_string("test_line_defs/letter_centered_layout.yml").unwrap();
+        let mut gdef: serde_yaml::Value = serde_yaml::from_str(&yml).unwrap();
+        let parameters = crate::parameters::apply(&mut gdef, &[]).unwrap();
+        let gdef = GeometryDef::from_value(gdef, &parameters).unwrap();
+        let letter = PaperSize::LETTER_PORTRAIT;
+
+        if let LineSet::SingleShape(Shape::Rectangle(frame)) = &gdef.line_sets[0] {
+            assert_eq!(frame.left.resolve(letter.width), 20.0);
+            assert_eq!(frame.right.resolve(letter.width), letter.width - 20.0);
+            assert_eq!(frame.bottom.resolve(letter.height), letter.height - 45.0);
+        } else {
+            panic!("The first line set is supposed to be the header frame.");
+        }
+
+        if let LineSet::SingleLine(divider) = &gdef.line_sets[2] {
+            assert!(matches!(divider.start.x, Coord::Proportional(_, fraction) if fraction == 0.5));
+            assert_eq!(divider.start.x_coord(&letter), letter.width / 2.0);
+        } else {
+            panic!("The third line set is supposed to be the divider.");
+        }
+
+        if let LineSet::HorizontalLines(lines) = &gdef.line_sets[3] {
+            assert!((lines.y_spacing.mm() - (letter.height - 85.0) / 30.0).abs() < 1e-9);
+            assert_eq!(lines.top_margin.mm(), 65.0);
+        } else {
+            panic!("The fourth line set is supposed to be the horizontal lines.");
+        }
+    }
+
+    #[test]
+    fn parse_letter_included_header() {
+        let yml = fs::read_to_string("test_line_defs/letter_included_header.yml").unwrap();
+        let gdef = GeometryDef::from_value(serde_yaml::from_str(&yml).unwrap(), &BTreeMap::new()).unwrap();
+        let letter = PaperSize::LETTER_PORTRAIT;
+
+        if let LineSet::Include(upper) = &gdef.line_sets[0] {
+            assert_eq!(upper.path, PathBuf::from("shared/margin_and_header.yml"));
+            assert!(upper.offset.is_none());
+            assert_eq!(upper.bounds.as_ref().map(|b| b.bottom_coord(&letter)), Some(letter.height / 2.0));
+        } else {
+            panic!("The first line set is supposed to be the upper sheet's include.");
+        }
+
+        if let LineSet::Include(lower) = &gdef.line_sets[1] {
+            let offset = lower.offset.expect("The lower sheet's include is supposed to be moved down.");
+            assert_eq!((offset.x.mm(), offset.y.mm()), (0.0, -letter.height / 2.0));
+        } else {
+            panic!("The second line set is supposed to be the lower sheet's include.");
+        }
+    }
+
     impl PartialEq for CmykDef {
         fn eq(&self, other: &Self) -> bool {
             self.0 == other.0 && self.1 == other.1 && self.2 == other.2 && self.3 == other.3
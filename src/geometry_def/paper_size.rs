@@ -0,0 +1,209 @@
+use std::fmt;
+use serde::{self, Deserialize};
+use serde::de::{self, Deserializer, Visitor};
+use super::length::Length;
+
+/// Paper dimensions in millimeters, already resolved from a preset and orientation.
+#[derive(Debug)]
+pub struct PaperSize {
+    pub width: f64,
+    pub height: f64
+}
+
+#[derive(Deserialize, Debug, Copy, Clone, PartialEq)]
+pub enum Orientation {
+    #[serde(rename = "portrait")]
+    Portrait,
+
+    #[serde(rename = "landscape")]
+    Landscape
+}
+
+/// Known paper sizes as (name, width mm, height mm), all in portrait orientation.
+/// Names are matched after normalization (see `normalize_preset_name`).
+const PRESETS: &[(&str, f64, f64)] = &[
+    ("a3", 297.0, 420.0),
+    ("a4", 210.0, 297.0),
+    ("a5", 148.0, 210.0),
+    ("a6", 105.0, 148.0),
+    ("b5", 176.0, 250.0),
+    ("b6", 125.0, 176.0),
+    // Japanese JIS B sizes
+    ("jis b5", 182.0, 257.0),
+    ("jis b6", 128.0, 182.0),
+    ("letter", 215.9, 279.4),
+    ("legal", 215.9, 355.6),
+    ("half letter", 139.7, 215.9),
+    ("executive", 184.15, 266.7),
+    ("tabloid", 279.4, 431.8),
+    ("travelers notebook", 110.0, 210.0),
+    ("travelers notebook passport", 89.0, 124.0),
+    ("hobonichi techo", 105.0, 148.0),
+    ("hobonichi cousin", 148.0, 210.0),
+    ("hobonichi weeks", 95.0, 188.0),
+    // Tomoe River loose sheets and notebooks are cut to A sizes and to the Japanese B sizes
+    ("tomoe river a4", 210.0, 297.0),
+    ("tomoe river a5", 148.0, 210.0),
+    ("tomoe river b5", 182.0, 257.0),
+    ("tomoe river b6", 128.0, 182.0)
+];
+
+#[derive(Debug, Deserialize)]
+struct PaperSizeMap {
+    preset: Option<String>,
+    #[serde(alias = "width mm")]
+    width: Option<Length>,
+    #[serde(alias = "height mm")]
+    height: Option<Length>,
+    orientation: Option<Orientation>
+}
+
+impl PaperSize {
+    /// Looks up a named paper size. The result is in portrait orientation.
+    pub fn from_preset(name: &str) -> Option<PaperSize> {
+        let name = normalize_preset_name(name);
+        PRESETS.iter()
+            .find(|(preset, _, _)| *preset == name)
+            .map(|&(_, width, height)| PaperSize { width, height })
+    }
+
+    /// Swaps the dimensions if needed, so that portrait paper is taller than wide and landscape is wider than tall.
+    pub fn oriented(self, orientation: Orientation) -> PaperSize {
+        let is_landscape = self.width > self.height;
+        if is_landscape == (orientation == Orientation::Landscape) {
+            self
+        } else {
+            PaperSize { width: self.height, height: self.width }
+        }
+    }
+}
+
+/// Lower-cases the name, drops apostrophes and treats '-' and '_' as spaces,
+/// so "Traveler's Notebook", "travelers-notebook" and "TRAVELERS_NOTEBOOK" are all the same preset.
+fn normalize_preset_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '\'' && *c != '’')
+        .map(|c| if c == '-' || c == '_' { ' ' } else { c.to_ascii_lowercase() })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+fn unknown_preset<E: de::Error>(name: &str) -> E {
+    let known: Vec<&str> = PRESETS.iter().map(|(preset, _, _)| *preset).collect();
+    E::custom(format!("Unknown paper size preset \"{}\". Known presets are: {}.", name, known.join(", ")))
+}
+
+struct PaperSizeVisitor;
+
+impl<'de> Visitor<'de> for PaperSizeVisitor {
+    type Value = PaperSize;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "A paper size preset name or a map {{preset: <name>, orientation: portrait|landscape}} \
+            or {{width: <length>, height: <length>}}")
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where E: de::Error {
+        PaperSize::from_preset(v).ok_or_else(|| unknown_preset(v))
+    }
+
+    fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+    where A: de::MapAccess<'de> {
+        let psm: PaperSizeMap = Deserialize::deserialize(de::value::MapAccessDeserializer::new(map))?;
+        let paper_size = match (psm.preset, psm.width, psm.height) {
+            (Some(preset), None, None) =>
+                PaperSize::from_preset(&preset).ok_or_else(|| unknown_preset(&preset))?,
+            (None, Some(width), Some(height)) =>
+                PaperSize { width: width.mm(), height: height.mm() },
+            (Some(_), _, _) =>
+                return Err(de::Error::custom("A paper size must have either a preset or the width and height, not both.")),
+            (None, _, _) =>
+                return Err(de::Error::custom("A paper size without a preset must have both the width and height."))
+        };
+
+        Ok(match psm.orientation {
+            Some(orientation) => paper_size.oriented(orientation),
+            None => paper_size
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for PaperSize {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where D: Deserializer<'de> {
+        deserializer.deserialize_any(PaperSizeVisitor { })
+    }
+}
+
+#[cfg(test)]
+impl PaperSize {
+    pub const LETTER_PORTRAIT: PaperSize = PaperSize { width: 215.9, height: 279.4 };
+    pub const LETTER_LANDSCAPE: PaperSize = PaperSize { width: 279.4, height: 215.9 };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_preset_name_as_portrait_paper() {
+        let ps: PaperSize = serde_yaml::from_str("Letter")
+            .expect("A preset name should be successfully parsed as a paper size.");
+        assert_eq!(ps.width, PaperSize::LETTER_PORTRAIT.width);
+        assert_eq!(ps.height, PaperSize::LETTER_PORTRAIT.height);
+    }
+
+    #[test]
+    fn parse_preset_with_landscape_orientation() {
+        let ps: PaperSize = serde_yaml::from_str("{preset: letter, orientation: landscape}")
+            .expect("A preset with orientation should be successfully parsed as a paper size.");
+        assert_eq!(ps.width, PaperSize::LETTER_LANDSCAPE.width);
+        assert_eq!(ps.height, PaperSize::LETTER_LANDSCAPE.height);
+    }
+
+    #[test]
+    fn preset_names_are_normalized() {
+        let ps: PaperSize = serde_yaml::from_str("\"Traveler's Notebook\"").unwrap();
+        assert_eq!(ps.width, 110.0);
+        assert_eq!(ps.height, 210.0);
+
+        let ps: PaperSize = serde_yaml::from_str("half-letter").unwrap();
+        assert_eq!(ps.width, 139.7);
+        assert_eq!(ps.height, 215.9);
+
+        let ps: PaperSize = serde_yaml::from_str("Tomoe-River B5").unwrap();
+        assert_eq!(ps.width, 182.0);
+        assert_eq!(ps.height, 257.0);
+    }
+
+    #[test]
+    fn orientation_applies_to_explicit_dimensions() {
+        let ps: PaperSize = serde_yaml::from_str("{\"width mm\": 210.0, \"height mm\": 148.0, orientation: portrait}")
+            .expect("Explicit dimensions with orientation should be successfully parsed as a paper size.");
+        assert_eq!(ps.width, 148.0);
+        assert_eq!(ps.height, 210.0);
+    }
+
+    #[test]
+    fn parse_explicit_dimensions_with_units() {
+        let ps: PaperSize = serde_yaml::from_str("{width: 8.5in, height: 11in}")
+            .expect("Explicit dimensions with units should be successfully parsed as a paper size.");
+        assert!((ps.width - PaperSize::LETTER_PORTRAIT.width).abs() < 1e-9);
+        assert!((ps.height - PaperSize::LETTER_PORTRAIT.height).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unknown_preset_is_rejected() {
+        let res: Result<PaperSize, _> = serde_yaml::from_str("{preset: napkin}");
+        assert!(res.is_err(), "An unknown preset should fail to parse.");
+    }
+
+    #[test]
+    fn preset_and_dimensions_together_are_rejected() {
+        let res: Result<PaperSize, _> = serde_yaml::from_str("{preset: a4, \"width mm\": 100.0, \"height mm\": 100.0}");
+        assert!(res.is_err(), "A preset together with explicit dimensions should fail to parse.");
+    }
+}
@@ -0,0 +1,204 @@
+use crate::geometry_def::{LineDef, LineStyleDef, PointDef, PaperSize, GraphGridLineSet};
+use crate::geometry_def::length::Length;
+use crate::clipping::{Rect, EPSILON};
+use thiserror::Error;
+
+pub fn create_graph_grid(line_set: &GraphGridLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let spacing = line_set.spacing.mm();
+    if spacing <= 0.0 {
+        return Err(Error::SpacingIsNotPositive(spacing));
+    }
+
+    if line_set.major_every == 0 {
+        return Err(Error::MajorEveryIsZero);
+    }
+
+    let margin = |m: Option<Length>| -> Result<f64, Error> {
+        let m = m.map_or(0.0, Length::mm);
+        if m < 0.0 {
+            return Err(Error::MarginIsNegative(m));
+        }
+        Ok(m)
+    };
+
+    let rect = Rect {
+        left: margin(line_set.left_margin)?,
+        bottom: margin(line_set.bottom_margin)?,
+        right: paper_size.width - margin(line_set.right_margin)?,
+        top: paper_size.height - margin(line_set.top_margin)?
+    };
+
+    if rect.width() <= 0.0 || rect.height() <= 0.0 {
+        return Err(Error::NoRoomBetweenMargins);
+    }
+
+    let (origin_x, origin_y) = match &line_set.origin {
+        Some(origin) => (origin.x_coord(paper_size), origin.y_coord(paper_size)),
+        None => (rect.left, rect.top)
+    };
+
+    let mut add_line = |x0, y0, x1, y1, style: &LineStyleDef| {
+        result.push(LineDef {
+            start: PointDef::from_mm(x0, y0),
+            end: PointDef::from_mm(x1, y1),
+            thickness: style.thickness,
+            color: style.color,
+            dash_pattern: style.dash_pattern
+        });
+    };
+
+    // Minor lines go first, so that the major lines are drawn over them where they cross
+    for &is_major in [false, true].iter() {
+        let style = if is_major { &line_set.major } else { &line_set.minor };
+        let wanted = |index: i64| (index.rem_euclid(line_set.major_every as i64) == 0) == is_major;
+
+        for index in line_indexes(origin_y, spacing, rect.bottom, rect.top).filter(|i| wanted(*i)) {
+            let y = origin_y + index as f64 * spacing;
+            add_line(rect.left, y, rect.right, y, style);
+        }
+
+        for index in line_indexes(origin_x, spacing, rect.left, rect.right).filter(|i| wanted(*i)) {
+            let x = origin_x + index as f64 * spacing;
+            add_line(x, rect.top, x, rect.bottom, style);
+        }
+    }
+
+    Ok(())
+}
+
+/// Numbers of the lines at `origin + index * spacing` that fall between `min` and `max`.
+/// Positions are computed from the index rather than by adding up the spacing, so they don't drift.
+fn line_indexes(origin: f64, spacing: f64, min: f64, max: f64) -> std::ops::RangeInclusive<i64> {
+    let first = ((min - origin - EPSILON) / spacing).ceil() as i64;
+    let last = ((max - origin + EPSILON) / spacing).floor() as i64;
+    first..=last
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Spacing of {0} is not a positive number.")]
+    SpacingIsNotPositive(f64),
+
+    #[error("Major lines must be every 1 or more minor lines.")]
+    MajorEveryIsZero,
+
+    #[error("Margin of {0} is a negative number.")]
+    MarginIsNegative(f64),
+
+    #[error("The margins leave no room for the grid.")]
+    NoRoomBetweenMargins,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::CmykDef;
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    const MINOR: LineStyleDef = LineStyleDef { thickness: Length::ZERO, color: CmykDef::BLACK, dash_pattern: None };
+    const MAJOR: LineStyleDef = LineStyleDef { thickness: Length::from_mm(0.3), color: CmykDef::BLACK, dash_pattern: None };
+
+    fn graph_grid(spacing: f64, origin: Option<PointDef>) -> GraphGridLineSet {
+        GraphGridLineSet {
+            spacing: Length::from_mm(spacing),
+            major_every: 5,
+            origin,
+            top_margin: None,
+            bottom_margin: None,
+            left_margin: None,
+            right_margin: None,
+            minor: MINOR,
+            major: MAJOR,
+            bounds: None
+        }
+    }
+
+    fn is_major(line: &LineDef) -> bool {
+        line.thickness.mm() > 0.0
+    }
+
+    #[test]
+    fn major_lines_land_on_whole_cells_from_origin() {
+        let paper_size = &PaperSize { width: 100.0, height: 50.0 };
+        let mut result: Vec<LineDef> = Vec::new();
+        create_graph_grid(&graph_grid(2.0, Some(PointDef::from_mm(13.0, 7.0))), paper_size, &mut result).unwrap();
+
+        // Vertical lines at x = 1, 3, ... 99 and horizontal lines at y = 1, 3, ... 49
+        assert_eq!(result.len(), 50 + 25);
+        for line in result.iter() {
+            let x = line.start.x_coord(paper_size);
+            let y = line.start.y_coord(paper_size);
+            let vertical = x == line.end.x_coord(paper_size);
+            let offset = if vertical { x - 13.0 } else { y - 7.0 };
+            let is_on_major = (offset / 2.0).round() as i64 % 5 == 0;
+            assert_eq!(is_major(line), is_on_major, "Line at x {}, y {}", x, y);
+        }
+    }
+
+    #[test]
+    fn no_line_is_drawn_twice() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_graph_grid(&graph_grid(1.0, None), paper_size, &mut result).unwrap();
+
+        let mut positions: Vec<(bool, i64)> = result.iter()
+            .map(|l| {
+                let vertical = l.start.x_coord(paper_size) == l.end.x_coord(paper_size);
+                let position = if vertical { l.start.x_coord(paper_size) } else { l.start.y_coord(paper_size) };
+                (vertical, (position * 1e6).round() as i64)
+            })
+            .collect();
+        let count = positions.len();
+        positions.sort_unstable();
+        positions.dedup();
+        assert_eq!(positions.len(), count);
+    }
+
+    #[test]
+    fn positions_do_not_drift() {
+        // 0.1mm doesn't add up exactly in floating point, index-based positions stay on the spot
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_graph_grid(&graph_grid(0.1, Some(PointDef::from_mm(0.0, 0.0))), paper_size, &mut result).unwrap();
+
+        let last_vertical = result.iter().rev()
+            .find(|l| l.start.x_coord(paper_size) == l.end.x_coord(paper_size))
+            .unwrap();
+        assert_eq!(last_vertical.start.x_coord(paper_size), 2155.0 * 0.1);
+        assert!(is_major(last_vertical));
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<GraphGridLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|grid, _| grid.spacing = Length::ZERO, Error::SpacingIsNotPositive(0.0)),
+            (|grid, _| grid.major_every = 0, Error::MajorEveryIsZero),
+            (|grid, _| grid.top_margin = Some(Length::from_mm(-1.0)), Error::MarginIsNegative(-1.0)),
+            (|grid, _| grid.bottom_margin = Some(Length::from_mm(-1.0)), Error::MarginIsNegative(-1.0)),
+            (|grid, _| grid.left_margin = Some(Length::from_mm(-1.0)), Error::MarginIsNegative(-1.0)),
+            (|grid, _| grid.right_margin = Some(Length::from_mm(-1.0)), Error::MarginIsNegative(-1.0)),
+            (|grid, _| grid.left_margin = Some(Length::from_mm(216.0)), Error::NoRoomBetweenMargins),
+            (|grid, _| grid.top_margin = Some(Length::from_mm(280.0)), Error::NoRoomBetweenMargins)
+        ];
+        assert_rejected(|| graph_grid(5.0, None), cases,
+            |line_set, paper_size| create_graph_grid(line_set, paper_size, &mut Vec::new()));
+    }
+}
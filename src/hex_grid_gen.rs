@@ -0,0 +1,191 @@
+use crate::geometry_def::{LineDef, PointDef, PaperSize, HexGridLineSet, HexOrientation};
+use crate::geometry_def::coord::Coord;
+use crate::geometry_def::length::Length;
+use crate::clipping::Rect;
+use thiserror::Error;
+
+pub fn create_hex_grid(line_set: &HexGridLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let side = match (line_set.side, line_set.flat_to_flat) {
+        (Some(side), None) => side.mm(),
+        (None, Some(flat_to_flat)) => flat_to_flat.mm() / 3.0_f64.sqrt(),
+        (Some(_), Some(_)) => return Err(Error::CellSizeIsAmbiguous),
+        (None, None) => return Err(Error::CellSizeIsMissing)
+    };
+
+    if side <= 0.0 {
+        return Err(Error::CellSizeIsNotPositive(side));
+    }
+
+    let origin = line_set.origin.unwrap_or(PointDef { x: Coord::OffZero(Length::ZERO), y: Coord::OffFarEdge(Length::ZERO) });
+    let (ox, oy) = (origin.x_coord(paper_size), origin.y_coord(paper_size));
+    let page = Rect { left: 0.0, bottom: 0.0, right: paper_size.width, top: paper_size.height };
+
+    // Cell centers are origin + q * a + r * b. Vertex k of a cell is at first_vertex_angle + 60 * k degrees.
+    let flat_to_flat = side * 3.0_f64.sqrt();
+    let (a, b, first_vertex_angle) = match line_set.orientation {
+        HexOrientation::PointyTop => ((flat_to_flat, 0.0), (flat_to_flat / 2.0, 1.5 * side), 30.0_f64),
+        HexOrientation::FlatTop => ((1.5 * side, flat_to_flat / 2.0), (0.0, flat_to_flat), 0.0_f64)
+    };
+    let vertices: Vec<(f64, f64)> = (0..6)
+        .map(|k| (first_vertex_angle + 60.0 * k as f64).to_radians().sin_cos())
+        .map(|(sin, cos)| (side * cos, side * sin))
+        .collect();
+
+    // Find the range of q and r that covers the page, by converting the page corners to (q, r)
+    let det = a.0 * b.1 - a.1 * b.0;
+    let to_qr = |x: f64, y: f64| {
+        let (dx, dy) = (x - ox, y - oy);
+        ((dx * b.1 - dy * b.0) / det, (a.0 * dy - a.1 * dx) / det)
+    };
+    let corners = [to_qr(0.0, 0.0), to_qr(page.right, 0.0), to_qr(0.0, page.top), to_qr(page.right, page.top)];
+    let q_min = corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min).floor() as i64 - 1;
+    let q_max = corners.iter().map(|c| c.0).fold(f64::NEG_INFINITY, f64::max).ceil() as i64 + 1;
+    let r_min = corners.iter().map(|c| c.1).fold(f64::INFINITY, f64::min).floor() as i64 - 1;
+    let r_max = corners.iter().map(|c| c.1).fold(f64::NEG_INFINITY, f64::max).ceil() as i64 + 1;
+
+    // Every edge is shared by two cells. Each cell only draws edges 0, 1 and 2 (from vertex 0 to vertex 3),
+    // the other three belong to the neighbors, so no edge is drawn twice.
+    for r in r_min..=r_max {
+        for q in q_min..=q_max {
+            let cx = ox + q as f64 * a.0 + r as f64 * b.0;
+            let cy = oy + q as f64 * a.1 + r as f64 * b.1;
+            if cx < -side || cx > page.right + side || cy < -side || cy > page.top + side {
+                continue;
+            }
+
+            for k in 0..3 {
+                let (x0, y0) = (cx + vertices[k].0, cy + vertices[k].1);
+                let (x1, y1) = (cx + vertices[k + 1].0, cy + vertices[k + 1].1);
+                if let Some((x0, y0, x1, y1)) = page.clip_segment(x0, y0, x1, y1) {
+                    result.push(LineDef {
+                        start: PointDef::from_mm(x0, y0),
+                        end: PointDef::from_mm(x1, y1),
+                        thickness: line_set.thickness,
+                        color: line_set.color,
+                        dash_pattern: line_set.dash_pattern
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Hexagon size of {0} is not a positive number.")]
+    CellSizeIsNotPositive(f64),
+
+    #[error("Hexagon size must be given either as \"side\" or as \"flat to flat\", not both.")]
+    CellSizeIsAmbiguous,
+
+    #[error("Hexagon size is missing. It must be given either as \"side\" or as \"flat to flat\".")]
+    CellSizeIsMissing,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::CmykDef;
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    fn hex_grid(orientation: HexOrientation) -> HexGridLineSet {
+        HexGridLineSet {
+            orientation,
+            side: Some(Length::from_mm(10.0)),
+            flat_to_flat: None,
+            origin: Some(PointDef::from_mm(50.0, 50.0)),
+            thickness: Length::ZERO,
+            color: CmykDef::BLACK,
+            dash_pattern: None,
+            bounds: None
+        }
+    }
+
+    fn endpoints(line: &LineDef, paper_size: &PaperSize) -> [(i64, i64); 2] {
+        // Rounded to 1/1000 mm, so the same point computed from two cells compares equal
+        let round = |v: f64| (v * 1000.0).round() as i64;
+        let mut ends = [
+            (round(line.start.x_coord(paper_size)), round(line.start.y_coord(paper_size))),
+            (round(line.end.x_coord(paper_size)), round(line.end.y_coord(paper_size)))
+        ];
+        ends.sort();
+        ends
+    }
+
+    #[test]
+    fn shared_edges_are_drawn_once() {
+        let paper_size = &PaperSize { width: 100.0, height: 80.0 };
+        for orientation in [HexOrientation::PointyTop, HexOrientation::FlatTop].iter() {
+            let mut result: Vec<LineDef> = Vec::new();
+            create_hex_grid(&hex_grid(*orientation), paper_size, &mut result).unwrap();
+            assert!(result.len() > 20);
+
+            let mut all_ends: Vec<[(i64, i64); 2]> = result.iter().map(|line| endpoints(line, paper_size)).collect();
+            let num_lines = all_ends.len();
+            all_ends.sort();
+            all_ends.dedup();
+            assert_eq!(all_ends.len(), num_lines, "{:?}: some edges are drawn more than once", orientation);
+        }
+    }
+
+    #[test]
+    fn origin_cell_is_complete() {
+        let paper_size = &PaperSize { width: 100.0, height: 80.0 };
+        let mut result: Vec<LineDef> = Vec::new();
+        create_hex_grid(&hex_grid(HexOrientation::PointyTop), paper_size, &mut result).unwrap();
+
+        // All six edges around the origin are 10mm long and have their middle sqrt(3) * 5 mm away from the origin
+        let around_origin = result.iter().filter(|line| {
+            let mx = (line.start.x_coord(paper_size) + line.end.x_coord(paper_size)) / 2.0;
+            let my = (line.start.y_coord(paper_size) + line.end.y_coord(paper_size)) / 2.0;
+            ((mx - 50.0).hypot(my - 50.0) - 5.0 * 3.0_f64.sqrt()).abs() < 1e-6
+        }).count();
+        assert_eq!(around_origin, 6);
+    }
+
+    #[test]
+    fn flat_to_flat_sets_the_cell_size() {
+        let paper_size = &PaperSize { width: 100.0, height: 80.0 };
+        let mut line_set = hex_grid(HexOrientation::FlatTop);
+        line_set.side = None;
+        line_set.flat_to_flat = Some(Length::from_mm(10.0 * 3.0_f64.sqrt()));
+        let mut result: Vec<LineDef> = Vec::new();
+        create_hex_grid(&line_set, paper_size, &mut result).unwrap();
+
+        let line = &result[result.len() / 2];
+        let length = (line.end.x_coord(paper_size) - line.start.x_coord(paper_size))
+            .hypot(line.end.y_coord(paper_size) - line.start.y_coord(paper_size));
+        assert!((length - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<HexGridLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|grid, _| grid.flat_to_flat = Some(Length::from_mm(10.0)), Error::CellSizeIsAmbiguous),
+            (|grid, _| grid.side = None, Error::CellSizeIsMissing),
+            (|grid, _| grid.side = Some(Length::from_mm(-2.0)), Error::CellSizeIsNotPositive(-2.0))
+        ];
+        assert_rejected(|| hex_grid(HexOrientation::FlatTop), cases,
+            |line_set, paper_size| create_hex_grid(line_set, paper_size, &mut Vec::new()));
+    }
+}
@@ -1,11 +1,27 @@
 use crate::geometry_def::{LineDef, PointDef, PaperSize, HorizontalLineSet};
 use crate::geometry_def::coord::Coord;
+use crate::geometry_def::length::Length;
 use thiserror::Error;
 
 pub fn create_horizontal_lines(line_set: &HorizontalLineSet, paper_size: &PaperSize,
     result: &mut Vec<LineDef>)
     -> Result<(), Error>
 {
+    for y in line_ys(line_set, paper_size)? {
+        result.push(LineDef {
+            start: PointDef { x: Coord::OffZero(Length::ZERO), y: Coord::OffZero(Length::from_mm(y)) },
+            end: PointDef { x: Coord::OffFarEdge(Length::ZERO), y: Coord::OffZero(Length::from_mm(y)) },
+            thickness: line_set.thickness,
+            color: line_set.color,
+            dash_pattern: line_set.dash_pattern
+        });
+    }
+
+    Ok(())
+}
+
+/// Y coordinates of the lines from the top down.
+pub fn line_ys(line_set: &HorizontalLineSet, paper_size: &PaperSize) -> Result<Vec<f64>, Error> {
     if paper_size.width <= 0.0 {
         return Err(Error::PaperWidthIsNotPositive(paper_size.width));
     }
@@ -14,35 +30,34 @@ pub fn create_horizontal_lines(line_set: &HorizontalLineSet, paper_size: &PaperS
         return Err(Error::PaperHeightIsNotPositive(paper_size.height));
     }
 
-    if line_set.y_spacing <= 0.0 {
-        return Err(Error::YSpacingIsNotPositive(line_set.y_spacing));
+    let y_spacing = line_set.y_spacing.mm();
+    if y_spacing <= 0.0 {
+        return Err(Error::YSpacingIsNotPositive(y_spacing));
     }
 
-    if line_set.top_margin <= 0.0 {
-        return Err(Error::TopMarginIsNotPositive(line_set.top_margin));
+    let top_margin = line_set.top_margin.mm();
+    if top_margin <= 0.0 {
+        return Err(Error::TopMarginIsNotPositive(top_margin));
     }
 
-    if line_set.bottom_margin <= 0.0 {
-        return Err(Error::BottomMarginIsNotPositive(line_set.bottom_margin));
+    let bottom_margin = line_set.bottom_margin.mm();
+    if bottom_margin <= 0.0 {
+        return Err(Error::BottomMarginIsNotPositive(bottom_margin));
     }
 
-    let mut y = paper_size.height - line_set.top_margin;
+    let mut result = Vec::new();
+    let mut y = paper_size.height - top_margin;
 
-    while y >= line_set.bottom_margin {
-        result.push(LineDef {
-            start: PointDef { x: Coord::OffZero(0.0), y: Coord::OffZero(y) },
-            end: PointDef { x: Coord::OffFarEdge(0.0), y: Coord::OffZero(y) },
-            thickness: line_set.thickness,
-            color: line_set.color,
-            dash_pattern: line_set.dash_pattern
-        });
-        y -= line_set.y_spacing;
+    while y >= bottom_margin {
+        result.push(y);
+        y -= y_spacing;
     }
 
-    Ok(())
+    Ok(result)
 }
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("Paper width of {0} is not a positive number.")]
     PaperWidthIsNotPositive(f64),
@@ -0,0 +1,163 @@
+use crate::geometry_def::ImageDef;
+use image::{ColorType, GenericImageView, ImageFormat};
+use printpdf::{ImageXObject, Px, ColorSpace, ColorBits};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// A PNG or JPEG image decoded into 8 bit RGB, with the alpha channel apart since PDF keeps it
+/// in a separate soft mask image.
+pub struct LoadedImage {
+    pub width_px: u32,
+    pub height_px: u32,
+    rgb: Vec<u8>,
+    alpha: Option<Vec<u8>>
+}
+
+impl LoadedImage {
+    pub fn load(path: &Path) -> Result<LoadedImage, Error> {
+        let bytes = std::fs::read(path)
+            .map_err(|source| Error::ReadError { path: path.to_path_buf(), source })?;
+        match image::guess_format(&bytes) {
+            Ok(ImageFormat::PNG) | Ok(ImageFormat::JPEG) => (),
+            _ => return Err(Error::UnsupportedFormat(path.to_path_buf()))
+        }
+        let image = image::load_from_memory(&bytes)
+            .map_err(|source| Error::DecodeError { path: path.to_path_buf(), source })?;
+
+        let (width_px, height_px) = image.dimensions();
+        let alpha = match image.color() {
+            ColorType::GrayA(_) | ColorType::RGBA(_) | ColorType::BGRA(_) =>
+                Some(image.to_rgba().pixels().map(|pixel| pixel[3]).collect()),
+            _ => None
+        };
+        Ok(LoadedImage { width_px, height_px, rgb: image.to_rgb().into_raw(), alpha })
+    }
+
+    pub fn xobject(&self) -> ImageXObject {
+        ImageXObject::new(Px(self.width_px as usize), Px(self.height_px as usize), ColorSpace::Rgb, ColorBits::Bit8,
+            true, None, None, self.rgb.clone())
+    }
+
+    /// One 8 bit gray pixel per image pixel, with the alpha channel scaled by the opacity.
+    /// None if the image comes out fully opaque.
+    pub fn soft_mask(&self, opacity: f64) -> Option<Vec<u8>> {
+        let scale = |alpha: u8| (f64::from(alpha) * opacity).round() as u8;
+        match &self.alpha {
+            Some(alpha) => Some(alpha.iter().map(|&a| scale(a)).collect()),
+            None if opacity < 1.0 => Some(vec![scale(u8::MAX); (self.width_px * self.height_px) as usize]),
+            None => None
+        }
+    }
+
+    /// Width and height of the image on the page, in mm.
+    pub fn size(&self, image: &ImageDef) -> Result<(f64, f64), Error> {
+        let aspect_ratio = f64::from(self.width_px) / f64::from(self.height_px);
+        if let Some(width) = image.width {
+            if width.mm() <= 0.0 {
+                return Err(Error::WidthIsNotPositive(width.mm()));
+            }
+        }
+        if let Some(height) = image.height {
+            if height.mm() <= 0.0 {
+                return Err(Error::HeightIsNotPositive(height.mm()));
+            }
+        }
+
+        match (image.width, image.height) {
+            (Some(width), Some(height)) => {
+                let width = width.mm().min(height.mm() * aspect_ratio);
+                Ok((width, width / aspect_ratio))
+            },
+            (Some(width), None) => Ok((width.mm(), width.mm() / aspect_ratio)),
+            (None, Some(height)) => Ok((height.mm() * aspect_ratio, height.mm())),
+            (None, None) => Err(Error::SizeIsNotGiven(image.path.clone()))
+        }
+    }
+}
+
+pub fn opacity(image: &ImageDef) -> Result<f64, Error> {
+    let opacity = image.opacity.unwrap_or(1.0);
+    if !(0.0..=1.0).contains(&opacity) {
+        return Err(Error::OpacityIsOutOfRange(opacity));
+    }
+    Ok(opacity)
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("Can't read image file {path:?}.")]
+    ReadError { path: PathBuf, source: std::io::Error },
+
+    #[error("Image file {0:?} is neither PNG nor JPEG.")]
+    UnsupportedFormat(PathBuf),
+
+    #[error("Image file {path:?} can't be decoded.")]
+    DecodeError { path: PathBuf, source: image::ImageError },
+
+    #[error("Image {0:?} needs a width, a height or both.")]
+    SizeIsNotGiven(PathBuf),
+
+    #[error("Image width of {0} is not a positive number.")]
+    WidthIsNotPositive(f64),
+
+    #[error("Image height of {0} is not a positive number.")]
+    HeightIsNotPositive(f64),
+
+    #[error("Image opacity of {0} is not between 0 and 1.")]
+    OpacityIsOutOfRange(f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::PointDef;
+    use crate::geometry_def::length::Length;
+
+    fn image_def(width: Option<f64>, height: Option<f64>) -> ImageDef {
+        ImageDef {
+            path: PathBuf::from("crest.png"),
+            position: PointDef::from_mm(20.0, 250.0),
+            width: width.map(Length::from_mm),
+            height: height.map(Length::from_mm),
+            opacity: None,
+            rotation: 0.0
+        }
+    }
+
+    fn loaded_image(alpha: Option<Vec<u8>>) -> LoadedImage {
+        LoadedImage { width_px: 4, height_px: 2, rgb: vec![0; 24], alpha }
+    }
+
+    #[test]
+    fn size_keeps_aspect_ratio() {
+        let image = loaded_image(None);
+        assert_eq!(image.size(&image_def(Some(40.0), None)).unwrap(), (40.0, 20.0));
+        assert_eq!(image.size(&image_def(None, Some(10.0))).unwrap(), (20.0, 10.0));
+        // Both given: as large as fits
+        assert_eq!(image.size(&image_def(Some(40.0), Some(10.0))).unwrap(), (20.0, 10.0));
+        assert_eq!(image.size(&image_def(Some(10.0), Some(40.0))).unwrap(), (10.0, 5.0));
+        assert!(matches!(image.size(&image_def(None, None)), Err(Error::SizeIsNotGiven(_))));
+        assert!(matches!(image.size(&image_def(Some(-1.0), None)), Err(Error::WidthIsNotPositive(_))));
+    }
+
+    #[test]
+    fn soft_mask_combines_alpha_and_opacity() {
+        assert_eq!(loaded_image(None).soft_mask(1.0), None);
+        assert_eq!(loaded_image(None).soft_mask(0.5), Some(vec![128; 8]));
+        let alpha = vec![0, 255, 100, 200, 0, 0, 0, 0];
+        assert_eq!(loaded_image(Some(alpha.clone())).soft_mask(1.0), Some(alpha));
+        assert_eq!(loaded_image(Some(vec![200; 8])).soft_mask(0.5), Some(vec![100; 8]));
+    }
+
+    #[test]
+    fn example_png_keeps_its_alpha_channel() {
+        let image = LoadedImage::load(Path::new("test_line_defs/images/crest.png")).unwrap();
+        assert_eq!((image.width_px, image.height_px), (128, 128));
+        assert_eq!(image.rgb.len(), 128 * 128 * 3);
+        let alpha = image.alpha.unwrap();
+        // Transparent corners around an opaque shield
+        assert_eq!(alpha[0], 0);
+        assert_eq!(alpha[64 * 128 + 64], 255);
+    }
+}
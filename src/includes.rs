@@ -0,0 +1,88 @@
+use crate::geometry_def::{IncludedDef, PaperSize};
+use crate::geometry_def::expr;
+use crate::parameters;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// The files being included, from the top level geometry definition down, to catch files that include
+/// themselves directly or through other files.
+pub struct IncludeChain(Vec<PathBuf>);
+
+impl IncludeChain {
+    pub fn new(top_level: &Path) -> IncludeChain {
+        IncludeChain(vec![top_level.canonicalize().unwrap_or_else(|_| top_level.to_path_buf())])
+    }
+
+    pub fn push(&mut self, path: &Path) -> Result<(), Error> {
+        let canonical = path.canonicalize()
+            .map_err(|source| Error::ReadError { path: path.to_path_buf(), source })?;
+        if self.0.contains(&canonical) {
+            return Err(Error::IncludeCycle(path.to_path_buf()));
+        }
+        self.0.push(canonical);
+        Ok(())
+    }
+
+    pub fn pop(&mut self) {
+        self.0.pop();
+    }
+}
+
+/// Reads an included geometry definition. Its own parameter defaults apply to it, and its expressions
+/// see the paper size of the including file.
+pub fn load(path: &Path, paper_size: &PaperSize) -> Result<IncludedDef, Error> {
+    let file = std::fs::File::open(path)
+        .map_err(|source| Error::ReadError { path: path.to_path_buf(), source })?;
+    let mut gdef: serde_yaml::Value = serde_yaml::from_reader(file)
+        .map_err(|source| Error::ParseError { path: path.to_path_buf(), source })?;
+    let parameters = parameters::apply(&mut gdef, &[])
+        .map_err(|source| Error::ParametersError { path: path.to_path_buf(), source })?;
+
+    let context = expr::Context { width: paper_size.width, height: paper_size.height, parameters };
+    expr::with_context(context, || serde_yaml::from_value(gdef))
+        .map_err(|source| Error::ParseError { path: path.to_path_buf(), source })
+}
+
+#[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
+pub enum Error {
+    #[error("Can't read included file {path:?}.")]
+    ReadError { path: PathBuf, source: std::io::Error },
+
+    #[error("Included file {path:?} can't be parsed.")]
+    ParseError { path: PathBuf, source: serde_yaml::Error },
+
+    #[error("Parameters of included file {path:?} are invalid.")]
+    ParametersError { path: PathBuf, source: parameters::Error },
+
+    #[error("File {0:?} includes itself.")]
+    IncludeCycle(PathBuf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn include_cycles_are_errors() {
+        let top_level = Path::new("test_line_defs/letter_included_header.yml");
+        let header = Path::new("test_line_defs/shared/margin_and_header.yml");
+        let mut chain = IncludeChain::new(top_level);
+        chain.push(header).unwrap();
+        assert!(matches!(chain.push(Path::new("test_line_defs/shared/../letter_included_header.yml")),
+            Err(Error::IncludeCycle(_))));
+        chain.pop();
+        chain.push(header).unwrap();
+        assert!(matches!(chain.push(Path::new("test_line_defs/no_such_file.yml")), Err(Error::ReadError { .. })));
+    }
+
+    #[test]
+    fn included_file_sees_the_including_paper_size() {
+        let header = load(Path::new("test_line_defs/shared/margin_and_header.yml"), &PaperSize::LETTER_LANDSCAPE).unwrap();
+        let rule = match &header.line_sets[1] {
+            crate::geometry_def::LineSet::SingleLine(rule) => rule,
+            _ => panic!("The second line set is supposed to be the header rule.")
+        };
+        assert_eq!(rule.start.y_coord(&PaperSize::LETTER_LANDSCAPE), PaperSize::LETTER_LANDSCAPE.height - 25.0);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::geometry_def::{LineDef, PointDef, PaperSize, IsometricLineSet, IsometricOrientation};
+use crate::geometry_def::length::Length;
+use crate::clipping::Rect;
+use crate::line_family;
+use thiserror::Error;
+
+pub fn create_isometric_lines(line_set: &IsometricLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let triangle_side = line_set.triangle_side.mm();
+    if triangle_side <= 0.0 {
+        return Err(Error::TriangleSideIsNotPositive(triangle_side));
+    }
+
+    let margin = |m: Option<Length>| -> Result<f64, Error> {
+        let m = m.map_or(0.0, Length::mm);
+        if m < 0.0 {
+            return Err(Error::MarginIsNegative(m));
+        }
+        Ok(m)
+    };
+
+    let rect = Rect {
+        left: margin(line_set.left_margin)?,
+        bottom: margin(line_set.bottom_margin)?,
+        right: paper_size.width - margin(line_set.right_margin)?,
+        top: paper_size.height - margin(line_set.top_margin)?
+    };
+
+    if rect.width() <= 0.0 || rect.height() <= 0.0 {
+        return Err(Error::NoRoomBetweenMargins);
+    }
+
+    let angles = match line_set.orientation.unwrap_or(IsometricOrientation::Vertical) {
+        IsometricOrientation::Vertical => [90.0, 30.0, 150.0],
+        IsometricOrientation::Horizontal => [0.0, 60.0, 120.0]
+    };
+
+    // Distance between parallel lines is the height of the triangle.
+    // All three families go through the same corner, so they cross at the triangle vertices.
+    let spacing = triangle_side * 3.0_f64.sqrt() / 2.0;
+    let anchor = (rect.left, rect.top);
+
+    for angle in angles.iter() {
+        line_family::for_each_line(*angle, spacing, anchor, &rect, |x0, y0, x1, y1| {
+            result.push(LineDef {
+                start: PointDef::from_mm(x0, y0),
+                end: PointDef::from_mm(x1, y1),
+                thickness: line_set.thickness,
+                color: line_set.color,
+                dash_pattern: line_set.dash_pattern
+            });
+        });
+    }
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Triangle side of {0} is not a positive number.")]
+    TriangleSideIsNotPositive(f64),
+
+    #[error("Margin of {0} is a negative number.")]
+    MarginIsNegative(f64),
+
+    #[error("The margins leave no room for the grid.")]
+    NoRoomBetweenMargins,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::CmykDef;
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    fn isometric(orientation: IsometricOrientation) -> IsometricLineSet {
+        IsometricLineSet {
+            triangle_side: Length::from_mm(10.0),
+            orientation: Some(orientation),
+            top_margin: Some(Length::from_mm(10.0)),
+            bottom_margin: None,
+            left_margin: Some(Length::from_mm(10.0)),
+            right_margin: None,
+            thickness: Length::ZERO,
+            color: CmykDef::BLACK,
+            dash_pattern: None,
+            bounds: None
+        }
+    }
+
+    #[test]
+    fn all_three_families_meet_at_the_top_left_corner() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        for orientation in [IsometricOrientation::Vertical, IsometricOrientation::Horizontal].iter() {
+            let mut result: Vec<LineDef> = Vec::new();
+            create_isometric_lines(&isometric(*orientation), paper_size, &mut result).unwrap();
+
+            let corner = (10.0, paper_size.height - 10.0);
+            let touching_corner = result.iter().filter(|line| {
+                let on_line = |p: &PointDef| (p.x_coord(paper_size) - corner.0).abs() < 1e-6
+                    && (p.y_coord(paper_size) - corner.1).abs() < 1e-6;
+                on_line(&line.start) || on_line(&line.end)
+            }).count();
+            // Two of the three families have a line starting right at the corner, the third one runs along the edge
+            assert!(touching_corner >= 2, "{:?}: only {} lines touch the corner", orientation, touching_corner);
+
+            for line in result.iter() {
+                for p in [&line.start, &line.end].iter() {
+                    assert!(p.x_coord(paper_size) >= 10.0 - 1e-9);
+                    assert!(p.y_coord(paper_size) <= paper_size.height - 10.0 + 1e-9);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn vertical_lines_are_a_triangle_height_apart() {
+        let paper_size = &PaperSize { width: 100.0, height: 100.0 };
+        let mut result: Vec<LineDef> = Vec::new();
+        create_isometric_lines(&isometric(IsometricOrientation::Vertical), paper_size, &mut result).unwrap();
+
+        let mut xs: Vec<f64> = result.iter()
+            .filter(|line| (line.start.x_coord(paper_size) - line.end.x_coord(paper_size)).abs() < 1e-9)
+            .map(|line| line.start.x_coord(paper_size))
+            .collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!(xs.len() > 2);
+        for pair in xs.windows(2) {
+            assert!((pair[1] - pair[0] - 10.0 * 3.0_f64.sqrt() / 2.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<IsometricLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|grid, _| grid.triangle_side = Length::ZERO, Error::TriangleSideIsNotPositive(0.0)),
+            (|grid, _| grid.bottom_margin = Some(Length::from_mm(-1.0)), Error::MarginIsNegative(-1.0)),
+            (|grid, _| grid.right_margin = Some(Length::from_mm(210.0)), Error::NoRoomBetweenMargins)
+        ];
+        assert_rejected(|| isometric(IsometricOrientation::Vertical), cases,
+            |line_set, paper_size| create_isometric_lines(line_set, paper_size, &mut Vec::new()));
+    }
+}
@@ -0,0 +1,85 @@
+use crate::clipping::{Rect, EPSILON};
+
+/// Calls `add_line(x0, y0, x1, y1)` for every line of a family of parallel lines that crosses the rectangle,
+/// with each line already cut at the rectangle edges.
+/// The lines go at `angle_deg` counter-clockwise from the X axis, `spacing` apart (measured perpendicular
+/// to the lines), and one of them passes through the anchor point. The anchor doesn't have to be inside the rectangle.
+pub fn for_each_line<F>(angle_deg: f64, spacing: f64, anchor: (f64, f64), rect: &Rect, mut add_line: F)
+where F: FnMut(f64, f64, f64, f64) {
+    assert!(spacing > 0.0);
+
+    let (sin_a, cos_a) = angle_deg.to_radians().sin_cos();
+    // Direction along the lines and the normal to them
+    let (dx, dy) = (cos_a, sin_a);
+    let (nx, ny) = (-sin_a, cos_a);
+    let (ax, ay) = anchor;
+
+    // Find which lines can cross the rectangle by projecting its corners onto the normal
+    let corners = [(rect.left, rect.bottom), (rect.right, rect.bottom), (rect.right, rect.top), (rect.left, rect.top)];
+    let offsets: Vec<f64> = corners.iter().map(|(x, y)| (x - ax) * nx + (y - ay) * ny).collect();
+    let min_offset = offsets.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max_offset = offsets.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let first = ((min_offset - EPSILON) / spacing).ceil() as i64;
+    let last = ((max_offset + EPSILON) / spacing).floor() as i64;
+
+    // A segment this long, centered next to the rectangle center, covers the whole rectangle
+    let (cx, cy) = ((rect.left + rect.right) / 2.0, (rect.bottom + rect.top) / 2.0);
+    let half_length = rect.width() + rect.height();
+
+    for i in first..=last {
+        let offset = i as f64 * spacing;
+        let (px, py) = (ax + offset * nx, ay + offset * ny);
+        let along = (cx - px) * dx + (cy - py) * dy;
+        let (mx, my) = (px + along * dx, py + along * dy);
+
+        if let Some((x0, y0, x1, y1)) = rect.clip_segment(
+            mx - half_length * dx, my - half_length * dy,
+            mx + half_length * dx, my + half_length * dy) {
+            add_line(x0, y0, x1, y1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RECT: Rect = Rect { left: 0.0, bottom: 0.0, right: 100.0, top: 50.0 };
+
+    fn collect(angle_deg: f64, spacing: f64, anchor: (f64, f64)) -> Vec<(f64, f64, f64, f64)> {
+        let mut lines = Vec::new();
+        for_each_line(angle_deg, spacing, anchor, &RECT, |x0, y0, x1, y1| lines.push((x0, y0, x1, y1)));
+        lines
+    }
+
+    #[test]
+    fn vertical_lines_pass_through_anchor() {
+        let lines = collect(90.0, 10.0, (5.0, 20.0));
+        // x = 5, 15, ..., 95
+        assert_eq!(lines.len(), 10);
+        for (i, (x0, y0, x1, y1)) in lines.iter().enumerate() {
+            let x = 95.0 - 10.0 * i as f64;
+            assert!((x0 - x).abs() < 1e-9 && (x1 - x).abs() < 1e-9, "line {} is at x {}, expected {}", i, x0, x);
+            assert!((y0 - y1).abs() > 49.0);
+        }
+    }
+
+    #[test]
+    fn lines_on_the_edges_are_kept() {
+        let lines = collect(0.0, 10.0, (0.0, 0.0));
+        // y = 0, 10, ..., 50
+        assert_eq!(lines.len(), 6);
+    }
+
+    #[test]
+    fn slanted_lines_stay_inside_and_are_evenly_spaced() {
+        let lines = collect(30.0, 7.0, (200.0, -100.0));
+        assert!(lines.len() > 5);
+        let (nx, ny) = (-30.0_f64.to_radians().sin(), 30.0_f64.to_radians().cos());
+        for &(x0, y0, x1, y1) in lines.iter() {
+            assert!(RECT.contains(x0, y0) && RECT.contains(x1, y1));
+            let offset = (x0 - 200.0) * nx + (y0 + 100.0) * ny;
+            assert!(((offset / 7.0).round() * 7.0 - offset).abs() < 1e-6, "offset {} is not a multiple of the spacing", offset);
+        }
+    }
+}
@@ -0,0 +1,277 @@
+use crate::geometry_def::{LineDef, LineStyleDef, PointDef, PaperSize, HorizontalLogLineSet, VerticalLogLineSet};
+use crate::geometry_def::coord::Coord;
+use crate::geometry_def::length::Length;
+use thiserror::Error;
+
+pub fn create_horizontal_log_lines(line_set: &HorizontalLogLineSet, paper_size: &PaperSize,
+    result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    check_paper_size(paper_size)?;
+
+    let top_margin = line_set.top_margin.mm();
+    if top_margin < 0.0 {
+        return Err(Error::TopMarginIsNegative(top_margin));
+    }
+
+    let bottom_margin = line_set.bottom_margin.mm();
+    if bottom_margin < 0.0 {
+        return Err(Error::BottomMarginIsNegative(bottom_margin));
+    }
+
+    let positions = log_positions(line_set.decades, line_set.subdivisions,
+        bottom_margin, paper_size.height - top_margin)?;
+
+    for (y, is_major) in positions {
+        let style = if is_major { &line_set.major } else { &line_set.minor };
+        result.push(styled_line(
+            PointDef { x: Coord::OffZero(Length::ZERO), y: Coord::OffZero(Length::from_mm(y)) },
+            PointDef { x: Coord::OffFarEdge(Length::ZERO), y: Coord::OffZero(Length::from_mm(y)) },
+            style));
+    }
+
+    Ok(())
+}
+
+pub fn create_vertical_log_lines(line_set: &VerticalLogLineSet, paper_size: &PaperSize,
+    result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    check_paper_size(paper_size)?;
+
+    let left_margin = line_set.left_margin.mm();
+    if left_margin < 0.0 {
+        return Err(Error::LeftMarginIsNegative(left_margin));
+    }
+
+    let right_margin = line_set.right_margin.mm();
+    if right_margin < 0.0 {
+        return Err(Error::RightMarginIsNegative(right_margin));
+    }
+
+    let positions = log_positions(line_set.decades, line_set.subdivisions,
+        left_margin, paper_size.width - right_margin)?;
+
+    for (x, is_major) in positions {
+        let style = if is_major { &line_set.major } else { &line_set.minor };
+        result.push(styled_line(
+            PointDef { x: Coord::OffZero(Length::from_mm(x)), y: Coord::OffZero(Length::ZERO) },
+            PointDef { x: Coord::OffZero(Length::from_mm(x)), y: Coord::OffFarEdge(Length::ZERO) },
+            style));
+    }
+
+    Ok(())
+}
+
+fn check_paper_size(paper_size: &PaperSize) -> Result<(), Error> {
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    Ok(())
+}
+
+fn styled_line(start: PointDef, end: PointDef, style: &LineStyleDef) -> LineDef {
+    LineDef {
+        start,
+        end,
+        thickness: style.thickness,
+        color: style.color,
+        dash_pattern: style.dash_pattern
+    }
+}
+
+/// Values of the minor lines in a decade for each number of subdivisions, from 1 to 9. Minor lines only go
+/// at whole multiples of the decade value, where the values can be read off, and the smaller rulings pick
+/// the usual ones, like 1-2-5 for 3 subdivisions.
+const MINOR_LINE_VALUES: [&[u32]; 9] = [
+    &[],
+    &[5],
+    &[2, 5],
+    &[2, 3, 5],
+    &[2, 3, 5, 7],
+    &[2, 3, 4, 5, 7],
+    &[2, 3, 4, 5, 6, 8],
+    &[2, 3, 4, 5, 6, 7, 8],
+    &[2, 3, 4, 5, 6, 7, 8, 9]
+];
+
+/// Positions of the lines between `start` (value 1) and `end` (value 10 to the power of `decades`),
+/// each with a flag telling whether it's a decade line.
+fn log_positions(decades: u32, subdivisions: u32, start: f64, end: f64) -> Result<Vec<(f64, bool)>, Error> {
+    if decades == 0 {
+        return Err(Error::DecadesIsZero);
+    }
+
+    if !(1..=9).contains(&subdivisions) {
+        return Err(Error::SubdivisionsIsOutOfRange { actual: subdivisions, min: 1, max: 9 });
+    }
+
+    if end <= start {
+        return Err(Error::NoRoomBetweenMargins(end - start));
+    }
+
+    let decade_size = (end - start) / decades as f64;
+    let mut positions = Vec::new();
+    for decade in 0..decades {
+        let decade_start = start + decade as f64 * decade_size;
+        positions.push((decade_start, true));
+        for value in MINOR_LINE_VALUES[subdivisions as usize - 1] {
+            positions.push((decade_start + (*value as f64).log10() * decade_size, false));
+        }
+    }
+    positions.push((end, true));
+
+    Ok(positions)
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Top margin of {0} is a negative number.")]
+    TopMarginIsNegative(f64),
+
+    #[error("Bottom margin of {0} is a negative number.")]
+    BottomMarginIsNegative(f64),
+
+    #[error("Left margin of {0} is a negative number.")]
+    LeftMarginIsNegative(f64),
+
+    #[error("Right margin of {0} is a negative number.")]
+    RightMarginIsNegative(f64),
+
+    #[error("The number of decades must be 1 or more.")]
+    DecadesIsZero,
+
+    #[error("{actual} subdivisions per decade is out of range. It must be from {min} to {max}.")]
+    SubdivisionsIsOutOfRange { actual: u32, min: u32, max: u32 },
+
+    #[error("The margins leave {0} of room for the lines, which is not a positive number.")]
+    NoRoomBetweenMargins(f64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::CmykDef;
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    const STYLE: LineStyleDef = LineStyleDef { thickness: Length::ZERO, color: CmykDef::BLACK, dash_pattern: None };
+
+    fn horizontal_log_lines() -> HorizontalLogLineSet {
+        HorizontalLogLineSet {
+            decades: 1,
+            subdivisions: 9,
+            top_margin: Length::from_mm(29.4),
+            bottom_margin: Length::from_mm(50.0),
+            major: STYLE,
+            minor: STYLE,
+            bounds: None
+        }
+    }
+
+    fn vertical_log_lines() -> VerticalLogLineSet {
+        VerticalLogLineSet {
+            decades: 2,
+            subdivisions: 9,
+            left_margin: Length::from_mm(20.0),
+            right_margin: Length::from_mm(20.0),
+            major: STYLE,
+            minor: STYLE,
+            bounds: None
+        }
+    }
+
+    #[test]
+    fn full_decades_have_lines_at_every_digit() {
+        let positions = log_positions(2, 9, 10.0, 210.0).unwrap();
+        assert_eq!(positions.len(), 19);
+        assert_eq!(positions.iter().filter(|(_, is_major)| *is_major).count(), 3);
+        assert_eq!(positions[0], (10.0, true));
+        assert!((positions[1].0 - (10.0 + 2.0_f64.log10() * 100.0)).abs() < 1e-9);
+        assert!((positions[8].0 - (10.0 + 9.0_f64.log10() * 100.0)).abs() < 1e-9);
+        assert_eq!(positions[9], (110.0, true));
+        assert_eq!(positions[18], (210.0, true));
+    }
+
+    #[test]
+    fn single_subdivision_gives_decade_lines_only() {
+        let positions = log_positions(3, 1, 0.0, 90.0).unwrap();
+        assert_eq!(positions, vec![(0.0, true), (30.0, true), (60.0, true), (90.0, true)]);
+    }
+
+    #[test]
+    fn fewer_subdivisions_keep_minor_lines_at_whole_values() {
+        let minor_values = |subdivisions| -> Vec<f64> {
+            log_positions(1, subdivisions, 0.0, 100.0).unwrap().iter()
+                .filter(|(_, is_major)| !is_major)
+                .map(|(position, _)| 10.0_f64.powf(position / 100.0))
+                .collect()
+        };
+
+        let assert_values = |subdivisions, expected: &[f64]| {
+            let values = minor_values(subdivisions);
+            assert_eq!(values.len(), expected.len(), "Minor lines for {} subdivisions", subdivisions);
+            for (value, expected_value) in values.iter().zip(expected.iter()) {
+                assert!((value - expected_value).abs() < 1e-9, "Minor line at {}, expected {}", value, expected_value);
+            }
+        };
+        assert_values(2, &[5.0]);
+        assert_values(3, &[2.0, 5.0]);
+        assert_values(5, &[2.0, 3.0, 5.0, 7.0]);
+
+        for subdivisions in 1..=9 {
+            let values = minor_values(subdivisions);
+            assert_eq!(values.len(), subdivisions as usize - 1);
+            for value in values {
+                assert!((value - value.round()).abs() < 1e-9, "Minor line at {} for {} subdivisions", value, subdivisions);
+            }
+        }
+    }
+
+    #[test]
+    fn horizontal_log_lines_go_up_from_the_bottom_margin() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_horizontal_log_lines(&horizontal_log_lines(), paper_size, &mut result).unwrap();
+
+        assert_eq!(result.len(), 10);
+        assert_eq!(result[0].start.y_coord(paper_size), 50.0);
+        assert!((result[9].start.y_coord(paper_size) - 250.0).abs() < 1e-9);
+        assert_eq!(result[9].end.x_coord(paper_size), paper_size.width);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let horizontal_cases: &[InvalidCase<HorizontalLogLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|lines, _| lines.top_margin = Length::from_mm(-1.0), Error::TopMarginIsNegative(-1.0)),
+            (|lines, _| lines.bottom_margin = Length::from_mm(-1.0), Error::BottomMarginIsNegative(-1.0)),
+            (|lines, _| lines.decades = 0, Error::DecadesIsZero),
+            (|lines, _| lines.subdivisions = 0, Error::SubdivisionsIsOutOfRange { actual: 0, min: 1, max: 9 }),
+            (|lines, _| lines.subdivisions = 10, Error::SubdivisionsIsOutOfRange { actual: 10, min: 1, max: 9 }),
+            (|lines, _| lines.top_margin = Length::from_mm(250.0), Error::NoRoomBetweenMargins(279.4 - 250.0 - 50.0))
+        ];
+        assert_rejected(horizontal_log_lines, horizontal_cases,
+            |line_set, paper_size| create_horizontal_log_lines(line_set, paper_size, &mut Vec::new()));
+
+        let vertical_cases: &[InvalidCase<VerticalLogLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|lines, _| lines.left_margin = Length::from_mm(-1.0), Error::LeftMarginIsNegative(-1.0)),
+            (|lines, _| lines.right_margin = Length::from_mm(-1.0), Error::RightMarginIsNegative(-1.0)),
+            (|lines, _| lines.right_margin = Length::from_mm(200.0), Error::NoRoomBetweenMargins(215.9 - 200.0 - 20.0))
+        ];
+        assert_rejected(vertical_log_lines, vertical_cases,
+            |line_set, paper_size| create_vertical_log_lines(line_set, paper_size, &mut Vec::new()));
+    }
+}
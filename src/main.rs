@@ -1,14 +1,40 @@
 mod cmd_line;
 mod pdf_gen;
+mod clipping;
+mod line_family;
+mod outline;
+mod builtin_fonts;
+mod embedded_fonts;
+mod font_subset;
+mod images;
+mod parameters;
+mod includes;
 mod geometry_def;
 mod slant_lines_gen;
 mod seyes_lines_gen;
 mod horizontal_lines_gen;
 mod vertical_lines_gen;
+mod dot_grid_gen;
+mod isometric_gen;
+mod hex_grid_gen;
+mod polar_grid_gen;
+mod log_lines_gen;
+mod music_staves_gen;
+mod calligraphy_gen;
+mod genkouyoushi_gen;
+mod character_grid_gen;
+mod penmanship_gen;
+mod graph_grid_gen;
+mod perspective_gen;
+mod parallel_lines_gen;
+mod row_bands_gen;
+#[cfg(test)]
+mod test_support;
 
 use thiserror::Error;
 use cmd_line::CmdLine;
-use geometry_def::{LineDef, GeometryDef, LineSet};
+use geometry_def::{LineDef, GeometryDef, LineSet, PaperSize, RectDef, Shape};
+use std::path::{Path, PathBuf};
 
 fn main() -> Result<(), Error> {
     let opts = match cmd_line::parse_cmd_line()? {
@@ -19,28 +45,115 @@ fn main() -> Result<(), Error> {
         CmdLine::Opts(opt) => opt
     };
 
-    let gdef = std::fs::File::open(opts.input_yaml)?;
-    let gdef: GeometryDef = serde_yaml::from_reader(gdef)?;
+    let gdef = std::fs::File::open(&opts.input_yaml)?;
+    let mut gdef: serde_yaml::Value = serde_yaml::from_reader(gdef)?;
+    let parameters = parameters::apply(&mut gdef, &opts.params)?;
+    let gdef = GeometryDef::from_value(gdef, &parameters)?;
+    let base_dir = opts.input_yaml.parent().unwrap_or_else(|| Path::new(""));
+    let fonts = embedded_fonts::load_all(&gdef.fonts, base_dir)?;
 
-    let mut lines: Vec<LineDef> = Vec::new();
+    let mut include_chain = includes::IncludeChain::new(&opts.input_yaml);
+    let shapes = create_shapes(&gdef.line_sets, gdef.content_area.as_ref(), &gdef.paper_size, base_dir, &mut include_chain)?;
 
-    for line_set in gdef.line_sets {
+    let background = gdef.background.as_ref().map(|background| &background.color);
+    pdf_gen::create_pdf(&gdef.paper_size, background, &fonts, &shapes, opts.num_pages, &opts.output_pdf)?;
+    Ok(())
+}
+
+/// Generates the shapes of the line sets of one geometry definition file, and of the files it includes.
+/// Relative paths in it are relative to `base_dir`.
+fn create_shapes(line_sets: &[LineSet], content_area: Option<&RectDef>, paper_size: &PaperSize, base_dir: &Path,
+                 include_chain: &mut includes::IncludeChain) -> Result<Vec<Shape>, Error> {
+    let mut shapes: Vec<Shape> = Vec::new();
+
+    for line_set in line_sets.iter() {
+        // Line set's own bounds take precedence over the page content area
+        let bounds = match line_set.bounds().or(content_area) {
+            Some(bounds) => Some(clipping::Rect::from_def(bounds, paper_size)?),
+            None => None
+        };
+
+        // Line generators only make lines, the rest make any shapes
+        let mut set_lines: Vec<LineDef> = Vec::new();
+        let mut set_shapes: Vec<Shape> = Vec::new();
         match line_set {
             LineSet::SingleLine(line) =>
-                lines.push(line),
-            LineSet::Slant(slant_lines) =>
-                slant_lines_gen::create_slant_lines(&slant_lines, &gdef.paper_size, &mut lines)?,
+                set_lines.push(line.clone()),
+            LineSet::SingleShape(shape) =>
+                set_shapes.push(shape.clone()),
+            LineSet::Text(text) =>
+                set_shapes.push(Shape::Text(text.clone())),
+            LineSet::Image(image) => {
+                // Image paths are relative to the geometry definition, like font paths
+                let mut image = image.clone();
+                image.path = base_dir.join(&image.path);
+                set_shapes.push(Shape::Image(image));
+            },
+            LineSet::Include(include) => {
+                let path = base_dir.join(&include.path);
+                include_chain.push(&path)?;
+                let included = includes::load(&path, paper_size)?;
+                let included_dir = path.parent().unwrap_or_else(|| Path::new(""));
+                set_shapes = create_shapes(&included.line_sets, included.content_area.as_ref(), paper_size, included_dir, include_chain)
+                    .map_err(|source| Error::IncludedFileError { path: path.clone(), source: Box::new(source) })?;
+                include_chain.pop();
+                if let Some(offset) = &include.offset {
+                    set_shapes.iter_mut().for_each(|shape| shape.translate(offset, paper_size));
+                }
+            },
+            LineSet::Slant(slant_lines) => {
+                slant_lines_gen::create_slant_lines(slant_lines, paper_size, &mut set_lines)?;
+                if let Some(bands) = &slant_lines.bands {
+                    let bands = slant_lines_gen::resolve_bands(bands, line_sets, paper_size)?;
+                    set_lines = slant_lines_gen::confine_to_bands(set_lines, &bands, paper_size);
+                }
+            },
             LineSet::Seyes(seyes_lines) =>
-                seyes_lines_gen::create_seyes_lines(&seyes_lines, &gdef.paper_size, &mut lines)?,
+                seyes_lines_gen::create_seyes_lines(seyes_lines, paper_size, &mut set_lines)?,
             LineSet::HorizontalLines(horiz_lines) =>
-                horizontal_lines_gen::create_horizontal_lines(&horiz_lines, &gdef.paper_size, &mut lines)?,
+                horizontal_lines_gen::create_horizontal_lines(horiz_lines, paper_size, &mut set_lines)?,
             LineSet::VerticalLines(vert_lines) =>
-                vertical_lines_gen::create_vertical_lines(&vert_lines, &gdef.paper_size, &mut lines)?
+                vertical_lines_gen::create_vertical_lines(vert_lines, paper_size, &mut set_lines)?,
+            LineSet::DotGrid(dot_grid) =>
+                dot_grid_gen::create_dot_grid(dot_grid, paper_size, &mut set_shapes)?,
+            LineSet::Isometric(isometric_lines) =>
+                isometric_gen::create_isometric_lines(isometric_lines, paper_size, &mut set_lines)?,
+            LineSet::HexGrid(hex_lines) =>
+                hex_grid_gen::create_hex_grid(hex_lines, paper_size, &mut set_lines)?,
+            LineSet::PolarGrid(polar_grid) =>
+                polar_grid_gen::create_polar_grid(polar_grid, paper_size, &mut set_shapes)?,
+            LineSet::HorizontalLogLines(log_lines) =>
+                log_lines_gen::create_horizontal_log_lines(log_lines, paper_size, &mut set_lines)?,
+            LineSet::VerticalLogLines(log_lines) =>
+                log_lines_gen::create_vertical_log_lines(log_lines, paper_size, &mut set_lines)?,
+            LineSet::MusicStaves(staves) =>
+                music_staves_gen::create_music_staves(staves, paper_size, &mut set_lines)?,
+            LineSet::Calligraphy(calligraphy_lines) =>
+                calligraphy_gen::create_calligraphy_lines(calligraphy_lines, paper_size, &mut set_lines)?,
+            LineSet::Genkouyoushi(genkouyoushi) =>
+                genkouyoushi_gen::create_genkouyoushi(genkouyoushi, paper_size, &mut set_lines)?,
+            LineSet::CharacterGrid(character_grid) =>
+                character_grid_gen::create_character_grid(character_grid, paper_size, &mut set_lines)?,
+            LineSet::Penmanship(penmanship_lines) =>
+                penmanship_gen::create_penmanship_lines(penmanship_lines, paper_size, &mut set_lines)?,
+            LineSet::GraphGrid(graph_grid) =>
+                graph_grid_gen::create_graph_grid(graph_grid, paper_size, &mut set_lines)?,
+            LineSet::Perspective(perspective_lines) =>
+                perspective_gen::create_perspective_lines(perspective_lines, paper_size, &mut set_lines)?,
+            LineSet::ParallelLines(parallel_lines) =>
+                parallel_lines_gen::create_parallel_lines(parallel_lines, paper_size, &mut set_lines)?,
+            LineSet::RowBands(row_bands) =>
+                row_bands_gen::create_row_bands(row_bands, paper_size, &mut set_shapes)?
+        }
+
+        set_shapes.extend(set_lines.into_iter().map(Shape::Line));
+        if let Some(bounds) = bounds {
+            clipping::clip_shapes(&mut set_shapes, &bounds, paper_size);
         }
+        shapes.append(&mut set_shapes);
     }
 
-    pdf_gen::create_pdf(&gdef.paper_size, &lines, opts.num_pages, &opts.output_pdf)?;
-    Ok(())
+    Ok(shapes)
 }
 
 #[derive(Error, Debug)]
@@ -54,6 +167,15 @@ pub enum Error {
     #[error("Geometry definition parsing error.")]
     GeometryDefParseError(#[from] serde_yaml::Error),
 
+    #[error("Geometry definition parameters error.")]
+    ParametersError(#[from] parameters::Error),
+
+    #[error("Geometry definition include error.")]
+    IncludeError(#[from] includes::Error),
+
+    #[error("Error in included file {path:?}.")]
+    IncludedFileError { path: PathBuf, source: Box<Error> },
+
     #[error("Slant line generation error.")]
     SlantLinesGenError(#[from] slant_lines_gen::Error),
 
@@ -66,6 +188,54 @@ pub enum Error {
     #[error("Vertical line generation error.")]
     VerticalLinesGenError(#[from] vertical_lines_gen::Error),
 
+    #[error("Dot grid generation error.")]
+    DotGridGenError(#[from] dot_grid_gen::Error),
+
+    #[error("Isometric grid generation error.")]
+    IsometricGenError(#[from] isometric_gen::Error),
+
+    #[error("Hex grid generation error.")]
+    HexGridGenError(#[from] hex_grid_gen::Error),
+
+    #[error("Polar grid generation error.")]
+    PolarGridGenError(#[from] polar_grid_gen::Error),
+
+    #[error("Log lines generation error.")]
+    LogLinesGenError(#[from] log_lines_gen::Error),
+
+    #[error("Music staves generation error.")]
+    MusicStavesGenError(#[from] music_staves_gen::Error),
+
+    #[error("Calligraphy lines generation error.")]
+    CalligraphyGenError(#[from] calligraphy_gen::Error),
+
+    #[error("Genkouyoushi generation error.")]
+    GenkouyoushiGenError(#[from] genkouyoushi_gen::Error),
+
+    #[error("Character practice grid generation error.")]
+    CharacterGridGenError(#[from] character_grid_gen::Error),
+
+    #[error("Penmanship lines generation error.")]
+    PenmanshipGenError(#[from] penmanship_gen::Error),
+
+    #[error("Graph grid generation error.")]
+    GraphGridGenError(#[from] graph_grid_gen::Error),
+
+    #[error("Perspective lines generation error.")]
+    PerspectiveGenError(#[from] perspective_gen::Error),
+
+    #[error("Parallel lines generation error.")]
+    ParallelLinesGenError(#[from] parallel_lines_gen::Error),
+
+    #[error("Row bands generation error.")]
+    RowBandsGenError(#[from] row_bands_gen::Error),
+
+    #[error("Font loading error.")]
+    FontLoadError(#[from] embedded_fonts::Error),
+
+    #[error("Invalid clipping bounds.")]
+    ClippingError(#[from] clipping::Error),
+
     #[error("Error when generating the PDF from the lines.")]
     PdfGenError(#[from] pdf_gen::Error)
 }
\ No newline at end of file
@@ -0,0 +1,217 @@
+use crate::geometry_def::{LineDef, PointDef, PaperSize, MusicStavesLineSet};
+use crate::clipping::EPSILON;
+use thiserror::Error;
+
+const LINES_PER_STAFF: u32 = 5;
+
+pub fn create_music_staves(line_set: &MusicStavesLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let line_spacing = line_set.line_spacing.mm();
+    if line_spacing <= 0.0 {
+        return Err(Error::LineSpacingIsNotPositive(line_spacing));
+    }
+
+    let staff_gap = line_set.staff_gap.mm();
+    if staff_gap < 0.0 {
+        return Err(Error::StaffGapIsNegative(staff_gap));
+    }
+
+    let top_margin = line_set.top_margin.mm();
+    if top_margin < 0.0 {
+        return Err(Error::TopMarginIsNegative(top_margin));
+    }
+
+    let bottom_margin = line_set.bottom_margin.mm();
+    if bottom_margin < 0.0 {
+        return Err(Error::BottomMarginIsNegative(bottom_margin));
+    }
+
+    let left_margin = line_set.left_margin.mm();
+    if left_margin < 0.0 {
+        return Err(Error::LeftMarginIsNegative(left_margin));
+    }
+
+    let right_margin = line_set.right_margin.mm();
+    if right_margin < 0.0 {
+        return Err(Error::RightMarginIsNegative(right_margin));
+    }
+
+    let left = left_margin;
+    let right = paper_size.width - right_margin;
+    if right <= left {
+        return Err(Error::NoRoomBetweenMargins(right - left));
+    }
+
+    let staff_height = (LINES_PER_STAFF - 1) as f64 * line_spacing;
+    let staff_pitch = staff_height + staff_gap;
+    let top = paper_size.height - top_margin;
+    let room = top - bottom_margin;
+    let max_staves = if room + EPSILON < staff_height {
+        0
+    } else {
+        ((room - staff_height + EPSILON) / staff_pitch).floor() as u32 + 1
+    };
+
+    let is_grand_staff = line_set.grand_staff_brace.is_some();
+    let num_staves = match line_set.staves {
+        Some(staves) if staves > max_staves =>
+            return Err(Error::TooManyStaves { requested: staves, fit: max_staves }),
+        Some(staves) if is_grand_staff && staves % 2 != 0 =>
+            return Err(Error::OddNumberOfGrandStaves(staves)),
+        Some(staves) => staves,
+        // Grand staves only come in pairs
+        None if is_grand_staff => max_staves - max_staves % 2,
+        None => max_staves
+    };
+
+    let staff_top = |staff: u32| top - staff as f64 * staff_pitch;
+
+    for staff in 0..num_staves {
+        for line in 0..LINES_PER_STAFF {
+            let y = staff_top(staff) - line as f64 * line_spacing;
+            result.push(LineDef {
+                start: PointDef::from_mm(left, y),
+                end: PointDef::from_mm(right, y),
+                thickness: line_set.thickness,
+                color: line_set.color,
+                dash_pattern: None
+            });
+        }
+    }
+
+    if let Some(brace) = &line_set.grand_staff_brace {
+        for upper_staff in (0..num_staves).step_by(2) {
+            result.push(LineDef {
+                start: PointDef::from_mm(left, staff_top(upper_staff)),
+                end: PointDef::from_mm(left, staff_top(upper_staff + 1) - staff_height),
+                thickness: brace.thickness,
+                color: brace.color,
+                dash_pattern: brace.dash_pattern
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Line spacing of {0} is not a positive number.")]
+    LineSpacingIsNotPositive(f64),
+
+    #[error("Staff gap of {0} is a negative number.")]
+    StaffGapIsNegative(f64),
+
+    #[error("Top margin of {0} is a negative number.")]
+    TopMarginIsNegative(f64),
+
+    #[error("Bottom margin of {0} is a negative number.")]
+    BottomMarginIsNegative(f64),
+
+    #[error("Left margin of {0} is a negative number.")]
+    LeftMarginIsNegative(f64),
+
+    #[error("Right margin of {0} is a negative number.")]
+    RightMarginIsNegative(f64),
+
+    #[error("The margins leave {0} of room for the staves, which is not a positive number.")]
+    NoRoomBetweenMargins(f64),
+
+    #[error("{requested} staves were requested, but only {fit} fit between the margins.")]
+    TooManyStaves { requested: u32, fit: u32 },
+
+    #[error("Grand staves need an even number of staves, not {0}.")]
+    OddNumberOfGrandStaves(u32),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::{CmykDef, LineStyleDef};
+    use crate::geometry_def::length::Length;
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    fn staves(staves: Option<u32>, grand_staff: bool) -> MusicStavesLineSet {
+        MusicStavesLineSet {
+            line_spacing: Length::from_mm(2.0),
+            staff_gap: Length::from_mm(12.0),
+            staves,
+            top_margin: Length::from_mm(9.4),
+            bottom_margin: Length::from_mm(10.0),
+            left_margin: Length::from_mm(15.0),
+            right_margin: Length::from_mm(15.0),
+            thickness: Length::ZERO,
+            color: CmykDef::BLACK,
+            grand_staff_brace: if grand_staff {
+                Some(LineStyleDef { thickness: Length::from_mm(0.5), color: CmykDef::BLACK, dash_pattern: None })
+            } else {
+                None
+            },
+            bounds: None
+        }
+    }
+
+    #[test]
+    fn auto_fit_staves_between_margins() {
+        // 260mm of room, 8mm staves every 20mm: 13 staves take 12 * 20 + 8 = 248mm
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_music_staves(&staves(None, false), paper_size, &mut result).unwrap();
+
+        assert_eq!(result.len(), 13 * 5);
+        assert_eq!(result[0].start.y_coord(paper_size), 270.0);
+        assert_eq!(result[4].start.y_coord(paper_size), 262.0);
+        assert_eq!(result[5].start.y_coord(paper_size), 250.0);
+        assert!((result[64].start.y_coord(paper_size) - 22.0).abs() < 1e-9);
+        assert_eq!(result[0].start.x_coord(paper_size), 15.0);
+        assert!((result[0].end.x_coord(paper_size) - (paper_size.width - 15.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn grand_staves_are_paired_and_braced() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_music_staves(&staves(None, true), paper_size, &mut result).unwrap();
+
+        // 13 staves fit, but only 6 pairs of them
+        assert_eq!(result.len(), 12 * 5 + 6);
+        let brace = &result[60];
+        assert_eq!(brace.start.x_coord(paper_size), 15.0);
+        assert_eq!(brace.start.y_coord(paper_size), 270.0);
+        assert_eq!(brace.end.y_coord(paper_size), 242.0);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<MusicStavesLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|staves, _| staves.line_spacing = Length::ZERO, Error::LineSpacingIsNotPositive(0.0)),
+            (|staves, _| staves.staff_gap = Length::from_mm(-1.0), Error::StaffGapIsNegative(-1.0)),
+            (|staves, _| staves.top_margin = Length::from_mm(-1.0), Error::TopMarginIsNegative(-1.0)),
+            (|staves, _| staves.bottom_margin = Length::from_mm(-1.0), Error::BottomMarginIsNegative(-1.0)),
+            (|staves, _| staves.left_margin = Length::from_mm(-1.0), Error::LeftMarginIsNegative(-1.0)),
+            (|staves, _| staves.right_margin = Length::from_mm(-1.0), Error::RightMarginIsNegative(-1.0)),
+            (|staves, _| staves.right_margin = Length::from_mm(210.0), Error::NoRoomBetweenMargins(215.9 - 210.0 - 15.0)),
+            (|staves, _| staves.staves = Some(14), Error::TooManyStaves { requested: 14, fit: 13 }),
+            (|staves, _| staves.staves = Some(5), Error::OddNumberOfGrandStaves(5))
+        ];
+        assert_rejected(|| staves(Some(4), true), cases,
+            |line_set, paper_size| create_music_staves(line_set, paper_size, &mut Vec::new()));
+    }
+}
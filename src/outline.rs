@@ -0,0 +1,260 @@
+use crate::geometry_def::{PaperSize, PointDef, Shape};
+use crate::clipping::EPSILON;
+
+/// A point in millimeters off the lower left corner of the paper.
+pub type Point = (f64, f64);
+
+/// Tolerance in mm for flattening curves into straight segments.
+const FLATNESS: f64 = 0.05;
+
+/// Upper limit on the straight segments per curve, so a huge curve doesn't make a huge PDF.
+const MAX_SEGMENTS_PER_CURVE: usize = 256;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Segment {
+    LineTo (Point),
+
+    /// Cubic Bezier curve: first control point, second control point and the end point.
+    CurveTo (Point, Point, Point)
+}
+
+/// Outline of a shape made of straight and cubic Bezier segments, already resolved to millimeters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outline {
+    pub start: Point,
+    pub segments: Vec<Segment>,
+    /// A closed outline goes back from the last point to the start.
+    pub closed: bool
+}
+
+impl Outline {
+    /// Resolves the shape to millimeters on the paper. Returns None for a polyline without points and for text.
+    pub fn from_shape(shape: &Shape, paper_size: &PaperSize) -> Option<Outline> {
+        let point = |p: &PointDef| (p.x_coord(paper_size), p.y_coord(paper_size));
+        Some(match shape {
+            Shape::Line(line) =>
+                Outline::polyline(point(&line.start), &[point(&line.end)], false),
+            Shape::Dot(dot) => {
+                let radius = dot.diameter.mm() / 2.0;
+                Outline::elliptic_arc(point(&dot.center), radius, radius, 0.0, 0.0, 360.0)
+            },
+            Shape::Arc(arc) => {
+                let radius = arc.radius.mm();
+                Outline::elliptic_arc(point(&arc.center), radius, radius, 0.0, arc.start_angle, arc.end_angle)
+            },
+            Shape::Rectangle(rect) => Outline::rectangle(
+                rect.left.resolve(paper_size.width), rect.bottom.resolve(paper_size.height),
+                rect.right.resolve(paper_size.width), rect.top.resolve(paper_size.height),
+                rect.corner_radius.map_or(0.0, |r| r.mm())),
+            Shape::Polyline(polyline) => {
+                let (first, rest) = polyline.points.split_first()?;
+                let rest: Vec<Point> = rest.iter().map(point).collect();
+                Outline::polyline(point(first), &rest, polyline.closed)
+            },
+            Shape::Circle(circle) => {
+                let radius = circle.radius.mm();
+                Outline::elliptic_arc(point(&circle.center), radius, radius, 0.0, 0.0, 360.0)
+            },
+            Shape::Ellipse(ellipse) => Outline::elliptic_arc(point(&ellipse.center),
+                ellipse.x_radius.mm(), ellipse.y_radius.mm(), ellipse.rotation, 0.0, 360.0),
+            Shape::Bezier(bezier) => Outline::bezier(
+                point(&bezier.start), point(&bezier.control_1), point(&bezier.control_2), point(&bezier.end)),
+            Shape::Text(_) | Shape::Image(_) => return None
+        })
+    }
+
+    pub fn polyline(start: Point, rest: &[Point], closed: bool) -> Outline {
+        Outline { start, segments: rest.iter().map(|p| Segment::LineTo(*p)).collect(), closed }
+    }
+
+    /// A rectangle going counter-clockwise from the bottom edge. Corner radius is cut down to half
+    /// the shorter side, and a zero radius gives square corners.
+    pub fn rectangle(left: f64, bottom: f64, right: f64, top: f64, corner_radius: f64) -> Outline {
+        let r = corner_radius.max(0.0).min((right - left).abs() / 2.0).min((top - bottom).abs() / 2.0);
+        if r <= 0.0 {
+            return Outline::polyline((left, bottom), &[(right, bottom), (right, top), (left, top)], true);
+        }
+
+        let mut segments = Vec::new();
+        let corners = [
+            ((right - r, bottom), (right - r, bottom + r), -90.0),
+            ((right, top - r), (right - r, top - r), 0.0),
+            ((left + r, top), (left + r, top - r), 90.0),
+            ((left, bottom + r), (left + r, bottom + r), 180.0)
+        ];
+        for &(edge_end, corner_center, start_angle) in corners.iter() {
+            segments.push(Segment::LineTo(edge_end));
+            segments.extend(arc_segments(corner_center, r, r, 0.0, start_angle, start_angle + 90.0));
+        }
+        Outline { start: (left + r, bottom), segments, closed: true }
+    }
+
+    /// An arc of an ellipse rotated counter-clockwise by `rotation_deg`, going counter-clockwise
+    /// from the start angle to the end angle. The angles are parametric, so they are the real angles
+    /// off the ellipse's own X axis only for a circle. A sweep of 360 degrees or more is a closed ellipse.
+    pub fn elliptic_arc(center: Point, x_radius: f64, y_radius: f64, rotation_deg: f64,
+        start_angle: f64, end_angle: f64) -> Outline
+    {
+        Outline {
+            start: ellipse_point(center, x_radius, y_radius, rotation_deg, start_angle),
+            segments: arc_segments(center, x_radius, y_radius, rotation_deg, start_angle, end_angle),
+            closed: end_angle - start_angle >= 360.0
+        }
+    }
+
+    pub fn bezier(start: Point, control_1: Point, control_2: Point, end: Point) -> Outline {
+        Outline { start, segments: vec![Segment::CurveTo(control_1, control_2, end)], closed: false }
+    }
+
+    /// All the points, including the Bezier control points.
+    pub fn points(&self) -> Vec<Point> {
+        let mut points = vec![self.start];
+        for segment in self.segments.iter() {
+            match *segment {
+                Segment::LineTo(p) => points.push(p),
+                Segment::CurveTo(c1, c2, p) => points.extend_from_slice(&[c1, c2, p])
+            }
+        }
+        points
+    }
+
+    /// Smallest (left, bottom, right, top) box around all the points. The curves never go outside of it.
+    pub fn bounding_box(&self) -> (f64, f64, f64, f64) {
+        self.points().iter().fold(
+            (f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            |(left, bottom, right, top), &(x, y)| (left.min(x), bottom.min(y), right.max(x), top.max(y)))
+    }
+
+    /// Replaces the curves with straight segments and returns the corners, starting with the start point.
+    /// A closed outline doesn't repeat the start point at the end.
+    pub fn flatten(&self) -> Vec<Point> {
+        let mut points = vec![self.start];
+        let mut current = self.start;
+        for segment in self.segments.iter() {
+            match *segment {
+                Segment::LineTo(p) => {
+                    points.push(p);
+                    current = p;
+                },
+                Segment::CurveTo(c1, c2, p) => {
+                    points.extend(flatten_curve(current, c1, c2, p));
+                    current = p;
+                }
+            }
+        }
+
+        if self.closed && points.len() > 1 && distance(points[0], *points.last().unwrap()) <= EPSILON {
+            points.pop();
+        }
+        points
+    }
+}
+
+/// Points along the cubic Bezier curve, without the start point and ending with the end point.
+pub fn flatten_curve(start: Point, control_1: Point, control_2: Point, end: Point) -> Vec<Point> {
+    // The curve is never longer than its control polygon
+    let length = distance(start, control_1) + distance(control_1, control_2) + distance(control_2, end);
+    let num_segments = ((length / FLATNESS).sqrt().ceil() as usize).clamp(1, MAX_SEGMENTS_PER_CURVE);
+    (1..=num_segments)
+        .map(|i| bezier_point(start, control_1, control_2, end, i as f64 / num_segments as f64))
+        .collect()
+}
+
+/// Approximates the elliptic arc with cubic Bezier curves, one per 90 degrees or less.
+fn arc_segments(center: Point, x_radius: f64, y_radius: f64, rotation_deg: f64, start_angle: f64, end_angle: f64)
+    -> Vec<Segment>
+{
+    let num_segments = ((end_angle - start_angle) / 90.0).ceil().max(1.0) as usize;
+    let step = (end_angle - start_angle) / num_segments as f64;
+    // Distance of the control points from the ends, relative to the radius
+    let k = 4.0 / 3.0 * (step.to_radians() / 4.0).tan();
+    let (sin_r, cos_r) = rotation_deg.to_radians().sin_cos();
+    // Tangent at the angle, as long as the radius
+    let tangent = |angle: f64| {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let (dx, dy) = (-x_radius * sin, y_radius * cos);
+        (dx * cos_r - dy * sin_r, dx * sin_r + dy * cos_r)
+    };
+
+    (0..num_segments)
+        .map(|i| {
+            let a0 = start_angle + step * i as f64;
+            let a1 = a0 + step;
+            let (p0, p1) = (ellipse_point(center, x_radius, y_radius, rotation_deg, a0),
+                ellipse_point(center, x_radius, y_radius, rotation_deg, a1));
+            let (t0, t1) = (tangent(a0), tangent(a1));
+            Segment::CurveTo(
+                (p0.0 + k * t0.0, p0.1 + k * t0.1),
+                (p1.0 - k * t1.0, p1.1 - k * t1.1),
+                p1)
+        })
+        .collect()
+}
+
+fn ellipse_point(center: Point, x_radius: f64, y_radius: f64, rotation_deg: f64, angle: f64) -> Point {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (sin_r, cos_r) = rotation_deg.to_radians().sin_cos();
+    let (x, y) = (x_radius * cos, y_radius * sin);
+    (center.0 + x * cos_r - y * sin_r, center.1 + x * sin_r + y * cos_r)
+}
+
+fn bezier_point(p0: Point, c1: Point, c2: Point, p1: Point, t: f64) -> Point {
+    let s = 1.0 - t;
+    let (a, b, c, d) = (s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t);
+    (a * p0.0 + b * c1.0 + c * c2.0 + d * p1.0, a * p0.1 + b * c1.1 + c * c2.1 + d * p1.1)
+}
+
+fn distance(a: Point, b: Point) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_circle_is_closed_with_points_on_the_circle() {
+        let circle = Outline::elliptic_arc((10.0, 20.0), 5.0, 5.0, 0.0, 0.0, 360.0);
+        assert!(circle.closed);
+        assert_eq!(circle.segments.len(), 4);
+        for (x, y) in circle.flatten() {
+            let r = (x - 10.0).hypot(y - 20.0);
+            assert!((r - 5.0).abs() < 0.01, "Point ({}, {}) is {} off the center", x, y, r);
+        }
+    }
+
+    #[test]
+    fn rotated_ellipse_bounding_box() {
+        // Rotated by 90 degrees, the long axis is vertical
+        let ellipse = Outline::elliptic_arc((0.0, 0.0), 10.0, 4.0, 90.0, 0.0, 360.0);
+        let points = ellipse.flatten();
+        let max_x = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+        let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        assert!((max_x - 4.0).abs() < 0.01 && (max_y - 10.0).abs() < 0.01, "Ellipse reaches x {}, y {}", max_x, max_y);
+    }
+
+    #[test]
+    fn rounded_rectangle_keeps_straight_edges() {
+        let rect = Outline::rectangle(0.0, 0.0, 40.0, 20.0, 5.0);
+        assert!(rect.closed);
+        assert_eq!(rect.start, (5.0, 0.0));
+        assert_eq!(rect.segments[0], Segment::LineTo((35.0, 0.0)));
+        let (left, bottom, right, top) = rect.bounding_box();
+        assert!(left.abs() < 1e-9 && bottom.abs() < 1e-9, "Lower left corner at ({}, {})", left, bottom);
+        assert!((right - 40.0).abs() < 1e-9 && (top - 20.0).abs() < 1e-9, "Upper right corner at ({}, {})", right, top);
+
+        let square_corners = Outline::rectangle(0.0, 0.0, 40.0, 20.0, 0.0);
+        assert_eq!(square_corners.flatten(), vec![(0.0, 0.0), (40.0, 0.0), (40.0, 20.0), (0.0, 20.0)]);
+    }
+
+    #[test]
+    fn flattened_bezier_goes_through_the_ends() {
+        let curve = Outline::bezier((0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0));
+        let points = curve.flatten();
+        assert!(points.len() > 4);
+        assert_eq!(points[0], (0.0, 0.0));
+        assert_eq!(*points.last().unwrap(), (10.0, 0.0));
+        let middle = bezier_point((0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0), 0.5);
+        assert_eq!(middle, (5.0, 7.5));
+    }
+}
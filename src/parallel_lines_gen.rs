@@ -0,0 +1,167 @@
+use crate::geometry_def::{LineDef, PointDef, PaperSize, ParallelLineSet, SpacingAxis};
+use crate::clipping::{Rect, EPSILON};
+use crate::line_family;
+use thiserror::Error;
+
+pub fn create_parallel_lines(line_set: &ParallelLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    if !(0.0..=180.0).contains(&line_set.angle) {
+        return Err(Error::AngleIsOutOfRange { actual: line_set.angle, min: 0.0, max: 180.0 });
+    }
+
+    let spacing = line_set.spacing.mm();
+    if spacing <= 0.0 {
+        return Err(Error::SpacingIsNotPositive(spacing));
+    }
+
+    // Spacing along an axis is the perpendicular spacing over the sine of the angle between the lines and the axis
+    let (sin_a, cos_a) = line_set.angle.to_radians().sin_cos();
+    let axis = line_set.spacing_axis.unwrap_or(SpacingAxis::Perpendicular);
+    let perpendicular_spacing = match axis {
+        SpacingAxis::Perpendicular => spacing,
+        SpacingAxis::X => spacing * sin_a.abs(),
+        SpacingAxis::Y => spacing * cos_a.abs()
+    };
+    if perpendicular_spacing < EPSILON {
+        return Err(Error::SpacingAxisIsAlongLines { angle: line_set.angle, axis });
+    }
+
+    let anchor = match &line_set.anchor {
+        Some(anchor) => (anchor.x_coord(paper_size), anchor.y_coord(paper_size)),
+        None => (0.0, paper_size.height)
+    };
+
+    let page = Rect { left: 0.0, bottom: 0.0, right: paper_size.width, top: paper_size.height };
+    line_family::for_each_line(line_set.angle, perpendicular_spacing, anchor, &page, |x0, y0, x1, y1| {
+        result.push(LineDef {
+            start: PointDef::from_mm(x0, y0),
+            end: PointDef::from_mm(x1, y1),
+            thickness: line_set.thickness,
+            color: line_set.color,
+            dash_pattern: line_set.dash_pattern
+        });
+    });
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Angle of {actual} is out of range. It must be between {min} and {max}.")]
+    AngleIsOutOfRange { actual: f64, min: f64, max: f64 },
+
+    #[error("Spacing of {0} is not a positive number.")]
+    SpacingIsNotPositive(f64),
+
+    #[error("Lines at {angle} degrees run along the {axis:?} axis, so the spacing can't be measured along it.")]
+    SpacingAxisIsAlongLines { angle: f64, axis: SpacingAxis },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::CmykDef;
+    use crate::geometry_def::length::Length;
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    fn parallel_lines(angle: f64, spacing: f64, spacing_axis: SpacingAxis, anchor: Option<PointDef>) -> ParallelLineSet {
+        ParallelLineSet {
+            angle,
+            spacing: Length::from_mm(spacing),
+            spacing_axis: Some(spacing_axis),
+            anchor,
+            thickness: Length::ZERO,
+            color: CmykDef::BLACK,
+            dash_pattern: None,
+            bounds: None
+        }
+    }
+
+    /// X coordinates where the lines cross the horizontal line at the given height, in order.
+    fn crossings_at(lines: &[LineDef], paper_size: &PaperSize, y: f64) -> Vec<f64> {
+        let mut xs: Vec<f64> = lines.iter()
+            .filter_map(|l| {
+                let (x0, y0) = (l.start.x_coord(paper_size), l.start.y_coord(paper_size));
+                let (x1, y1) = (l.end.x_coord(paper_size), l.end.y_coord(paper_size));
+                if (y0 - y) * (y1 - y) > 0.0 || y0 == y1 {
+                    None
+                } else {
+                    Some(x0 + (x1 - x0) * (y - y0) / (y1 - y0))
+                }
+            })
+            .collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        xs
+    }
+
+    #[test]
+    fn backhand_lines_spaced_along_x_through_anchor() {
+        let paper_size = &PaperSize { width: 100.0, height: 50.0 };
+        let mut result: Vec<LineDef> = Vec::new();
+        create_parallel_lines(&parallel_lines(120.0, 10.0, SpacingAxis::X, Some(PointDef::from_mm(53.0, 25.0))),
+            paper_size, &mut result).unwrap();
+
+        let xs = crossings_at(&result, paper_size, 25.0);
+        assert_eq!(xs.len(), 10);
+        for (i, x) in xs.iter().enumerate() {
+            assert!((x - (3.0 + 10.0 * i as f64)).abs() < 1e-9, "Crossing {} at x {}", i, x);
+        }
+
+        // Leaning left, the top end of each line is left of its bottom end
+        for line in result.iter() {
+            let (top, bottom) = if line.start.y_coord(paper_size) > line.end.y_coord(paper_size) {
+                (&line.start, &line.end)
+            } else {
+                (&line.end, &line.start)
+            };
+            assert!(top.x_coord(paper_size) < bottom.x_coord(paper_size));
+        }
+    }
+
+    #[test]
+    fn horizontal_lines_spaced_perpendicular() {
+        let paper_size = &PaperSize { width: 100.0, height: 50.0 };
+        let mut result: Vec<LineDef> = Vec::new();
+        create_parallel_lines(&parallel_lines(0.0, 7.0, SpacingAxis::Perpendicular, None), paper_size, &mut result).unwrap();
+
+        // Lines at y = 50, 43, ... 1
+        assert_eq!(result.len(), 8);
+        for line in result.iter() {
+            let y = line.start.y_coord(paper_size);
+            assert!(((50.0 - y) / 7.0 - ((50.0 - y) / 7.0).round()).abs() < 1e-9);
+            assert_eq!(y, line.end.y_coord(paper_size));
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<ParallelLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|lines, _| lines.angle = -1.0, Error::AngleIsOutOfRange { actual: -1.0, min: 0.0, max: 180.0 }),
+            (|lines, _| lines.angle = 180.5, Error::AngleIsOutOfRange { actual: 180.5, min: 0.0, max: 180.0 }),
+            (|lines, _| lines.spacing = Length::ZERO, Error::SpacingIsNotPositive(0.0)),
+            (|lines, _| lines.spacing_axis = Some(SpacingAxis::Y),
+                Error::SpacingAxisIsAlongLines { angle: 90.0, axis: SpacingAxis::Y }),
+            (|lines, _| { lines.angle = 0.0; lines.spacing_axis = Some(SpacingAxis::X); },
+                Error::SpacingAxisIsAlongLines { angle: 0.0, axis: SpacingAxis::X })
+        ];
+        assert_rejected(|| parallel_lines(90.0, 5.0, SpacingAxis::X, None), cases,
+            |line_set, paper_size| create_parallel_lines(line_set, paper_size, &mut Vec::new()));
+    }
+}
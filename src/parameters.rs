@@ -0,0 +1,156 @@
+use serde_yaml::Value;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+const PARAMETERS_KEY: &str = "parameters";
+
+/// Replaces every `$name` value in the geometry definition with the value of the parameter of that name
+/// from its `parameters` section, after the values given on the command line replace the defaults.
+/// A parameter can stand for anything a field takes, e.g. a number, a length like "5mm" or a CMYK color.
+/// A value that really starts with "$" is written with "$$".
+/// Returns the parameter values, for the expressions that refer to parameters by name.
+pub fn apply(gdef: &mut Value, overrides: &[(String, String)]) -> Result<BTreeMap<String, Value>, Error> {
+    let gdef = match gdef {
+        Value::Mapping(gdef) => gdef,
+        _ => return Ok(BTreeMap::new())
+    };
+
+    let key = Value::String(PARAMETERS_KEY.to_string());
+    let mut parameters: BTreeMap<String, Value> = BTreeMap::new();
+    match gdef.get(&key) {
+        Some(Value::Mapping(defaults)) => {
+            for (name, value) in defaults.iter() {
+                match name {
+                    Value::String(name) => parameters.insert(name.clone(), value.clone()),
+                    _ => return Err(Error::InvalidName(format!("{:?}", name)))
+                };
+            }
+        },
+        Some(Value::Null) | None => (),
+        Some(_) => return Err(Error::ParametersAreNotAMap)
+    }
+
+    for (name, value) in overrides {
+        let parameter = parameters.get_mut(name).ok_or_else(|| Error::UnknownOverride(name.clone()))?;
+        *parameter = serde_yaml::from_str(value)
+            .map_err(|source| Error::InvalidOverride { name: name.clone(), source })?;
+    }
+
+    for (section, value) in gdef.iter_mut() {
+        if *section != key {
+            substitute(value, &parameters)?;
+        }
+    }
+    Ok(parameters)
+}
+
+fn substitute(value: &mut Value, parameters: &BTreeMap<String, Value>) -> Result<(), Error> {
+    match value {
+        Value::String(s) => {
+            if let Some(name) = s.strip_prefix('$') {
+                if name.starts_with('$') {
+                    *s = name.to_string();
+                } else {
+                    *value = parameters.get(name).cloned().ok_or_else(|| Error::UnknownParameter(name.to_string()))?;
+                }
+            }
+        },
+        Value::Sequence(values) => {
+            for value in values.iter_mut() {
+                substitute(value, parameters)?;
+            }
+        },
+        Value::Mapping(values) => {
+            for (_, value) in values.iter_mut() {
+                substitute(value, parameters)?;
+            }
+        },
+        _ => ()
+    }
+    Ok(())
+}
+
+/// Parses a command line parameter value of the form "name=value".
+pub fn parse_override(s: &str) -> Result<(String, String), String> {
+    match s.find('=') {
+        Some(pos) if pos > 0 => Ok((s[..pos].trim().to_string(), s[pos + 1..].trim().to_string())),
+        _ => Err(format!("Parameter \"{}\" is not of the form name=value.", s))
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("The parameters section is not a map of names to values.")]
+    ParametersAreNotAMap,
+
+    #[error("Parameter name {0} is not a string.")]
+    InvalidName(String),
+
+    #[error("Unknown parameter \"{0}\".")]
+    UnknownParameter(String),
+
+    #[error("Parameter \"{0}\" given on the command line is not in the parameters section.")]
+    UnknownOverride(String),
+
+    #[error("Value of parameter \"{name}\" given on the command line can't be parsed.")]
+    InvalidOverride { name: String, source: serde_yaml::Error }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GDEF: &str = "
+parameters:
+  spacing: 8mm
+  color: [0.0, 0.0, 0.0, 0.5]
+line sets:
+  - horizontal lines:
+      y spacing: $spacing
+      color cmyk: $color
+      top margin: 10
+  - text:
+      text: $$5 off
+";
+
+    fn applied(overrides: &[(&str, &str)]) -> Result<Value, Error> {
+        let mut gdef: Value = serde_yaml::from_str(GDEF).unwrap();
+        let overrides: Vec<(String, String)> = overrides.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect();
+        apply(&mut gdef, &overrides)?;
+        Ok(gdef)
+    }
+
+    #[test]
+    fn references_take_parameter_values() {
+        let gdef = applied(&[]).unwrap();
+        let lines = &gdef["line sets"][0]["horizontal lines"];
+        assert_eq!(lines["y spacing"], Value::String("8mm".to_string()));
+        assert_eq!(lines["color cmyk"][3].as_f64(), Some(0.5));
+        assert_eq!(lines["top margin"].as_u64(), Some(10));
+        assert_eq!(gdef["line sets"][1]["text"]["text"], Value::String("$5 off".to_string()));
+    }
+
+    #[test]
+    fn command_line_overrides_defaults() {
+        let gdef = applied(&[("spacing", "0.25in"), ("color", "[0.6, 0.2, 0.0, 0.0]")]).unwrap();
+        let lines = &gdef["line sets"][0]["horizontal lines"];
+        assert_eq!(lines["y spacing"], Value::String("0.25in".to_string()));
+        assert_eq!(lines["color cmyk"][0].as_f64(), Some(0.6));
+    }
+
+    #[test]
+    fn unknown_parameters_are_errors() {
+        assert!(matches!(applied(&[("spaceing", "6mm")]), Err(Error::UnknownOverride(_))));
+
+        let mut gdef: Value = serde_yaml::from_str("line sets: [{dot grid: {spacing: $pitch}}]").unwrap();
+        assert!(matches!(apply(&mut gdef, &[]), Err(Error::UnknownParameter(name)) if name == "pitch"));
+    }
+
+    #[test]
+    fn override_parsing() {
+        assert_eq!(parse_override("spacing=6mm"), Ok(("spacing".to_string(), "6mm".to_string())));
+        assert_eq!(parse_override("title=a=b"), Ok(("title".to_string(), "a=b".to_string())));
+        assert!(parse_override("=6mm").is_err());
+        assert!(parse_override("spacing").is_err());
+    }
+}
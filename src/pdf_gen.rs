@@ -1,79 +1,645 @@
-use crate::geometry_def::{PaperSize, LineDef};
+use crate::geometry_def::{PaperSize, Shape, CmykDef, DashPatternDef, LineStyleDef, TextDef, TextAlignment, ImageDef};
+use crate::geometry_def::length::Length;
+use crate::outline::{self, Outline, Segment};
+use crate::builtin_fonts;
+use crate::embedded_fonts::EmbeddedFont;
+use crate::font_subset;
+use crate::images::{self, LoadedImage};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::path::Path;
 use thiserror::Error;
 use printpdf::{PdfDocument, Mm, Point, Line, LineCapStyle, Color, Cmyk, LineDashPattern};
+use printpdf::{BuiltinFont, IndirectFontRef, CurTransMat};
+use printpdf::{Image, ImageXObject, Px, ColorSpace, ColorBits};
 use printpdf::types::PdfLayerReference;
+use lopdf::{Object, ObjectId};
 
 pub const MIN_NUM_PAGES: u32 = 1;
 pub const MAX_NUM_PAGES: u32 = 10000;
 
-pub fn create_pdf(paper_size: &PaperSize, lines: &[LineDef], num_pages: u32, pdf_path: &Path) -> Result<(), Error> {
-    if num_pages < MIN_NUM_PAGES || num_pages > MAX_NUM_PAGES {
-        return Err(Error::InvalidNumberOfPages { num_pages: num_pages, min: MIN_NUM_PAGES, max: MAX_NUM_PAGES });
+pub fn create_pdf(paper_size: &PaperSize, background: Option<&CmykDef>, embedded_fonts: &BTreeMap<String, EmbeddedFont>,
+    shapes: &[Shape], num_pages: u32, pdf_path: &Path)
+    -> Result<(), Error>
+{
+    let pdf = render_pdf(paper_size, background, embedded_fonts, shapes, num_pages)?;
+    std::fs::write(pdf_path, pdf)?;
+    Ok(())
+}
+
+fn render_pdf(paper_size: &PaperSize, background: Option<&CmykDef>, embedded_fonts: &BTreeMap<String, EmbeddedFont>,
+    shapes: &[Shape], num_pages: u32)
+    -> Result<Vec<u8>, Error>
+{
+    if !(MIN_NUM_PAGES..=MAX_NUM_PAGES).contains(&num_pages) {
+        return Err(Error::InvalidNumberOfPages { num_pages, min: MIN_NUM_PAGES, max: MAX_NUM_PAGES });
     }
 
     let (doc, page_idx, layer_idx) = PdfDocument::new("Test page", Mm(paper_size.width), Mm(paper_size.height), "Layer 1");
     let layer = doc.get_page(page_idx).get_layer(layer_idx);
 
-    let add_geometry_to_layer = |layer: &PdfLayerReference| {
+    // Every page is the same, so the outlines are resolved only once
+    let outlines: Vec<(Outline, &Shape)> = shapes.iter()
+        .filter_map(|shape| Outline::from_shape(shape, paper_size).map(|outline| (outline, shape)))
+        .collect();
+    let page = background.map(|_| Outline::rectangle(0.0, 0.0, paper_size.width, paper_size.height, 0.0));
+
+    // Every font is added to the document once, and all the text is checked before anything is drawn.
+    // Fonts from the geometry definition take precedence over the built-in fonts of the same name,
+    // and names that point to the same font file share one copy of it.
+    let mut fonts: HashMap<&str, (TextFont, IndirectFontRef)> = HashMap::new();
+    let mut used_glyphs: BTreeMap<&str, BTreeMap<u16, char>> = BTreeMap::new();
+    for shape in shapes {
+        if let Shape::Text(text) = shape {
+            let name = text.font.as_deref().unwrap_or(builtin_fonts::DEFAULT_FONT);
+            if !fonts.contains_key(name) {
+                let font = if let Some(font) = embedded_fonts.get(name) {
+                    let added = fonts.values().find(|(added, _)| matches!(added, TextFont::Embedded(added) if added.bytes == font.bytes));
+                    let font_ref = match added {
+                        Some((_, font_ref)) => font_ref.clone(),
+                        None => doc.add_external_font(&font.bytes[..])?
+                    };
+                    (TextFont::Embedded(font), font_ref)
+                } else {
+                    let font = builtin_fonts::find(name)
+                        .ok_or_else(|| Error::UnknownFont { name: name.to_string(), known: known_fonts(embedded_fonts) })?;
+                    (TextFont::Builtin(font), doc.add_builtin_font(font)?)
+                };
+                fonts.insert(name, font);
+            }
+
+            let font = fonts[name].0;
+            if let Some(character) = font.missing_char(&text.text) {
+                return Err(Error::UnsupportedCharacter { character, font: name.to_string(), text: text.text.clone() });
+            }
+            if let TextFont::Embedded(font) = font {
+                used_glyphs.entry(name).or_default().extend(text.text.chars().map(|c| (font.glyph_id(c), c)));
+            }
+        }
+    }
+
+    // Every image file is decoded once, however many times it is drawn
+    let mut loaded_images: HashMap<&Path, LoadedImage> = HashMap::new();
+    for shape in shapes {
+        if let Shape::Image(image) = shape {
+            if !loaded_images.contains_key(image.path.as_path()) {
+                loaded_images.insert(&image.path, LoadedImage::load(&image.path)?);
+            }
+        }
+    }
+    let placed_images = shapes.iter()
+        .filter_map(|shape| match shape {
+            Shape::Image(image) => Some(image),
+            _ => None
+        })
+        .map(|image| {
+            let loaded = &loaded_images[image.path.as_path()];
+            Ok(PlacedImage { def: image, image: loaded, size: loaded.size(image)?, soft_mask: loaded.soft_mask(images::opacity(image)?) })
+        })
+        .collect::<Result<Vec<PlacedImage>, images::Error>>()?;
+
+    let add_geometry_to_layer = |layer: &PdfLayerReference, first_page: bool| {
         // Before adding the line, we may need to set the stroke type/color/thickness/etc
         layer.set_line_cap_style(LineCapStyle::Round);
 
-        for line in lines {
-            layer.set_outline_thickness(line.thickness); // In pts, 0 is a special value for exactly 1 device px
-            layer.set_outline_color(Color::Cmyk(Cmyk::new(
-                line.color.0, line.color.1, line.color.2, line.color.3, None))); // 1.0 = 100%
+        if let (Some(page), Some(color)) = (&page, background) {
+            add_fill(layer, page, color);
+        }
 
-            if let Some(dp) = &line.dash_pattern {
-                let mut dash_pattern = LineDashPattern::default();
-                dash_pattern.dash_1 = Some(dp.dash);
-                if let Some(gap) = dp.gap {
-                    dash_pattern.gap_1 = Some(gap);
-                }
-                layer.set_line_dash_pattern(dash_pattern);
-            } else {
-                layer.set_line_dash_pattern(LineDashPattern::default());
+        // All the fills go first, so that shaded areas never cover the lines
+        for (outline, shape) in outlines.iter() {
+            if let Some(color) = shape.fill() {
+                add_fill(layer, outline, &color);
             }
+        }
 
-            let points = vec![
-                (Point::new(Mm(line.start.x_coord(&paper_size)), Mm(line.start.y_coord(&paper_size))), false),
-                (Point::new(Mm(line.end.x_coord(&paper_size)), Mm(line.end.y_coord(&paper_size))), false)
-            ];
-
-            let line = Line {
-                points: points,
-                is_closed: false,
-                has_fill: false,
-                has_stroke: true,
-                is_clipping_path: false
-            };
-    
-            layer.add_shape(line);
+        // Images go over the shading and under the lines
+        for image in placed_images.iter() {
+            add_image(layer, image, first_page, paper_size);
+        }
+
+        for (outline, shape) in outlines.iter() {
+            if let Some(style) = shape.stroke() {
+                add_stroke(layer, outline, &style);
+            }
+        }
+
+        // Text goes on top of everything
+        for shape in shapes {
+            if let Shape::Text(text) = shape {
+                let (font, font_ref) = &fonts[text.font.as_deref().unwrap_or(builtin_fonts::DEFAULT_FONT)];
+                add_text(layer, text, *font, font_ref, paper_size);
+            }
         }
     };
 
-    add_geometry_to_layer(&layer);
+    add_geometry_to_layer(&layer, true);
 
     for _ in 1..num_pages {
         let (page_idx, layer_idx) = doc.add_page(Mm(paper_size.width), Mm(paper_size.height), "Layer 1");
         let layer = doc.get_page(page_idx).get_layer(layer_idx);
-        add_geometry_to_layer(&layer);
+        add_geometry_to_layer(&layer, false);
+    }
+
+    let mut pdf: Vec<u8> = Vec::new();
+    doc.save(&mut std::io::BufWriter::new(&mut pdf))?;
+    if !used_glyphs.is_empty() || !placed_images.is_empty() {
+        let mut subsets: Vec<(&EmbeddedFont, BTreeMap<u16, char>)> = Vec::new();
+        for (name, glyphs) in used_glyphs {
+            let font = &embedded_fonts[name];
+            match subsets.iter_mut().find(|(added, _)| added.bytes == font.bytes) {
+                Some((_, all_glyphs)) => all_glyphs.extend(glyphs),
+                None => subsets.push((font, glyphs))
+            }
+        }
+        let mut doc = lopdf::Document::load_mem(&pdf)?;
+        subset_fonts(&mut doc, &subsets)?;
+        share_images(&mut doc, &placed_images)?;
+        pdf.clear();
+        doc.save_to(&mut pdf)?;
+    }
+    Ok(pdf)
+}
+
+/// A font that text can be drawn with.
+#[derive(Clone, Copy)]
+enum TextFont<'a> {
+    Builtin(BuiltinFont),
+    Embedded(&'a EmbeddedFont)
+}
+
+impl TextFont<'_> {
+    fn missing_char(self, text: &str) -> Option<char> {
+        match self {
+            TextFont::Builtin(_) => builtin_fonts::unsupported_char(text),
+            TextFont::Embedded(font) => font.missing_char(text)
+        }
     }
 
-    let file = std::fs::File::create(pdf_path)?;
-    let mut writer = std::io::BufWriter::new(file);
-    doc.save(&mut writer)?;
+    /// Width of the text in font sizes.
+    fn text_width(self, text: &str) -> f64 {
+        match self {
+            TextFont::Builtin(font) => builtin_fonts::text_width(font, text),
+            TextFont::Embedded(font) => font.text_width(text)
+        }
+    }
+}
+
+/// An image with everything that drawing it on every page needs.
+struct PlacedImage<'a> {
+    def: &'a ImageDef,
+    image: &'a LoadedImage,
+    /// Width and height in mm.
+    size: (f64, f64),
+    soft_mask: Option<Vec<u8>>
+}
+
+fn known_fonts(embedded_fonts: &BTreeMap<String, EmbeddedFont>) -> String {
+    let embedded = embedded_fonts.keys().map(String::as_str);
+    embedded.chain(builtin_fonts::names()).collect::<Vec<&str>>().join(", ")
+}
+
+/// printpdf embeds a font file whole and uncompressed, maps every glyph of it back to Unicode, and writes
+/// glyph widths that don't always match the font. So once the PDF is saved, every font file is swapped for
+/// a compressed subset with just the glyphs that the text uses (given with the characters they show),
+/// and the font gets the widths and the Unicode map of only those glyphs.
+fn subset_fonts(doc: &mut lopdf::Document, fonts: &[(&EmbeddedFont, BTreeMap<u16, char>)]) -> Result<(), Error> {
+    for (font, glyphs) in fonts {
+        let glyph_ids: BTreeSet<u16> = glyphs.keys().copied().collect();
+        let file_id = find_object(doc, |object| matches!(object, Object::Stream(stream) if stream.content == font.bytes))?;
+        let descriptor_id = find_object(doc, |object| refers_to(object, b"FontFile3", file_id))?;
+        let font_id = find_object(doc, |object| {
+            let descendant = object.as_dict()
+                .and_then(|dict| dict.get(b"DescendantFonts"))
+                .and_then(Object::as_array)
+                .ok()
+                .and_then(|fonts| fonts.first());
+            descendant.is_some_and(|descendant| refers_to(descendant, b"FontDescriptor", descriptor_id))
+        })?;
+
+        let subset = font_subset::subset(&font.bytes, &glyph_ids)?;
+        let mut file_dict = lopdf::Dictionary::new();
+        file_dict.set("Length1", subset.len() as i64);
+        let mut file = lopdf::Stream::new(file_dict, subset);
+        file.compress()?;
+        doc.objects.insert(file_id, Object::Stream(file));
+
+        let descriptor = doc.get_object_mut(descriptor_id)?.as_dict_mut()?;
+        descriptor.remove(b"FontFile3");
+        descriptor.set("FontFile2", Object::Reference(file_id));
+
+        let to_unicode_id = doc.get_object(font_id)?.as_dict()?.get(b"ToUnicode")?.as_reference()?;
+        let mut to_unicode = lopdf::Stream::new(lopdf::Dictionary::new(), to_unicode_cmap(glyphs).into_bytes());
+        to_unicode.compress()?;
+        doc.objects.insert(to_unicode_id, Object::Stream(to_unicode));
+
+        // Glyph widths as "glyph id [width]" pairs
+        let widths: Vec<Object> = glyph_ids.iter()
+            .flat_map(|&glyph_id| vec![
+                Object::Integer(i64::from(glyph_id)),
+                Object::Array(vec![Object::Integer(font.glyph_width(glyph_id))])
+            ])
+            .collect();
+        let font_dict = doc.get_object_mut(font_id)?.as_dict_mut()?;
+        let descendant = font_dict.get_mut(b"DescendantFonts")?
+            .as_array_mut()
+            .and_then(|fonts| fonts.first_mut())
+            .ok_or(Error::EmbeddedFontNotFound)?
+            .as_dict_mut()?;
+        descendant.set("W", Object::Array(widths));
+    }
     Ok(())
 }
 
+/// printpdf can neither share an image between pages nor make it see-through, and leaves it uncompressed.
+/// So once the PDF is saved, every page shows the images of the first page instead of its stand-ins,
+/// and the images get compressed and get soft masks for their alpha channel and opacity. printpdf names
+/// the images of every page X0, X1, ... in the order they are drawn.
+fn share_images(doc: &mut lopdf::Document, placed_images: &[PlacedImage]) -> Result<(), Error> {
+    if placed_images.is_empty() {
+        return Ok(());
+    }
+
+    let pages: Vec<ObjectId> = doc.get_pages().values().copied().collect();
+    let names: Vec<Vec<u8>> = (0..placed_images.len()).map(|i| format!("X{}", i).into_bytes()).collect();
+    let first_page_images = match pages.first() {
+        Some(&first_page) => {
+            let xobjects = page_xobjects(doc, first_page)?;
+            names.iter()
+                .map(|name| Ok(xobjects.get(name)?.as_reference()?))
+                .collect::<Result<Vec<ObjectId>, Error>>()?
+        },
+        None => return Ok(())
+    };
+
+    for &page in pages.iter().skip(1) {
+        let xobjects = page_xobjects(doc, page)?;
+        let mut stand_ins: Vec<ObjectId> = Vec::new();
+        for (name, &image_id) in names.iter().zip(first_page_images.iter()) {
+            stand_ins.push(xobjects.get(name)?.as_reference()?);
+            xobjects.set(name.clone(), Object::Reference(image_id));
+        }
+        for stand_in in stand_ins {
+            doc.objects.remove(&stand_in);
+        }
+    }
+
+    for (placed, &image_id) in placed_images.iter().zip(first_page_images.iter()) {
+        doc.get_object_mut(image_id)?.as_stream_mut()?.compress()?;
+        if let Some(soft_mask) = &placed.soft_mask {
+            let mut mask_dict = lopdf::Dictionary::new();
+            mask_dict.set("Type", "XObject");
+            mask_dict.set("Subtype", "Image");
+            mask_dict.set("Width", i64::from(placed.image.width_px));
+            mask_dict.set("Height", i64::from(placed.image.height_px));
+            mask_dict.set("ColorSpace", "DeviceGray");
+            mask_dict.set("BitsPerComponent", 8);
+            let mut mask = lopdf::Stream::new(mask_dict, soft_mask.clone());
+            mask.compress()?;
+            let mask_id = doc.add_object(mask);
+            doc.get_object_mut(image_id)?.as_stream_mut()?.dict.set("SMask", Object::Reference(mask_id));
+        }
+    }
+    Ok(())
+}
+
+fn page_xobjects(doc: &mut lopdf::Document, page_id: ObjectId) -> Result<&mut lopdf::Dictionary, Error> {
+    let resources_id = doc.get_object(page_id)?.as_dict()?.get(b"Resources")?.as_reference()?;
+    Ok(doc.get_object_mut(resources_id)?.as_dict_mut()?.get_mut(b"XObject")?.as_dict_mut()?)
+}
+
+/// CMap that lets PDF viewers copy and search the text, by mapping glyph ids back to characters.
+fn to_unicode_cmap(glyphs: &BTreeMap<u16, char>) -> String {
+    let mut cmap = String::from(concat!(
+        "/CIDInit /ProcSet findresource begin\n",
+        "12 dict begin\n",
+        "begincmap\n",
+        "/CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n",
+        "/CMapName /Adobe-Identity-UCS def\n",
+        "/CMapType 2 def\n",
+        "1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n"));
+
+    // A "bfchar" block can map at most 100 glyphs
+    let glyphs: Vec<(&u16, &char)> = glyphs.iter().collect();
+    for block in glyphs.chunks(100) {
+        cmap += &format!("{} beginbfchar\n", block.len());
+        for (glyph_id, c) in block {
+            let utf16: String = c.encode_utf16(&mut [0; 2]).iter().map(|unit| format!("{:04X}", unit)).collect();
+            cmap += &format!("<{:04X}> <{}>\n", glyph_id, utf16);
+        }
+        cmap += "endbfchar\n";
+    }
+
+    cmap += "endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n";
+    cmap
+}
+
+fn find_object(doc: &lopdf::Document, is_wanted: impl Fn(&Object) -> bool) -> Result<ObjectId, Error> {
+    doc.objects.iter()
+        .find(|(_, object)| is_wanted(object))
+        .map(|(&id, _)| id)
+        .ok_or(Error::EmbeddedFontNotFound)
+}
+
+/// Whether the object is a dictionary with a reference to the given object under the key.
+fn refers_to(object: &Object, key: &[u8], id: ObjectId) -> bool {
+    object.as_dict()
+        .and_then(|dict| dict.get(key))
+        .and_then(Object::as_reference)
+        .is_ok_and(|reference| reference == id)
+}
+
+/// Fills the area inside the outline. An open outline is filled as if it were closed.
+fn add_fill(layer: &PdfLayerReference, outline: &Outline, color: &CmykDef) {
+    layer.set_fill_color(cmyk_color(color));
+    layer.add_shape(Line {
+        points: outline_points(outline),
+        is_closed: outline.closed,
+        has_fill: true,
+        has_stroke: false,
+        is_clipping_path: false
+    });
+}
+
+fn add_stroke(layer: &PdfLayerReference, outline: &Outline, style: &LineStyleDef) {
+    set_stroke(layer, style.thickness.pt(), &style.color, &style.dash_pattern);
+    layer.add_shape(Line {
+        points: outline_points(outline),
+        is_closed: outline.closed,
+        has_fill: false,
+        has_stroke: true,
+        is_clipping_path: false
+    });
+}
+
+/// Draws the text at the origin of a coordinate system that is moved to the text position, turned,
+/// shifted left for the alignment, and scaled to the font size, since printpdf only takes whole point font sizes.
+fn add_text(layer: &PdfLayerReference, text: &TextDef, font: TextFont, font_ref: &IndirectFontRef,
+    paper_size: &PaperSize)
+{
+    let font_size_pt = text.font_size.pt();
+    let width = font.text_width(&text.text) * text.font_size.mm();
+    let shift = match text.alignment.unwrap_or(TextAlignment::Left) {
+        TextAlignment::Left => 0.0,
+        TextAlignment::Center => -width / 2.0,
+        TextAlignment::Right => -width
+    };
+
+    layer.save_graphics_state();
+    layer.set_ctm(CurTransMat::Translate(Mm(text.position.x_coord(paper_size)), Mm(text.position.y_coord(paper_size))));
+    if text.rotation != 0.0 {
+        layer.set_ctm(CurTransMat::Rotate(text.rotation)); // Counter-clockwise, despite what printpdf says
+    }
+    if shift != 0.0 {
+        layer.set_ctm(CurTransMat::Translate(Mm(shift), Mm(0.0)));
+    }
+    layer.set_ctm(CurTransMat::Scale(font_size_pt, font_size_pt));
+    layer.set_fill_color(cmyk_color(&text.color));
+
+    layer.begin_text_section();
+    layer.set_font(font_ref, 1);
+    layer.write_text(text.text.clone(), font_ref);
+    layer.end_text_section();
+    layer.restore_graphics_state();
+}
+
+/// Draws the image in a coordinate system that is moved to the image position and turned. printpdf puts
+/// a copy of the image on every page that shows it, so only the first page gets the pixels, and the other
+/// pages get a one pixel stand-in that `share_images` swaps for the first page's image.
+fn add_image(layer: &PdfLayerReference, image: &PlacedImage, first_page: bool, paper_size: &PaperSize) {
+    let xobject = if first_page {
+        image.image.xobject()
+    } else {
+        ImageXObject::new(Px(1), Px(1), ColorSpace::Rgb, ColorBits::Bit8, false, None, None, vec![0; 3])
+    };
+
+    // At 72 dpi, printpdf makes every pixel of the image one point
+    let (width, height) = image.size;
+    let scale_x = Length::from_mm(width).pt() / xobject.width.0 as f64;
+    let scale_y = Length::from_mm(height).pt() / xobject.height.0 as f64;
+
+    layer.save_graphics_state();
+    layer.set_ctm(CurTransMat::Translate(Mm(image.def.position.x_coord(paper_size)), Mm(image.def.position.y_coord(paper_size))));
+    if image.def.rotation != 0.0 {
+        layer.set_ctm(CurTransMat::Rotate(image.def.rotation)); // Counter-clockwise, despite what printpdf says
+    }
+    Image::from(xobject).add_to_layer(layer.clone(), None, None, None, Some(scale_x), Some(scale_y), Some(72.0));
+    layer.restore_graphics_state();
+}
+
+/// In printpdf points, the flag means that the next point is a Bezier control point.
+fn outline_points(outline: &Outline) -> Vec<(Point, bool)> {
+    let point = |(x, y): (f64, f64)| Point::new(Mm(x), Mm(y));
+    let next_is_curve = |i: usize| matches!(outline.segments.get(i), Some(Segment::CurveTo(..)));
+
+    let mut points = Vec::with_capacity(outline.segments.len() * 3 + 1);
+    let mut current = outline.start;
+    points.push((point(current), next_is_curve(0)));
+    for (i, segment) in outline.segments.iter().enumerate() {
+        match *segment {
+            Segment::LineTo(p) => points.push((point(p), next_is_curve(i + 1))),
+            Segment::CurveTo(c1, c2, p) if c1 == c2 => {
+                // printpdf mistakes equal control points for the "y" curve shorthand, so draw it straight
+                if let Some(last) = points.last_mut() {
+                    last.1 = false;
+                }
+                let flattened = outline::flatten_curve(current, c1, c2, p);
+                let last_idx = flattened.len() - 1;
+                for (j, q) in flattened.into_iter().enumerate() {
+                    points.push((point(q), j == last_idx && next_is_curve(i + 1)));
+                }
+            },
+            Segment::CurveTo(c1, c2, p) => {
+                points.push((point(c1), true));
+                points.push((point(c2), false));
+                points.push((point(p), next_is_curve(i + 1)));
+            }
+        }
+        current = match *segment {
+            Segment::LineTo(p) | Segment::CurveTo(_, _, p) => p
+        };
+    }
+    points
+}
+
+fn set_stroke(layer: &PdfLayerReference, thickness_pt: f64, color: &CmykDef, dash_pattern: &Option<DashPatternDef>) {
+    layer.set_outline_thickness(thickness_pt); // In pts, 0 is a special value for exactly 1 device px
+    layer.set_outline_color(cmyk_color(color));
+
+    if let Some(dp) = dash_pattern {
+        let mut dash_pattern = LineDashPattern::default();
+        dash_pattern.dash_1 = Some(dp.dash);
+        if let Some(gap) = dp.gap {
+            dash_pattern.gap_1 = Some(gap);
+        }
+        layer.set_line_dash_pattern(dash_pattern);
+    } else {
+        layer.set_line_dash_pattern(LineDashPattern::default());
+    }
+}
+
+fn cmyk_color(color: &CmykDef) -> Color {
+    Color::Cmyk(Cmyk::new(color.0, color.1, color.2, color.3, None)) // 1.0 = 100%
+}
+
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("Invalid number of pages {num_pages}. Must be between {min} and {max}.")]
     InvalidNumberOfPages { num_pages: u32, min: u32, max: u32 },
 
+    #[error("Unknown font \"{name}\". Known fonts are: {known}.")]
+    UnknownFont { name: String, known: String },
+
+    #[error("Character {character:?} in text \"{text}\" can't be shown with font \"{font}\".")]
+    UnsupportedCharacter { character: char, font: String, text: String },
+
+    #[error("Can't find an embedded font in the saved PDF.")]
+    EmbeddedFontNotFound,
+
+    #[error("Image error.")]
+    ImageError(#[from] images::Error),
+
+    #[error("Error when subsetting an embedded font.")]
+    FontSubsetError(#[from] font_subset::Error),
+
+    #[error("Error when replacing embedded fonts with their subsets or sharing images between pages.")]
+    FontReplaceError(#[from] lopdf::Error),
+
     #[error("Error when constructing or saving the PDF.")]
     PdfError(#[from] printpdf::errors::Error),
 
     #[error("File system I/O error.")]
     IOError(#[from] std::io::Error)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::PointDef;
+    use std::path::PathBuf;
+
+    fn text(text: &str, font: &str) -> Shape {
+        Shape::Text(TextDef {
+            text: text.to_string(),
+            position: PointDef::from_mm(20.0, 250.0),
+            font_size: Length::from_mm(5.0),
+            font: Some(font.to_string()),
+            alignment: None,
+            rotation: 0.0,
+            color: CmykDef::BLACK
+        })
+    }
+
+    /// The descendant font dictionaries of the fonts embedded into the PDF.
+    fn embedded_fonts(pdf: &lopdf::Document) -> Vec<&lopdf::Dictionary> {
+        pdf.objects.values()
+            .filter_map(|object| object.as_dict().ok())
+            .filter_map(|dict| dict.get(b"DescendantFonts").and_then(Object::as_array).ok())
+            .filter_map(|fonts| fonts.first().and_then(|font| font.as_dict().ok()))
+            .collect()
+    }
+
+    /// Glyph ids that the /W array of the descendant font gives widths for.
+    fn glyphs_with_widths(font: &lopdf::Dictionary) -> BTreeSet<u16> {
+        font.get(b"W").and_then(Object::as_array).unwrap()
+            .chunks(2)
+            .map(|pair| pair[0].as_i64().unwrap() as u16)
+            .collect()
+    }
+
+    #[test]
+    fn fonts_are_swapped_for_subsets_with_their_widths_and_unicode_map() {
+        let font = EmbeddedFont::load(Path::new("test_line_defs/fonts/DejaVuSerif.ttf")).unwrap();
+        let (doc, page_idx, layer_idx) = PdfDocument::new("Test", Mm(100.0), Mm(100.0), "Layer 1");
+        let font_ref = doc.add_external_font(&font.bytes[..]).unwrap();
+        let layer = doc.get_page(page_idx).get_layer(layer_idx);
+        layer.use_text("Hi", 12, Mm(10.0), Mm(10.0), &font_ref);
+        let mut pdf: Vec<u8> = Vec::new();
+        doc.save(&mut std::io::BufWriter::new(&mut pdf)).unwrap();
+
+        let mut pdf = lopdf::Document::load_mem(&pdf).unwrap();
+        let glyphs: BTreeMap<u16, char> = "Hi".chars().map(|c| (font.glyph_id(c), c)).collect();
+        subset_fonts(&mut pdf, &[(&font, glyphs.clone())]).unwrap();
+
+        let embedded = embedded_fonts(&pdf);
+        assert_eq!(embedded.len(), 1);
+        let descriptor_id = embedded[0].get(b"FontDescriptor").and_then(Object::as_reference).unwrap();
+        let descriptor = pdf.get_object(descriptor_id).and_then(Object::as_dict).unwrap();
+        assert!(descriptor.get(b"FontFile3").is_err());
+        let file_id = descriptor.get(b"FontFile2").and_then(Object::as_reference).unwrap();
+        let file = pdf.get_object(file_id).and_then(Object::as_stream).unwrap();
+        let subset = font_subset::subset(&font.bytes, &glyphs.keys().copied().collect()).unwrap();
+        assert_eq!(file.decompressed_content().unwrap(), subset);
+        assert_eq!(file.dict.get(b"Length1").and_then(Object::as_i64).unwrap(), subset.len() as i64);
+
+        let widths = embedded[0].get(b"W").and_then(Object::as_array).unwrap();
+        let expected: Vec<i64> = glyphs.keys()
+            .flat_map(|&glyph_id| vec![i64::from(glyph_id), font.glyph_width(glyph_id)])
+            .collect();
+        let actual: Vec<i64> = widths.chunks(2)
+            .flat_map(|pair| vec![pair[0].as_i64().unwrap(), pair[1].as_array().unwrap()[0].as_i64().unwrap()])
+            .collect();
+        assert_eq!(actual, expected);
+
+        let type0 = pdf.objects.values()
+            .filter_map(|object| object.as_dict().ok())
+            .find(|dict| dict.get(b"DescendantFonts").is_ok())
+            .unwrap();
+        let to_unicode_id = type0.get(b"ToUnicode").and_then(Object::as_reference).unwrap();
+        let to_unicode = pdf.get_object(to_unicode_id).and_then(Object::as_stream).unwrap();
+        let cmap = String::from_utf8(to_unicode.decompressed_content().unwrap()).unwrap();
+        assert!(cmap.contains("2 beginbfchar\n"));
+        assert!(cmap.contains(&format!("<{:04X}> <0048>\n", font.glyph_id('H'))));
+        assert!(cmap.contains(&format!("<{:04X}> <0069>\n", font.glyph_id('i'))));
+    }
+
+    #[test]
+    fn pages_share_one_image_with_a_soft_mask() {
+        let shapes = vec![Shape::Image(ImageDef {
+            path: PathBuf::from("test_line_defs/images/crest.png"),
+            position: PointDef::from_mm(20.0, 230.0),
+            width: None,
+            height: Some(Length::from_mm(24.0)),
+            opacity: None,
+            rotation: 0.0
+        })];
+        let pdf = render_pdf(&PaperSize::LETTER_PORTRAIT, None, &BTreeMap::new(), &shapes, 2).unwrap();
+        let mut pdf = lopdf::Document::load_mem(&pdf).unwrap();
+
+        let pages: Vec<ObjectId> = pdf.get_pages().values().copied().collect();
+        assert_eq!(pages.len(), 2);
+        let mut page_images: Vec<ObjectId> = Vec::new();
+        for page in pages {
+            let xobjects = page_xobjects(&mut pdf, page).unwrap();
+            assert_eq!(xobjects.len(), 1);
+            page_images.extend(xobjects.iter().map(|(_, image)| image.as_reference().unwrap()));
+        }
+        assert_eq!(page_images[0], page_images[1]);
+
+        let image = pdf.get_object(page_images[0]).and_then(Object::as_stream).unwrap();
+        let mask_id = image.dict.get(b"SMask").and_then(Object::as_reference).unwrap();
+        let mask = pdf.get_object(mask_id).and_then(Object::as_stream).unwrap();
+        assert_eq!(mask.dict.get(b"Width").and_then(Object::as_i64).unwrap(), 128);
+
+        // The stand-ins of the other pages are gone, leaving the image and its mask
+        let images = pdf.objects.values()
+            .filter_map(|object| object.as_stream().ok())
+            .filter(|stream| stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(&b"Image"[..]))
+            .count();
+        assert_eq!(images, 2);
+    }
+
+    #[test]
+    fn font_names_of_one_file_share_its_subset() {
+        let font = || EmbeddedFont::load(Path::new("test_line_defs/fonts/DejaVuSerif.ttf")).unwrap();
+        let fonts: BTreeMap<String, EmbeddedFont> = vec![("Body".to_string(), font()), ("Heading".to_string(), font())]
+            .into_iter().collect();
+        let shapes = vec![text("abc", "Heading"), text("xyz", "Body")];
+        let pdf = render_pdf(&PaperSize::LETTER_PORTRAIT, None, &fonts, &shapes, 1).unwrap();
+        let pdf = lopdf::Document::load_mem(&pdf).unwrap();
+
+        let embedded = embedded_fonts(&pdf);
+        assert_eq!(embedded.len(), 1);
+        let expected: BTreeSet<u16> = "abcxyz".chars().map(|c| fonts["Body"].glyph_id(c)).collect();
+        assert_eq!(glyphs_with_widths(embedded[0]), expected);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::geometry_def::{LineDef, LineStyleDef, PointDef, PaperSize, PenmanshipLineSet};
+use crate::geometry_def::coord::Coord;
+use crate::geometry_def::length::Length;
+use crate::clipping::EPSILON;
+use thiserror::Error;
+
+pub fn create_penmanship_lines(line_set: &PenmanshipLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let writing_height = line_set.writing_height.mm();
+    if writing_height <= 0.0 {
+        return Err(Error::WritingHeightIsNotPositive(writing_height));
+    }
+
+    let group_gap = line_set.group_gap.map_or(0.0, |gap| gap.mm());
+    if group_gap < 0.0 {
+        return Err(Error::GroupGapIsNegative(group_gap));
+    }
+
+    let top_margin = line_set.top_margin.mm();
+    if top_margin < 0.0 {
+        return Err(Error::TopMarginIsNegative(top_margin));
+    }
+
+    let bottom_margin = line_set.bottom_margin.mm();
+    if bottom_margin < 0.0 {
+        return Err(Error::BottomMarginIsNegative(bottom_margin));
+    }
+
+    let descender_depth = line_set.descender_depth.map_or(writing_height / 2.0, |depth| depth.mm());
+    if descender_depth <= 0.0 {
+        return Err(Error::DescenderDepthIsNotPositive(descender_depth));
+    }
+
+    let descender = if line_set.descender_line.is_some() { descender_depth } else { 0.0 };
+    let group_height = writing_height + descender;
+    let group_pitch = group_height + group_gap;
+
+    let top = paper_size.height - top_margin;
+    let room = top - bottom_margin;
+    let num_groups = if room + EPSILON < group_height {
+        0
+    } else {
+        ((room - group_height + EPSILON) / group_pitch).floor() as u32 + 1
+    };
+
+    for group in 0..num_groups {
+        let top_y = top - group as f64 * group_pitch;
+        let baseline_y = top_y - writing_height;
+        result.push(guideline(top_y, &line_set.top_line));
+        result.push(guideline(top_y - writing_height / 2.0, &line_set.midline));
+        result.push(guideline(baseline_y, &line_set.baseline));
+        if let Some(style) = &line_set.descender_line {
+            result.push(guideline(baseline_y - descender, style));
+        }
+    }
+
+    Ok(())
+}
+
+fn guideline(y: f64, style: &LineStyleDef) -> LineDef {
+    LineDef {
+        start: PointDef { x: Coord::OffZero(Length::ZERO), y: Coord::OffZero(Length::from_mm(y)) },
+        end: PointDef { x: Coord::OffFarEdge(Length::ZERO), y: Coord::OffZero(Length::from_mm(y)) },
+        thickness: style.thickness,
+        color: style.color,
+        dash_pattern: style.dash_pattern
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Writing height of {0} is not a positive number.")]
+    WritingHeightIsNotPositive(f64),
+
+    #[error("Descender depth of {0} is not a positive number.")]
+    DescenderDepthIsNotPositive(f64),
+
+    #[error("Group gap of {0} is a negative number.")]
+    GroupGapIsNegative(f64),
+
+    #[error("Top margin of {0} is a negative number.")]
+    TopMarginIsNegative(f64),
+
+    #[error("Bottom margin of {0} is a negative number.")]
+    BottomMarginIsNegative(f64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::{CmykDef, DashPatternDef};
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    const SOLID: LineStyleDef = LineStyleDef { thickness: Length::ZERO, color: CmykDef::BLACK, dash_pattern: None };
+    const DASHED: LineStyleDef = LineStyleDef {
+        thickness: Length::ZERO,
+        color: CmykDef::BLACK,
+        dash_pattern: Some(DashPatternDef { dash: 3, gap: Some(2) })
+    };
+
+    fn penmanship(descender_line: Option<LineStyleDef>) -> PenmanshipLineSet {
+        PenmanshipLineSet {
+            writing_height: Length::from_mm(20.0),
+            group_gap: Some(Length::from_mm(6.0)),
+            top_margin: Length::from_mm(29.4),
+            bottom_margin: Length::from_mm(20.0),
+            top_line: SOLID,
+            midline: DASHED,
+            baseline: SOLID,
+            descender_line,
+            descender_depth: None,
+            bounds: None
+        }
+    }
+
+    #[test]
+    fn groups_of_top_dashed_mid_and_baseline() {
+        // 230mm of room, 20mm groups every 26mm: 9 groups take 8 * 26 + 20 = 228mm
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_penmanship_lines(&penmanship(None), paper_size, &mut result).unwrap();
+
+        assert_eq!(result.len(), 9 * 3);
+        let ys: Vec<f64> = result.iter().map(|l| l.start.y_coord(paper_size)).collect();
+        assert!((ys[0] - 250.0).abs() < 1e-9 && (ys[1] - 240.0).abs() < 1e-9 && (ys[2] - 230.0).abs() < 1e-9);
+        assert!((ys[3] - 224.0).abs() < 1e-9);
+        assert!(result[1].dash_pattern.is_some());
+        assert!(result[0].dash_pattern.is_none() && result[2].dash_pattern.is_none());
+    }
+
+    #[test]
+    fn descender_line_is_half_the_writing_height_below_baseline() {
+        // 30mm groups every 36mm: 7 groups take 6 * 36 + 30 = 246mm, more than 230mm, so only 6 fit
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<LineDef> = Vec::new();
+        create_penmanship_lines(&penmanship(Some(SOLID)), paper_size, &mut result).unwrap();
+
+        assert_eq!(result.len(), 6 * 4);
+        assert!((result[3].start.y_coord(paper_size) - 220.0).abs() < 1e-9);
+        assert!((result[4].start.y_coord(paper_size) - 214.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn descender_depth_can_be_given() {
+        // 32mm groups every 38mm: 6 groups take 5 * 38 + 32 = 222mm
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut line_set = penmanship(Some(SOLID));
+        line_set.descender_depth = Some(Length::from_mm(12.0));
+        let mut result: Vec<LineDef> = Vec::new();
+        create_penmanship_lines(&line_set, paper_size, &mut result).unwrap();
+
+        assert_eq!(result.len(), 6 * 4);
+        assert!((result[3].start.y_coord(paper_size) - 218.0).abs() < 1e-9);
+        assert!((result[4].start.y_coord(paper_size) - 212.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<PenmanshipLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|lines, _| lines.writing_height = Length::ZERO, Error::WritingHeightIsNotPositive(0.0)),
+            (|lines, _| lines.group_gap = Some(Length::from_mm(-1.0)), Error::GroupGapIsNegative(-1.0)),
+            (|lines, _| lines.top_margin = Length::from_mm(-1.0), Error::TopMarginIsNegative(-1.0)),
+            (|lines, _| lines.bottom_margin = Length::from_mm(-1.0), Error::BottomMarginIsNegative(-1.0)),
+            (|lines, _| lines.descender_depth = Some(Length::ZERO), Error::DescenderDepthIsNotPositive(0.0))
+        ];
+        assert_rejected(|| penmanship(Some(SOLID)), cases,
+            |line_set, paper_size| create_penmanship_lines(line_set, paper_size, &mut Vec::new()));
+    }
+}
@@ -0,0 +1,222 @@
+use crate::geometry_def::{LineDef, PointDef, PaperSize, PerspectiveLineSet};
+use crate::clipping::{Rect, EPSILON};
+use thiserror::Error;
+
+const MAX_VANISHING_POINTS: usize = 3;
+
+pub fn create_perspective_lines(line_set: &PerspectiveLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let num_points = line_set.vanishing_points.len();
+    if num_points == 0 || num_points > MAX_VANISHING_POINTS {
+        return Err(Error::WrongNumberOfVanishingPoints { actual: num_points, max: MAX_VANISHING_POINTS });
+    }
+
+    let fan = match (line_set.angle_step, line_set.edge_spacing) {
+        (Some(angle_step), None) => {
+            if angle_step <= 0.0 || angle_step > 360.0 {
+                return Err(Error::AngleStepIsOutOfRange { actual: angle_step, min: 0.0, max: 360.0 });
+            }
+            Fan::AngleStep(angle_step)
+        },
+        (None, Some(edge_spacing)) => {
+            let edge_spacing = edge_spacing.mm();
+            if edge_spacing <= 0.0 {
+                return Err(Error::EdgeSpacingIsNotPositive(edge_spacing));
+            }
+            Fan::EdgeSpacing(edge_spacing)
+        },
+        (Some(_), Some(_)) => return Err(Error::FanIsAmbiguous),
+        (None, None) => return Err(Error::FanIsMissing)
+    };
+
+    let page = Rect { left: 0.0, bottom: 0.0, right: paper_size.width, top: paper_size.height };
+
+    let mut add_line = |x0, y0, x1, y1, thickness, color, dash_pattern| {
+        result.push(LineDef {
+            start: PointDef::from_mm(x0, y0),
+            end: PointDef::from_mm(x1, y1),
+            thickness,
+            color,
+            dash_pattern
+        });
+    };
+
+    if let Some(horizon) = &line_set.horizon {
+        let y = horizon.y.resolve(paper_size.height);
+        if page.contains(0.0, y) {
+            add_line(0.0, y, paper_size.width, y, horizon.thickness, horizon.color, horizon.dash_pattern);
+        }
+    }
+
+    for point in line_set.vanishing_points.iter() {
+        let (vx, vy) = (point.x_coord(paper_size), point.y_coord(paper_size));
+        for (x, y) in ray_ends(fan, vx, vy, &page) {
+            if let Some((x0, y0, x1, y1)) = page.clip_segment(vx, vy, x, y) {
+                add_line(x0, y0, x1, y1, line_set.thickness, line_set.color, line_set.dash_pattern);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Copy, Clone)]
+enum Fan {
+    AngleStep(f64),
+    EdgeSpacing(f64)
+}
+
+/// Far ends of the rays coming out of the vanishing point. Each ray runs from the vanishing point to its end.
+fn ray_ends(fan: Fan, vx: f64, vy: f64, page: &Rect) -> Vec<(f64, f64)> {
+    match fan {
+        Fan::AngleStep(angle_step) => {
+            // Long enough to get past the page from anywhere
+            let length = [(page.left, page.bottom), (page.right, page.bottom), (page.left, page.top), (page.right, page.top)]
+                .iter()
+                .map(|(x, y)| (x - vx).hypot(y - vy))
+                .fold(0.0, f64::max) + 1.0;
+            let num_rays = (360.0 / angle_step - EPSILON).ceil() as u32;
+            (0..num_rays)
+                .map(|ray| {
+                    let (sin, cos) = (ray as f64 * angle_step).to_radians().sin_cos();
+                    (vx + length * cos, vy + length * sin)
+                })
+                .collect()
+        },
+        Fan::EdgeSpacing(edge_spacing) => {
+            // Points go around the page counter-clockwise from the bottom left corner. A ray through the page
+            // crosses two edges, so only points on the edges facing away from the vanishing point are used,
+            // which gives every ray once. If the point is on the page, all the edges face away from it.
+            let (width, height) = (page.width(), page.height());
+            let perimeter = 2.0 * (width + height);
+            let num_points = (perimeter / edge_spacing - EPSILON).ceil() as u32;
+            (0..num_points)
+                .filter_map(|index| {
+                    let along = index as f64 * edge_spacing;
+                    if along < width {
+                        Some((page.left + along, page.bottom)).filter(|_| vy > page.bottom)
+                    } else if along < width + height {
+                        Some((page.right, page.bottom + along - width)).filter(|_| vx < page.right)
+                    } else if along < 2.0 * width + height {
+                        Some((page.right - (along - width - height), page.top)).filter(|_| vy < page.top)
+                    } else {
+                        Some((page.left, page.top - (along - 2.0 * width - height))).filter(|_| vx > page.left)
+                    }
+                })
+                .collect()
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("A perspective grid needs 1 to {max} vanishing points, not {actual}.")]
+    WrongNumberOfVanishingPoints { actual: usize, max: usize },
+
+    #[error("Angle step of {actual} degrees is out of range. It must be more than {min} and at most {max}.")]
+    AngleStepIsOutOfRange { actual: f64, min: f64, max: f64 },
+
+    #[error("Edge spacing of {0} is not a positive number.")]
+    EdgeSpacingIsNotPositive(f64),
+
+    #[error("Rays must be given either by \"angle step deg\" or by \"edge spacing\", not both.")]
+    FanIsAmbiguous,
+
+    #[error("Rays are missing. They must be given either by \"angle step deg\" or by \"edge spacing\".")]
+    FanIsMissing,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::CmykDef;
+    use crate::geometry_def::length::Length;
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    fn perspective(vanishing_points: Vec<PointDef>, angle_step: Option<f64>, edge_spacing: Option<f64>) -> PerspectiveLineSet {
+        PerspectiveLineSet {
+            horizon: None,
+            vanishing_points,
+            angle_step,
+            edge_spacing: edge_spacing.map(Length::from_mm),
+            thickness: Length::ZERO,
+            color: CmykDef::BLACK,
+            dash_pattern: None,
+            bounds: None
+        }
+    }
+
+    fn passes_through(line: &LineDef, paper_size: &PaperSize, x: f64, y: f64) -> bool {
+        let (x0, y0) = (line.start.x_coord(paper_size), line.start.y_coord(paper_size));
+        let (x1, y1) = (line.end.x_coord(paper_size), line.end.y_coord(paper_size));
+        ((x1 - x0) * (y - y0) - (y1 - y0) * (x - x0)).abs() < 1e-6
+    }
+
+    #[test]
+    fn one_point_fan_by_angle() {
+        let paper_size = &PaperSize { width: 200.0, height: 100.0 };
+        let mut result: Vec<LineDef> = Vec::new();
+        create_perspective_lines(&perspective(vec![PointDef::from_mm(100.0, 50.0)], Some(15.0), None),
+            paper_size, &mut result).unwrap();
+
+        assert_eq!(result.len(), 24);
+        for line in result.iter() {
+            assert_eq!(line.start.x_coord(paper_size), 100.0);
+            assert_eq!(line.start.y_coord(paper_size), 50.0);
+        }
+    }
+
+    #[test]
+    fn vanishing_points_off_the_page() {
+        // Two-point perspective with both points on the horizon, far to the left and right of the page
+        let paper_size = &PaperSize { width: 200.0, height: 100.0 };
+        let points = vec![PointDef::from_mm(-300.0, 60.0), PointDef::from_mm(500.0, 60.0)];
+        let mut result: Vec<LineDef> = Vec::new();
+        create_perspective_lines(&perspective(points, None, Some(10.0)), paper_size, &mut result).unwrap();
+
+        // Each point uses the top, bottom and far side edges: 20 + 10 + 20 points,
+        // less the near corner, where the ray only touches the page
+        assert_eq!(result.len(), 2 * 49);
+        for (i, line) in result.iter().enumerate() {
+            let vx = if i < 49 { -300.0 } else { 500.0 };
+            assert!(passes_through(line, paper_size, vx, 60.0));
+            for (x, y) in &[(line.start.x_coord(paper_size), line.start.y_coord(paper_size)),
+                    (line.end.x_coord(paper_size), line.end.y_coord(paper_size))] {
+                assert!((0.0..=200.0).contains(x) && (0.0..=100.0).contains(y), "Line end ({}, {}) is off the page", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<PerspectiveLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|fan, _| fan.vanishing_points.clear(), Error::WrongNumberOfVanishingPoints { actual: 0, max: 3 }),
+            (|fan, _| fan.vanishing_points = vec![PointDef::from_mm(0.0, 0.0); 4],
+                Error::WrongNumberOfVanishingPoints { actual: 4, max: 3 }),
+            (|fan, _| fan.angle_step = Some(0.0), Error::AngleStepIsOutOfRange { actual: 0.0, min: 0.0, max: 360.0 }),
+            (|fan, _| fan.angle_step = Some(361.0), Error::AngleStepIsOutOfRange { actual: 361.0, min: 0.0, max: 360.0 }),
+            (|fan, _| { fan.angle_step = None; fan.edge_spacing = Some(Length::ZERO); }, Error::EdgeSpacingIsNotPositive(0.0)),
+            (|fan, _| fan.edge_spacing = Some(Length::from_mm(10.0)), Error::FanIsAmbiguous),
+            (|fan, _| fan.angle_step = None, Error::FanIsMissing)
+        ];
+        assert_rejected(|| perspective(vec![PointDef::from_mm(100.0, 140.0)], Some(10.0), None), cases,
+            |line_set, paper_size| create_perspective_lines(line_set, paper_size, &mut Vec::new()));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::geometry_def::{LineDef, ArcDef, PointDef, PaperSize, PolarGridLineSet, Shape};
+use crate::geometry_def::length::Length;
+use crate::clipping::{self, Rect, EPSILON};
+use thiserror::Error;
+
+pub fn create_polar_grid(line_set: &PolarGridLineSet, paper_size: &PaperSize, result: &mut Vec<Shape>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let ring_spacing = line_set.ring_spacing.mm();
+    if ring_spacing <= 0.0 {
+        return Err(Error::RingSpacingIsNotPositive(ring_spacing));
+    }
+
+    if line_set.radial_step <= 0.0 || line_set.radial_step > 360.0 {
+        return Err(Error::RadialStepIsOutOfRange { actual: line_set.radial_step, min: 0.0, max: 360.0 });
+    }
+
+    if let Some(major_rings) = &line_set.major_rings {
+        if major_rings.every == 0 {
+            return Err(Error::MajorRingsEveryIsZero);
+        }
+    }
+
+    let cx = line_set.center.x_coord(paper_size);
+    let cy = line_set.center.y_coord(paper_size);
+    let page = Rect { left: 0.0, bottom: 0.0, right: paper_size.width, top: paper_size.height };
+
+    let outer_radius = match line_set.outer_radius {
+        Some(outer_radius) => outer_radius.mm(),
+        None => [(0.0, 0.0), (page.right, 0.0), (0.0, page.top), (page.right, page.top)].iter()
+            .map(|(x, y)| (x - cx).hypot(y - cy))
+            .fold(0.0, f64::max)
+    };
+    if outer_radius <= 0.0 {
+        return Err(Error::OuterRadiusIsNotPositive(outer_radius));
+    }
+
+    let num_rings = ((outer_radius + EPSILON) / ring_spacing).floor() as u32;
+    for ring in 1..=num_rings {
+        let (thickness, color) = match &line_set.major_rings {
+            Some(major_rings) if ring % major_rings.every == 0 =>
+                (major_rings.thickness, major_rings.color.unwrap_or(line_set.color)),
+            _ => (line_set.thickness, line_set.color)
+        };
+
+        let circle = ArcDef {
+            center: line_set.center,
+            radius: Length::from_mm(ring as f64 * ring_spacing),
+            start_angle: 0.0,
+            end_angle: 360.0,
+            thickness,
+            color,
+            dash_pattern: line_set.dash_pattern
+        };
+        result.extend(clipping::clip_arc(&circle, &page, paper_size).into_iter().map(Shape::Arc));
+    }
+
+    let num_radials = (360.0 / line_set.radial_step - EPSILON).ceil() as u32;
+    for radial in 0..num_radials {
+        let (sin, cos) = (radial as f64 * line_set.radial_step).to_radians().sin_cos();
+        let clipped = page.clip_segment(cx, cy, cx + outer_radius * cos, cy + outer_radius * sin);
+        if let Some((x0, y0, x1, y1)) = clipped {
+            result.push(Shape::Line(LineDef {
+                start: PointDef::from_mm(x0, y0),
+                end: PointDef::from_mm(x1, y1),
+                thickness: line_set.thickness,
+                color: line_set.color,
+                dash_pattern: line_set.dash_pattern
+            }));
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Ring spacing of {0} is not a positive number.")]
+    RingSpacingIsNotPositive(f64),
+
+    #[error("Outer radius of {0} is not a positive number.")]
+    OuterRadiusIsNotPositive(f64),
+
+    #[error("Radial line step of {actual} degrees is out of range. It must be more than {min} and at most {max}.")]
+    RadialStepIsOutOfRange { actual: f64, min: f64, max: f64 },
+
+    #[error("Major rings must be every 1 or more rings.")]
+    MajorRingsEveryIsZero,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::{CmykDef, MajorRingsDef};
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    fn polar_grid() -> PolarGridLineSet {
+        PolarGridLineSet {
+            center: PointDef::from_mm(100.0, 100.0),
+            ring_spacing: Length::from_mm(10.0),
+            outer_radius: Some(Length::from_mm(50.0)),
+            radial_step: 30.0,
+            thickness: Length::ZERO,
+            color: CmykDef::BLACK,
+            dash_pattern: None,
+            major_rings: Some(MajorRingsDef { every: 2, thickness: Length::from_mm(0.5), color: None }),
+            bounds: None
+        }
+    }
+
+    #[test]
+    fn rings_and_radial_lines_inside_the_page() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut result: Vec<Shape> = Vec::new();
+        create_polar_grid(&polar_grid(), paper_size, &mut result).unwrap();
+
+        let rings: Vec<&ArcDef> = result.iter().filter_map(|s| if let Shape::Arc(a) = s { Some(a) } else { None }).collect();
+        let radials: Vec<&LineDef> = result.iter().filter_map(|s| if let Shape::Line(l) = s { Some(l) } else { None }).collect();
+        assert_eq!(rings.len(), 5);
+        assert_eq!(radials.len(), 12);
+
+        for (i, ring) in rings.iter().enumerate() {
+            assert_eq!(ring.radius.mm(), 10.0 * (i + 1) as f64);
+            assert_eq!((ring.start_angle, ring.end_angle), (0.0, 360.0));
+            let expected_thickness = if (i + 1) % 2 == 0 { 0.5 } else { 0.0 };
+            assert_eq!(ring.thickness.mm(), expected_thickness);
+        }
+
+        for line in radials.iter() {
+            let length = (line.end.x_coord(paper_size) - 100.0).hypot(line.end.y_coord(paper_size) - 100.0);
+            assert!((length - 50.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn rings_are_cut_at_the_paper_edge() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut line_set = polar_grid();
+        line_set.center = PointDef::from_mm(0.0, 100.0);
+        let mut result: Vec<Shape> = Vec::new();
+        create_polar_grid(&line_set, paper_size, &mut result).unwrap();
+
+        for shape in result.iter() {
+            if let Shape::Arc(arc) = shape {
+                assert!((arc.start_angle + 90.0).abs() < 1e-9 && (arc.end_angle - 90.0).abs() < 1e-9,
+                    "Ring is from {} to {} degrees", arc.start_angle, arc.end_angle);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<PolarGridLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|grid, _| grid.ring_spacing = Length::ZERO, Error::RingSpacingIsNotPositive(0.0)),
+            (|grid, _| grid.radial_step = 0.0, Error::RadialStepIsOutOfRange { actual: 0.0, min: 0.0, max: 360.0 }),
+            (|grid, _| grid.radial_step = 400.0, Error::RadialStepIsOutOfRange { actual: 400.0, min: 0.0, max: 360.0 }),
+            (|grid, _| grid.major_rings.as_mut().unwrap().every = 0, Error::MajorRingsEveryIsZero),
+            (|grid, _| grid.outer_radius = Some(Length::ZERO), Error::OuterRadiusIsNotPositive(0.0))
+        ];
+        assert_rejected(polar_grid, cases,
+            |line_set, paper_size| create_polar_grid(line_set, paper_size, &mut Vec::new()));
+    }
+}
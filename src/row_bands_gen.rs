@@ -0,0 +1,157 @@
+use crate::geometry_def::{PaperSize, RectangleDef, RowBandsLineSet, Shape, ShadedRows};
+use crate::geometry_def::coord::Coord;
+use crate::geometry_def::length::Length;
+use crate::clipping::EPSILON;
+use thiserror::Error;
+
+pub fn create_row_bands(line_set: &RowBandsLineSet, paper_size: &PaperSize, result: &mut Vec<Shape>)
+    -> Result<(), Error>
+{
+    if paper_size.width <= 0.0 {
+        return Err(Error::PaperWidthIsNotPositive(paper_size.width));
+    }
+
+    if paper_size.height <= 0.0 {
+        return Err(Error::PaperHeightIsNotPositive(paper_size.height));
+    }
+
+    let row_height = line_set.row_height.mm();
+    if row_height <= 0.0 {
+        return Err(Error::RowHeightIsNotPositive(row_height));
+    }
+
+    let top_margin = line_set.top_margin.mm();
+    if top_margin < 0.0 {
+        return Err(Error::TopMarginIsNegative(top_margin));
+    }
+
+    let bottom_margin = line_set.bottom_margin.mm();
+    if bottom_margin < 0.0 {
+        return Err(Error::BottomMarginIsNegative(bottom_margin));
+    }
+
+    let left_margin = line_set.left_margin.unwrap_or(Length::ZERO);
+    if left_margin.mm() < 0.0 {
+        return Err(Error::LeftMarginIsNegative(left_margin.mm()));
+    }
+
+    let right_margin = line_set.right_margin.unwrap_or(Length::ZERO);
+    if right_margin.mm() < 0.0 {
+        return Err(Error::RightMarginIsNegative(right_margin.mm()));
+    }
+
+    let top = paper_size.height - top_margin;
+    let num_rows = ((top - bottom_margin + EPSILON) / row_height).floor().max(0.0) as u32;
+    let first_shaded = match line_set.shaded_rows.unwrap_or(ShadedRows::Odd) {
+        ShadedRows::Odd => 0,
+        ShadedRows::Even => 1
+    };
+
+    for row in (first_shaded..num_rows).step_by(2) {
+        let row_top = top - row as f64 * row_height;
+        result.push(Shape::Rectangle(RectangleDef {
+            left: Coord::OffZero(left_margin),
+            bottom: Coord::OffZero(Length::from_mm(row_top - row_height)),
+            right: Coord::OffFarEdge(right_margin),
+            top: Coord::OffZero(Length::from_mm(row_top)),
+            corner_radius: None,
+            stroke: None,
+            fill: Some(line_set.fill)
+        }));
+    }
+
+    Ok(())
+}
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Paper width of {0} is not a positive number.")]
+    PaperWidthIsNotPositive(f64),
+
+    #[error("Paper height of {0} is not a positive number.")]
+    PaperHeightIsNotPositive(f64),
+
+    #[error("Row height of {0} is not a positive number.")]
+    RowHeightIsNotPositive(f64),
+
+    #[error("Top margin of {0} is a negative number.")]
+    TopMarginIsNegative(f64),
+
+    #[error("Bottom margin of {0} is a negative number.")]
+    BottomMarginIsNegative(f64),
+
+    #[error("Left margin of {0} is a negative number.")]
+    LeftMarginIsNegative(f64),
+
+    #[error("Right margin of {0} is a negative number.")]
+    RightMarginIsNegative(f64),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry_def::CmykDef;
+    use crate::test_support::{assert_rejected, InvalidCase};
+
+    fn row_bands(shaded_rows: Option<ShadedRows>) -> RowBandsLineSet {
+        RowBandsLineSet {
+            row_height: Length::from_mm(10.0),
+            top_margin: Length::from_mm(29.4),
+            bottom_margin: Length::from_mm(20.0),
+            left_margin: Some(Length::from_mm(15.0)),
+            right_margin: None,
+            shaded_rows,
+            fill: CmykDef::BLACK,
+            bounds: None
+        }
+    }
+
+    fn row_edges(shape: &Shape) -> (f64, f64, f64, f64) {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        match shape {
+            Shape::Rectangle(rect) => (
+                rect.left.resolve(paper_size.width), rect.bottom.resolve(paper_size.height),
+                rect.right.resolve(paper_size.width), rect.top.resolve(paper_size.height)),
+            _ => panic!("Expecting a rectangle.")
+        }
+    }
+
+    #[test]
+    fn odd_rows_are_shaded_by_default() {
+        // 230mm of room fits 23 rows, 12 of them odd
+        let mut result: Vec<Shape> = Vec::new();
+        create_row_bands(&row_bands(None), &PaperSize::LETTER_PORTRAIT, &mut result).unwrap();
+
+        assert_eq!(result.len(), 12);
+        let (left, bottom, right, top) = row_edges(&result[0]);
+        assert_eq!((left, right), (15.0, 215.9));
+        assert!((top - 250.0).abs() < 1e-9 && (bottom - 240.0).abs() < 1e-9);
+        let (_, bottom, _, top) = row_edges(&result[11]);
+        assert!((top - 30.0).abs() < 1e-9 && (bottom - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn even_rows_start_one_row_down() {
+        let mut result: Vec<Shape> = Vec::new();
+        create_row_bands(&row_bands(Some(ShadedRows::Even)), &PaperSize::LETTER_PORTRAIT, &mut result).unwrap();
+
+        assert_eq!(result.len(), 11);
+        let (_, _, _, top) = row_edges(&result[0]);
+        assert!((top - 240.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<RowBandsLineSet, Error>] = &[
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0)),
+            (|rows, _| rows.row_height = Length::ZERO, Error::RowHeightIsNotPositive(0.0)),
+            (|rows, _| rows.top_margin = Length::from_mm(-1.0), Error::TopMarginIsNegative(-1.0)),
+            (|rows, _| rows.bottom_margin = Length::from_mm(-1.0), Error::BottomMarginIsNegative(-1.0)),
+            (|rows, _| rows.left_margin = Some(Length::from_mm(-1.0)), Error::LeftMarginIsNegative(-1.0)),
+            (|rows, _| rows.right_margin = Some(Length::from_mm(-1.0)), Error::RightMarginIsNegative(-1.0))
+        ];
+        assert_rejected(|| row_bands(None), cases,
+            |line_set, paper_size| create_row_bands(line_set, paper_size, &mut Vec::new()));
+    }
+}
@@ -1,10 +1,39 @@
-use crate::geometry_def::{LineDef, PointDef, PaperSize, SeyesLineSet, CmykDef};
+use crate::geometry_def::{LineDef, PointDef, PaperSize, SeyesLineSet};
 use crate::geometry_def::coord::Coord;
+use crate::geometry_def::length::Length;
 use thiserror::Error;
 
 pub fn create_seyes_lines(line_set: &SeyesLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
     -> Result<(), Error>
 {
+    for (y, is_base) in line_positions(line_set, paper_size)? {
+        let (thickness, color) = if is_base {
+            (line_set.base_thickness, line_set.base_color)
+        } else {
+            (line_set.aux_thickness, line_set.aux_color)
+        };
+        result.push(LineDef {
+            start: PointDef { x: Coord::OffZero(Length::ZERO), y: Coord::OffZero(Length::from_mm(y)) },
+            end: PointDef { x: Coord::OffFarEdge(Length::ZERO), y: Coord::OffZero(Length::from_mm(y)) },
+            thickness,
+            color,
+            dash_pattern: None
+        });
+    }
+
+    Ok(())
+}
+
+/// Y coordinates of the base lines, the ones that letters stand on.
+pub fn base_line_ys(line_set: &SeyesLineSet, paper_size: &PaperSize) -> Result<Vec<f64>, Error> {
+    Ok(line_positions(line_set, paper_size)?.into_iter()
+        .filter(|(_, is_base)| *is_base)
+        .map(|(y, _)| y)
+        .collect())
+}
+
+/// Y coordinates of all the lines from the top down, each with a flag telling whether it's a base line.
+fn line_positions(line_set: &SeyesLineSet, paper_size: &PaperSize) -> Result<Vec<(f64, bool)>, Error> {
     if paper_size.width <= 0.0 {
         return Err(Error::PaperWidthIsNotPositive(paper_size.width));
     }
@@ -13,52 +42,48 @@ pub fn create_seyes_lines(line_set: &SeyesLineSet, paper_size: &PaperSize, resul
         return Err(Error::PaperHeightIsNotPositive(paper_size.height));
     }
 
-    if line_set.y_spacing <= 0.0 {
-        return Err(Error::YSpacingIsNotPositive(line_set.y_spacing));
+    let y_spacing = line_set.y_spacing.mm();
+    if y_spacing <= 0.0 {
+        return Err(Error::YSpacingIsNotPositive(y_spacing));
     }
 
-    if line_set.top_margin <= 0.0 {
-        return Err(Error::TopMarginIsNotPositive(line_set.top_margin));
+    let top_margin = line_set.top_margin.mm();
+    if top_margin <= 0.0 {
+        return Err(Error::TopMarginIsNotPositive(top_margin));
     }
 
-    if line_set.bottom_margin <= 0.0 {
-        return Err(Error::BottomMarginIsNotPositive(line_set.bottom_margin));
+    let bottom_margin = line_set.bottom_margin.mm();
+    if bottom_margin <= 0.0 {
+        return Err(Error::BottomMarginIsNotPositive(bottom_margin));
     }
 
-    let mut add_line = |y, thickness, color: &CmykDef| {
-        result.push(LineDef {
-            start: PointDef { x: Coord::OffZero(0.0), y: Coord::OffZero(y) },
-            end: PointDef { x: Coord::OffFarEdge(0.0), y: Coord::OffZero(y) },
-            thickness: thickness,
-            color: *color,
-            dash_pattern: None
-        });
-    };
+    let mut result = Vec::new();
 
-    let mut y = paper_size.height - line_set.top_margin;
-    if y >= line_set.bottom_margin {
-        add_line(y, line_set.aux_thickness, &line_set.aux_color);
+    let mut y = paper_size.height - top_margin;
+    if y >= bottom_margin {
+        result.push((y, false));
     }
-    y -= line_set.y_spacing;
-    if y >= line_set.bottom_margin {
-        add_line(y, line_set.aux_thickness, &line_set.aux_color);
+    y -= y_spacing;
+    if y >= bottom_margin {
+        result.push((y, false));
     }
 
-    while y - (4.0 * line_set.y_spacing) >= line_set.bottom_margin {
-        y -= line_set.y_spacing;
-        add_line(y, line_set.aux_thickness, &line_set.aux_color);
-        y -= line_set.y_spacing;
-        add_line(y, line_set.base_thickness, &line_set.base_color);
-        y -= line_set.y_spacing;
-        add_line(y, line_set.aux_thickness, &line_set.aux_color);
-        y -= line_set.y_spacing;
-        add_line(y, line_set.aux_thickness, &line_set.aux_color);
+    while y - (4.0 * y_spacing) >= bottom_margin {
+        y -= y_spacing;
+        result.push((y, false));
+        y -= y_spacing;
+        result.push((y, true));
+        y -= y_spacing;
+        result.push((y, false));
+        y -= y_spacing;
+        result.push((y, false));
     }
 
-    Ok(())
+    Ok(result)
 }
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("Paper width of {0} is not a positive number.")]
     PaperWidthIsNotPositive(f64),
@@ -1,17 +1,21 @@
-use crate::geometry_def::{LineDef, PointDef, PaperSize, SlantLineSet};
+use crate::geometry_def::{LineDef, PointDef, PaperSize, SlantLineSet, BandsDef, LineSet};
 use crate::geometry_def::coord::Coord;
+use crate::geometry_def::length::Length;
+use crate::clipping::Rect;
+use crate::{horizontal_lines_gen, seyes_lines_gen};
 use thiserror::Error;
 
 pub fn create_slant_lines(line_set: &SlantLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
     -> Result<(), Error>
 {
 
-    if line_set.slant_angle < 45.0 && line_set.slant_angle > 90.0 {
+    if !(45.0..=90.0).contains(&line_set.slant_angle) {
         return Err(Error::SlantAngleIsOutOfRange {actual: line_set.slant_angle, min: 45.0, max: 90.0});
     }
 
-    if line_set.x_spacing <= 0.0 {
-        return Err(Error::LineSpacingIsNotPositive(line_set.x_spacing));
+    let x_spacing = line_set.x_spacing.mm();
+    if x_spacing <= 0.0 {
+        return Err(Error::LineSpacingIsNotPositive(x_spacing));
     }
 
     if paper_size.width <= 0.0 {
@@ -26,13 +30,13 @@ pub fn create_slant_lines(line_set: &SlantLineSet, paper_size: &PaperSize, resul
     let tan_a = slant_angle.tan();
 
     // First line starts at the left size, on upper edge
-    let mut x0 = line_set.x_spacing;
+    let mut x0 = x_spacing;
     let mut y0 = paper_size.height;
 
     let mut add_line_to_result = |x0, y0, x1, y1| {
         result.push(LineDef {
-            start: PointDef {x: Coord::OffZero(x0), y: Coord::OffZero(y0)},
-            end: PointDef {x: Coord::OffZero(x1), y: Coord::OffZero(y1)},
+            start: PointDef {x: Coord::OffZero(Length::from_mm(x0)), y: Coord::OffZero(Length::from_mm(y0))},
+            end: PointDef {x: Coord::OffZero(Length::from_mm(x1)), y: Coord::OffZero(Length::from_mm(y1))},
             thickness: line_set.thickness,
             color: line_set.color,
             dash_pattern: None
@@ -55,10 +59,10 @@ pub fn create_slant_lines(line_set: &SlantLineSet, paper_size: &PaperSize, resul
         }
   
         add_line_to_result(x0, y0, x1, y1);
-        x0 += line_set.x_spacing;     
+        x0 += x_spacing;     
     }
 
-    let line_y_spacing = line_set.x_spacing * tan_a;
+    let line_y_spacing = x_spacing * tan_a;
     assert!(line_y_spacing > 0.0);
 
     // Advance the starting point down the right edge until past the lower edge:
@@ -82,7 +86,76 @@ pub fn create_slant_lines(line_set: &SlantLineSet, paper_size: &PaperSize, resul
     Ok(())
 }
 
+/// Works out the bands as (bottom, top) pairs of Y coordinates, from the top of the page down.
+/// Bands that overlap are merged, so that no part of a slant line is drawn twice.
+/// The line sets are all the line sets in the file, so that bands can follow the lines of one of them.
+pub fn resolve_bands(bands: &BandsDef, line_sets: &[LineSet], paper_size: &PaperSize)
+    -> Result<Vec<(f64, f64)>, Error>
+{
+    let bands: Vec<(f64, f64)> = match bands {
+        BandsDef::List(bands) => bands.iter()
+            .map(|band| {
+                let bottom = band.bottom.resolve(paper_size.height);
+                let top = band.top.resolve(paper_size.height);
+                if top <= bottom {
+                    return Err(Error::BandIsEmpty { bottom, top });
+                }
+                Ok((bottom, top))
+            })
+            .collect::<Result<_, _>>()?,
+        BandsDef::FromLineSet { line_set, height } => {
+            let height = height.mm();
+            if height <= 0.0 {
+                return Err(Error::BandHeightIsNotPositive(height));
+            }
+
+            let line_ys = match line_set.checked_sub(1).and_then(|index| line_sets.get(index)) {
+                Some(LineSet::HorizontalLines(horiz_lines)) => horizontal_lines_gen::line_ys(horiz_lines, paper_size)?,
+                Some(LineSet::Seyes(seyes_lines)) => seyes_lines_gen::base_line_ys(seyes_lines, paper_size)?,
+                Some(_) => return Err(Error::BandLineSetIsNotHorizontal(*line_set)),
+                None => return Err(Error::BandLineSetNotFound(*line_set))
+            };
+            line_ys.into_iter().map(|y| (y, y + height)).collect()
+        }
+    };
+    Ok(merge_bands(bands))
+}
+
+fn merge_bands(mut bands: Vec<(f64, f64)>) -> Vec<(f64, f64)> {
+    bands.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let mut merged: Vec<(f64, f64)> = Vec::with_capacity(bands.len());
+    for (bottom, top) in bands {
+        match merged.last_mut() {
+            Some(last) if top >= last.0 => last.0 = last.0.min(bottom),
+            _ => merged.push((bottom, top))
+        }
+    }
+    merged
+}
+
+/// Cuts the lines at the bottom and top of each band, and drops what's outside all the bands.
+pub fn confine_to_bands(lines: Vec<LineDef>, bands: &[(f64, f64)], paper_size: &PaperSize) -> Vec<LineDef> {
+    let mut result = Vec::new();
+    for (bottom, top) in bands.iter() {
+        let band = Rect { left: 0.0, bottom: *bottom, right: paper_size.width, top: *top };
+        for line in lines.iter() {
+            let clipped = band.clip_segment(
+                line.start.x_coord(paper_size), line.start.y_coord(paper_size),
+                line.end.x_coord(paper_size), line.end.y_coord(paper_size));
+            if let Some((x0, y0, x1, y1)) = clipped {
+                result.push(LineDef {
+                    start: PointDef::from_mm(x0, y0),
+                    end: PointDef::from_mm(x1, y1),
+                    ..line.clone()
+                });
+            }
+        }
+    }
+    result
+}
+
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("Slant angle of {actual} is out of range. It must be between {min} and {max}.")]
     SlantAngleIsOutOfRange {actual: f64, min: f64, max: f64},
@@ -95,27 +168,51 @@ pub enum Error {
 
     #[error("Paper height of {0} is not a positive number.")]
     PaperHeightIsNotPositive(f64),
+
+    #[error("Band from {bottom} to {top} is empty. Its top must be above its bottom.")]
+    BandIsEmpty { bottom: f64, top: f64 },
+
+    #[error("Band height of {0} is not a positive number.")]
+    BandHeightIsNotPositive(f64),
+
+    #[error("Bands follow line set number {0}, but there is no such line set.")]
+    BandLineSetNotFound(usize),
+
+    #[error("Bands follow line set number {0}, which is not a horizontal lines or Seyes line set.")]
+    BandLineSetIsNotHorizontal(usize),
+
+    #[error("Horizontal lines generation error in the band line set.")]
+    HorizontalLinesGenError(#[from] horizontal_lines_gen::Error),
+
+    #[error("Seyes lines generation error in the band line set.")]
+    SeyesLinesGenError(#[from] seyes_lines_gen::Error),
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::geometry_def::CmykDef;
+    use crate::geometry_def::{BandDef, SeyesLineSet};
+    use crate::geometry_def::length::Unit;
+    use crate::test_support::{assert_rejected, InvalidCase};
 
     const LINE_X_SPACING: f64 = 5.0;
 
+    fn slant_lines(slant_angle: f64) -> SlantLineSet {
+        SlantLineSet { slant_angle, x_spacing: Length::from_mm(LINE_X_SPACING), thickness: Length::new(0.4, Unit::Pt), color: CmykDef::BLACK, bands: None, bounds: None}
+    }
 
     #[test]
     fn line_up_high_slant_in_portrait_mode() {
         let paper_size = &PaperSize::LETTER_PORTRAIT;
         let mut result: Vec<LineDef> = Vec::new();
         create_slant_lines(
-            &SlantLineSet { slant_angle: 46.0, x_spacing: LINE_X_SPACING, thickness: 0.4, color: CmykDef::BLACK},
+            &SlantLineSet { slant_angle: 46.0, x_spacing: Length::from_mm(LINE_X_SPACING), thickness: Length::new(0.4, Unit::Pt), color: CmykDef::BLACK, bands: None, bounds: None},
             paper_size, &mut result)
             .unwrap();
         assert!(result.len() > 2);
-        assert_eq!(result[0].end.x_coord(&paper_size), 0.0);
-        assert_eq!(result.last().unwrap().end.y_coord(&paper_size), 0.0);
+        assert_eq!(result[0].end.x_coord(paper_size), 0.0);
+        assert_eq!(result.last().unwrap().end.y_coord(paper_size), 0.0);
         for line in result.iter() {
             check_line(line, paper_size);
         }
@@ -126,27 +223,131 @@ mod tests {
         let paper_size = &PaperSize::LETTER_LANDSCAPE;
         let mut result: Vec<LineDef> = Vec::new();
         create_slant_lines(
-            &SlantLineSet { slant_angle: 60.0, x_spacing: LINE_X_SPACING, thickness: 0.4, color: CmykDef::BLACK},
+            &SlantLineSet { slant_angle: 60.0, x_spacing: Length::from_mm(LINE_X_SPACING), thickness: Length::new(0.4, Unit::Pt), color: CmykDef::BLACK, bands: None, bounds: None},
             paper_size, &mut result)
             .unwrap();
         assert!(result.len() > 2);
-        assert_eq!(result[0].end.x_coord(&paper_size), 0.0);
-        assert_eq!(result[result.len() / 2].start.y_coord(&paper_size), paper_size.height);
-        assert_eq!(result[result.len() / 2].end.y_coord(&paper_size), 0.0);
-        assert!(result[result.len() / 2].start.x_coord(&paper_size) > 0.0);
-        assert!(result[result.len() / 2].end.x_coord(&paper_size) > 0.0);
-        assert!(result[result.len() / 2].end.x_coord(&paper_size) < result[result.len() / 2].start.x_coord(&paper_size));
-        assert_eq!(result.last().unwrap().end.y_coord(&paper_size), 0.0);
+        assert_eq!(result[0].end.x_coord(paper_size), 0.0);
+        assert_eq!(result[result.len() / 2].start.y_coord(paper_size), paper_size.height);
+        assert_eq!(result[result.len() / 2].end.y_coord(paper_size), 0.0);
+        assert!(result[result.len() / 2].start.x_coord(paper_size) > 0.0);
+        assert!(result[result.len() / 2].end.x_coord(paper_size) > 0.0);
+        assert!(result[result.len() / 2].end.x_coord(paper_size) < result[result.len() / 2].start.x_coord(paper_size));
+        assert_eq!(result.last().unwrap().end.y_coord(paper_size), 0.0);
         for line in result.iter() {
             check_line(line, paper_size);
         }
     }
 
+    #[test]
+    fn invalid_values_are_rejected() {
+        let cases: &[InvalidCase<SlantLineSet, Error>] = &[
+            (|lines, _| lines.slant_angle = 30.0, Error::SlantAngleIsOutOfRange { actual: 30.0, min: 45.0, max: 90.0 }),
+            (|lines, _| lines.slant_angle = 100.0, Error::SlantAngleIsOutOfRange { actual: 100.0, min: 45.0, max: 90.0 }),
+            (|lines, _| lines.x_spacing = Length::ZERO, Error::LineSpacingIsNotPositive(0.0)),
+            (|_, paper| paper.width = 0.0, Error::PaperWidthIsNotPositive(0.0)),
+            (|_, paper| paper.height = 0.0, Error::PaperHeightIsNotPositive(0.0))
+        ];
+        assert_rejected(|| slant_lines(60.0), cases,
+            |line_set, paper_size| create_slant_lines(line_set, paper_size, &mut Vec::new()));
+    }
+
+    #[test]
+    fn lines_are_cut_at_band_edges() {
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let mut lines: Vec<LineDef> = Vec::new();
+        create_slant_lines(
+            &SlantLineSet { slant_angle: 60.0, x_spacing: Length::from_mm(LINE_X_SPACING), thickness: Length::new(0.4, Unit::Pt), color: CmykDef::BLACK, bands: None, bounds: None},
+            paper_size, &mut lines)
+            .unwrap();
+
+        let bands = [(100.0, 110.0), (200.0, 205.0)];
+        let result = confine_to_bands(lines, &bands, paper_size);
+        assert!(!result.is_empty());
+        for line in result.iter() {
+            let (y0, y1) = (line.start.y_coord(paper_size), line.end.y_coord(paper_size));
+            let (bottom, top) = if y0 < 150.0 { bands[0] } else { bands[1] };
+            for y in &[y0, y1] {
+                assert!((bottom - 1e-9..=top + 1e-9).contains(y), "Line end at y {} is out of the band", y);
+            }
+        }
+    }
+
+    fn seyes_lines() -> SeyesLineSet {
+        SeyesLineSet {
+            y_spacing: Length::from_mm(2.0),
+            top_margin: Length::from_mm(30.0),
+            bottom_margin: Length::from_mm(20.0),
+            base_thickness: Length::ZERO,
+            base_color: CmykDef::BLACK,
+            aux_thickness: Length::ZERO,
+            aux_color: CmykDef::BLACK,
+            bounds: None
+        }
+    }
+
+    #[test]
+    fn bands_follow_seyes_base_lines() {
+        let seyes = seyes_lines();
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let base_line_ys = seyes_lines_gen::base_line_ys(&seyes, paper_size).unwrap();
+        let line_sets = vec![LineSet::Seyes(seyes)];
+
+        let bands = resolve_bands(&BandsDef::FromLineSet { line_set: 1, height: Length::from_mm(2.0) }, &line_sets, paper_size)
+            .unwrap();
+        assert_eq!(bands.len(), base_line_ys.len());
+        for ((bottom, top), y) in bands.iter().zip(base_line_ys.iter()) {
+            assert_eq!(bottom, y);
+            assert!((top - bottom - 2.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn invalid_bands_are_rejected() {
+        let line_sets = vec![LineSet::Seyes(seyes_lines()), LineSet::Slant(slant_lines(60.0))];
+        let cases: &[InvalidCase<BandsDef, Error>] = &[
+            (|bands, _| *bands = BandsDef::List(vec![BandDef { bottom: Coord::OffZero(Length::from_mm(100.0)), top: Coord::OffZero(Length::from_mm(100.0)) }]),
+                Error::BandIsEmpty { bottom: 100.0, top: 100.0 }),
+            (|bands, _| *bands = BandsDef::FromLineSet { line_set: 1, height: Length::ZERO }, Error::BandHeightIsNotPositive(0.0)),
+            (|bands, _| *bands = BandsDef::FromLineSet { line_set: 0, height: Length::from_mm(2.0) }, Error::BandLineSetNotFound(0)),
+            (|bands, _| *bands = BandsDef::FromLineSet { line_set: 3, height: Length::from_mm(2.0) }, Error::BandLineSetNotFound(3)),
+            (|bands, _| *bands = BandsDef::FromLineSet { line_set: 2, height: Length::from_mm(2.0) }, Error::BandLineSetIsNotHorizontal(2))
+        ];
+        assert_rejected(|| BandsDef::FromLineSet { line_set: 1, height: Length::from_mm(2.0) }, cases,
+            |bands, paper_size| resolve_bands(bands, &line_sets, paper_size).map(|_| ()));
+    }
+
+    #[test]
+    fn overlapping_bands_are_merged() {
+        use crate::geometry_def::HorizontalLineSet;
+
+        // 8mm bands above lines 6mm apart overlap into one band from the lowest line to 8mm above the highest
+        let horiz_lines = HorizontalLineSet {
+            y_spacing: Length::from_mm(6.0),
+            top_margin: Length::from_mm(30.0),
+            bottom_margin: Length::from_mm(20.0),
+            thickness: Length::ZERO,
+            color: CmykDef::BLACK,
+            dash_pattern: None,
+            bounds: None
+        };
+        let paper_size = &PaperSize::LETTER_PORTRAIT;
+        let line_ys = horizontal_lines_gen::line_ys(&horiz_lines, paper_size).unwrap();
+        let line_sets = vec![LineSet::HorizontalLines(horiz_lines)];
+
+        let bands = resolve_bands(&BandsDef::FromLineSet { line_set: 1, height: Length::from_mm(8.0) }, &line_sets, paper_size)
+            .unwrap();
+        assert_eq!(bands.len(), 1);
+        assert_eq!(bands[0], (*line_ys.last().unwrap(), line_ys[0] + 8.0));
+
+        assert_eq!(merge_bands(vec![(10.0, 20.0), (50.0, 60.0), (15.0, 30.0)]), vec![(50.0, 60.0), (10.0, 30.0)]);
+    }
+
     fn check_line(line: &LineDef, paper_size: &PaperSize) {
-        let x0 = line.start.x_coord(&paper_size);
-        let y0 = line.start.y_coord(&paper_size);
-        let x1 = line.end.x_coord(&paper_size);
-        let y1 = line.end.y_coord(&paper_size);
+        let x0 = line.start.x_coord(paper_size);
+        let y0 = line.start.y_coord(paper_size);
+        let x1 = line.end.x_coord(paper_size);
+        let y1 = line.end.y_coord(paper_size);
         assert!(0.0 <= x0);
         assert!(x0 <= paper_size.width);
         assert!(0.0 <= x1);
@@ -0,0 +1,26 @@
+use crate::geometry_def::PaperSize;
+use std::fmt::Display;
+
+/// A way to make a valid line set, or the letter paper it is drawn on, invalid, and the error it should give.
+pub type InvalidCase<L, E> = (fn(&mut L, &mut PaperSize), E);
+
+/// Checks that the line set is accepted on letter paper, and that every case makes the generator fail
+/// with the expected error, compared by message so that the reported values are checked too.
+pub fn assert_rejected<L, E: Display>(valid: impl Fn() -> L, cases: &[InvalidCase<L, E>],
+    generate: impl Fn(&L, &PaperSize) -> Result<(), E>)
+{
+    let letter = || PaperSize { width: PaperSize::LETTER_PORTRAIT.width, height: PaperSize::LETTER_PORTRAIT.height };
+    if let Err(error) = generate(&valid(), &letter()) {
+        panic!("The valid line set should be accepted, but: {}", error);
+    }
+
+    for (make_invalid, expected) in cases {
+        let mut line_set = valid();
+        let mut paper_size = letter();
+        make_invalid(&mut line_set, &mut paper_size);
+        match generate(&line_set, &paper_size) {
+            Ok(()) => panic!("\"{}\" was expected, but the line set was accepted.", expected),
+            Err(error) => assert_eq!(error.to_string(), expected.to_string())
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use crate::geometry_def::{LineDef, PointDef, PaperSize, VerticalLineSet};
 use crate::geometry_def::coord::Coord;
+use crate::geometry_def::length::Length;
 use thiserror::Error;
 
 pub fn create_vertical_lines(line_set: &VerticalLineSet, paper_size: &PaperSize, result: &mut Vec<LineDef>)
@@ -13,35 +14,39 @@ pub fn create_vertical_lines(line_set: &VerticalLineSet, paper_size: &PaperSize,
         return Err(Error::PaperHeightIsNotPositive(paper_size.height));
     }
 
-    if line_set.x_spacing <= 0.0 {
-        return Err(Error::XSpacingIsNotPositive(line_set.x_spacing));
+    let x_spacing = line_set.x_spacing.mm();
+    if x_spacing <= 0.0 {
+        return Err(Error::XSpacingIsNotPositive(x_spacing));
     }
 
-    if line_set.left_margin <= 0.0 {
-        return Err(Error::LeftMarginIsNotPositive(line_set.left_margin));
+    let left_margin = line_set.left_margin.mm();
+    if left_margin <= 0.0 {
+        return Err(Error::LeftMarginIsNotPositive(left_margin));
     }
 
-    if line_set.right_margin <= 0.0 {
-        return Err(Error::RightMarginIsNotPositive(line_set.right_margin));
+    let right_margin = line_set.right_margin.mm();
+    if right_margin <= 0.0 {
+        return Err(Error::RightMarginIsNotPositive(right_margin));
     }
 
-    let mut x = line_set.left_margin;
+    let mut x = left_margin;
 
-    while x <= paper_size.width - line_set.right_margin {
+    while x <= paper_size.width - right_margin {
         result.push(LineDef {
-            start: PointDef { x: Coord::OffZero(x), y: Coord::OffZero(0.0) },
-            end: PointDef { x: Coord::OffZero(x), y: Coord::OffFarEdge(0.0) },
+            start: PointDef { x: Coord::OffZero(Length::from_mm(x)), y: Coord::OffZero(Length::ZERO) },
+            end: PointDef { x: Coord::OffZero(Length::from_mm(x)), y: Coord::OffFarEdge(Length::ZERO) },
             thickness: line_set.thickness,
             color: line_set.color,
             dash_pattern: line_set.dash_pattern
         });
-        x += line_set.x_spacing;
+        x += x_spacing;
     }
 
     Ok(())
 }
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error {
     #[error("Paper width of {0} is not a positive number.")]
     PaperWidthIsNotPositive(f64),